@@ -0,0 +1,3 @@
+-- Add up migration script here
+alter table Users
+add column is_archived boolean not null default false;
@@ -0,0 +1,6 @@
+-- Add down migration script here
+alter table Users
+drop column upper_limit;
+
+alter table Users
+drop column lower_limit;
@@ -0,0 +1,22 @@
+-- Add down migration script here
+create table UserCardNumberMapOld (
+  user_id integer not null,
+  card_number varchar(255) not null unique,
+  primary key (user_id),
+  foreign key (user_id) references Users (id)
+);
+
+-- only the first card of every user can be kept
+insert into UserCardNumberMapOld (user_id, card_number)
+select user_id, card_number
+from UserCardNumberMap
+where id in (
+  select min(id)
+  from UserCardNumberMap
+  group by user_id
+);
+
+drop table UserCardNumberMap;
+
+alter table UserCardNumberMapOld
+rename to UserCardNumberMap;
@@ -0,0 +1,18 @@
+-- Add up migration script here
+create table UserCardNumberMapNew (
+  id integer not null,
+  user_id integer not null,
+  card_number varchar(255) not null unique,
+  label varchar(255),
+  primary key (id),
+  foreign key (user_id) references Users (id)
+);
+
+insert into UserCardNumberMapNew (user_id, card_number)
+select user_id, card_number
+from UserCardNumberMap;
+
+drop table UserCardNumberMap;
+
+alter table UserCardNumberMapNew
+rename to UserCardNumberMap;
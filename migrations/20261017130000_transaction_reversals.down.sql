@@ -0,0 +1,25 @@
+-- Add down migration script here
+drop index TransactionsReversesId;
+
+alter table Transactions
+add column is_undone boolean not null default false;
+
+update Transactions
+set is_undone = true
+where id in (
+  select reverses_id
+  from Transactions
+  where reverses_id is not null
+);
+
+delete from Transactions
+where reverses_id is not null;
+
+alter table Transactions
+drop column undo_reason;
+
+alter table Transactions
+drop column undone_by;
+
+alter table Transactions
+drop column reverses_id;
@@ -0,0 +1,22 @@
+-- Add up migration script here
+alter table Transactions
+add column reverses_id integer references Transactions (id);
+
+alter table Transactions
+add column undone_by integer references Users (id);
+
+alter table Transactions
+add column undo_reason varchar(255);
+
+-- already undone transactions get a reversal, as the time of the undo is
+-- unknown the original timestamp is used
+insert into Transactions
+  (sender, receiver, t_type_data, money, description, timestamp, reverses_id)
+select sender, receiver, t_type_data, money, description, timestamp, id
+from Transactions
+where is_undone = true;
+
+alter table Transactions
+drop column is_undone;
+
+create unique index TransactionsReversesId on Transactions (reverses_id);
@@ -0,0 +1,4 @@
+-- Add up migration script here
+-- set for the adjustments booked when a balance didn't match the ledger
+alter table Transactions
+add column is_correction boolean not null default false;
@@ -0,0 +1,6 @@
+-- Add down migration script here
+drop table RecurringChargeBookings;
+
+drop table RecurringChargeUsers;
+
+drop table RecurringCharges;
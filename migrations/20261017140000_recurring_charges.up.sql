@@ -0,0 +1,37 @@
+-- Add up migration script here
+create table RecurringCharges (
+  id integer not null,
+  description text not null,
+  money integer not null,
+  receiver integer not null,
+  interval text not null,
+  start date not null,
+  is_active boolean not null default true,
+  primary key (id),
+  foreign key (receiver) references Users (id)
+);
+
+-- failure is set when booking the charge for the user failed, the charge
+-- isn't booked for the user again until an admin retries it
+create table RecurringChargeUsers (
+  charge_id integer not null,
+  user_id integer not null,
+  since date not null,
+  failure text,
+  failed_at datetime,
+  unique (charge_id, user_id),
+  foreign key (charge_id) references RecurringCharges (id),
+  foreign key (user_id) references Users (id)
+);
+
+-- period is the number of intervals since the start of the charge
+create table RecurringChargeBookings (
+  charge_id integer not null,
+  user_id integer not null,
+  period integer not null,
+  transaction_id integer not null,
+  unique (charge_id, user_id, period),
+  foreign key (charge_id) references RecurringCharges (id),
+  foreign key (user_id) references Users (id),
+  foreign key (transaction_id) references Transactions (id)
+);
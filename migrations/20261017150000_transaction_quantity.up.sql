@@ -0,0 +1,3 @@
+-- Add up migration script here
+alter table Transactions
+add column quantity integer not null default 1;
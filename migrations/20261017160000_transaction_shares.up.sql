@@ -0,0 +1,7 @@
+-- Add up migration script here
+create table TransactionShares (
+  transaction_id integer not null references Transactions (id),
+  user_id integer not null references Users (id),
+  delta integer not null,
+  primary key (transaction_id, user_id)
+);
@@ -0,0 +1,17 @@
+-- Add up migration script here
+create table MoneyRequests (
+  id integer not null,
+  requester integer not null,
+  debtor integer not null,
+  money integer not null,
+  description text,
+  status text not null default 'pending',
+  created_at datetime not null,
+  expires_at datetime not null,
+  resolved_at datetime,
+  transaction_id integer,
+  primary key (id),
+  foreign key (requester) references Users (id),
+  foreign key (debtor) references Users (id),
+  foreign key (transaction_id) references Transactions (id)
+);
@@ -0,0 +1,2 @@
+-- Add down migration script here
+-- the backfilled shares are kept, they are what the transactions booked
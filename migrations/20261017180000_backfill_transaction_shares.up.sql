@@ -0,0 +1,66 @@
+-- Add up migration script here
+
+-- Transactions booked before every transaction stored its shares were split
+-- equally between the members of the groups, members ordered by id. Each of
+-- the first `remainder + 1` members paid or received one more cent.
+--
+-- The member query had no `order by`; sqlite walked the (gid, uid) index, so
+-- the order was by id in practice but never guaranteed. If it differed, a
+-- remainder cent is attributed to the wrong member here and Users.money is
+-- off by that cent from the sum of the shares; the reconcile page in the
+-- admin area books a correction for such drift. Members are ordered by id
+-- from now on.
+insert into TransactionShares (transaction_id, user_id, delta)
+select transaction_id, user_id, sum(delta)
+from (
+  select
+    Transactions.id as transaction_id,
+    Members.uid as user_id,
+    -(Transactions.money / Members.size) - (
+      case
+        when Transactions.money % Members.size > 0
+          and Members.position <= Transactions.money % Members.size + 1 then 1
+        else 0
+      end
+    ) as delta
+  from Transactions
+  join (
+    select
+      gid,
+      uid,
+      row_number() over (partition by gid order by uid) as position,
+      count(*) over (partition by gid) as size
+    from UserGroupMap
+  ) as Members on Members.gid = Transactions.sender
+  where Transactions.reverses_id is null
+  union all
+  select
+    Transactions.id as transaction_id,
+    Members.uid as user_id,
+    Transactions.money / Members.size + (
+      case
+        when Transactions.money % Members.size > 0
+          and Members.position <= Transactions.money % Members.size + 1 then 1
+        else 0
+      end
+    ) as delta
+  from Transactions
+  join (
+    select
+      gid,
+      uid,
+      row_number() over (partition by gid order by uid) as position,
+      count(*) over (partition by gid) as size
+    from UserGroupMap
+  ) as Members on Members.gid = Transactions.receiver
+  where Transactions.reverses_id is null
+)
+where transaction_id not in (select transaction_id from TransactionShares)
+group by transaction_id, user_id;
+
+-- an undo reverted exactly what the undone transaction booked
+insert into TransactionShares (transaction_id, user_id, delta)
+select Reversals.id, TransactionShares.user_id, -TransactionShares.delta
+from Transactions as Reversals
+join TransactionShares on TransactionShares.transaction_id = Reversals.reverses_id
+where Reversals.id not in (select transaction_id from TransactionShares);
@@ -0,0 +1,4 @@
+-- Add down migration script here
+drop table CashClosings;
+drop table CashCountDenominations;
+drop table CashCounts;
@@ -0,0 +1,31 @@
+-- Add up migration script here
+create table CashCounts (
+  id integer not null,
+  counted_at datetime not null,
+  -- cash the box should contain according to the transactions, in cents
+  expected integer not null,
+  counted integer not null,
+  note text,
+  primary key (id)
+);
+
+create table CashCountDenominations (
+  count_id integer not null,
+  -- value of a coin or note in cents
+  denomination integer not null,
+  quantity integer not null,
+  primary key (count_id, denomination),
+  foreign key (count_id) references CashCounts (id)
+);
+
+create table CashClosings (
+  id integer not null,
+  closed_at datetime not null,
+  kind text not null,
+  opening integer not null,
+  deposits integer not null,
+  withdrawals integer not null,
+  differences integer not null,
+  closing integer not null,
+  primary key (id)
+);
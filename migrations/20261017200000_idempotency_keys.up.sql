@@ -0,0 +1,13 @@
+-- Add up migration script here
+-- params are the serialized parameters of the request, a key sent again with
+-- other parameters is rejected
+create table IdempotencyKeys (
+  key text not null,
+  user_id integer not null,
+  params text not null,
+  transaction_id integer not null,
+  created_at datetime not null,
+  primary key (key, user_id),
+  foreign key (user_id) references Users (id),
+  foreign key (transaction_id) references Transactions (id)
+);
@@ -0,0 +1,8 @@
+-- Add up migration script here
+create table BankImportLines (
+  import_id text not null,
+  transaction_id integer not null,
+  imported_at datetime not null,
+  primary key (import_id),
+  foreign key (transaction_id) references Transactions (id)
+);
@@ -0,0 +1,3 @@
+-- Add down migration script here
+alter table Articles
+drop column is_active;
@@ -0,0 +1,3 @@
+-- Add up migration script here
+alter table Articles
+add column is_active boolean not null default true;
@@ -0,0 +1,5 @@
+-- Add down migration script here
+drop table StockMovements;
+
+alter table Articles
+drop column stock;
@@ -0,0 +1,18 @@
+-- Add up migration script here
+-- number of units in stock, null while the stock of the article isn't tracked
+alter table Articles
+add column stock integer;
+
+create table StockMovements (
+  id integer not null,
+  article_id integer not null,
+  kind text not null,
+  -- units added to the stock, negative when a count found less
+  quantity integer not null,
+  -- price per unit in cents, only known for restocks
+  purchase_price integer,
+  moved_on date not null,
+  created_at datetime not null,
+  primary key (id),
+  foreign key (article_id) references Articles (id)
+);
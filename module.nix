@@ -66,6 +66,110 @@ in
         };
       };
 
+      pin = mkSubmoduleOption {
+        session_timeout = mkOption {
+          type = types.ints.positive;
+          default = 120;
+          description = "The time in seconds after which an entered PIN has to be entered again.";
+        };
+        max_attempts = mkOption {
+          type = types.ints.positive;
+          default = 5;
+          description = "The number of wrong PINs after which a user is locked out.";
+        };
+        lockout = mkOption {
+          type = types.ints.positive;
+          default = 60;
+          description = "The time in seconds a user is locked out after too many wrong PINs. It doubles for every further wrong PIN.";
+        };
+      };
+
+      undo = mkSubmoduleOption {
+        max_age = mkOption {
+          type = types.ints.unsigned;
+          default = 120;
+          description = "The time in seconds in which a transaction can be undone. If set to 0, it will be disabled.";
+        };
+
+        types = mkOption {
+          type = types.listOf (
+            types.enum [
+              "deposit"
+              "withdraw"
+              "bought"
+              "received"
+              "sent"
+              "sent_and_received"
+            ]
+          );
+          default = [
+            "deposit"
+            "withdraw"
+            "bought"
+            "received"
+            "sent"
+            "sent_and_received"
+          ];
+          description = "The kinds of transactions that can be undone";
+        };
+
+        last_n = mkOption {
+          type = types.ints.unsigned;
+          default = 0;
+          description = "Only the last n transactions of a user can be undone. If set to 0, it will be disabled.";
+        };
+      };
+
+      requests = mkSubmoduleOption {
+        expire_after = mkOption {
+          type = types.ints.positive;
+          default = 14;
+          description = "The number of days after which an unanswered money request expires.";
+        };
+      };
+
+      cash_box = mkSubmoduleOption {
+        daily_closing = mkOption {
+          type = types.bool;
+          default = true;
+          description = "Whether to close the cash box automatically every night.";
+        };
+      };
+
+      idempotency = mkSubmoduleOption {
+        window = mkOption {
+          type = types.ints.positive;
+          default = 86400;
+          description = "The number of seconds in which a retried booking with the same idempotency key returns the original transaction instead of booking again.";
+        };
+      };
+
+      bank = mkSubmoduleOption {
+        iban = mkOption {
+          type = types.str;
+          default = "";
+          description = "The IBAN of the club account that top-up QR codes transfer to. No QR codes are shown while it is empty.";
+        };
+        bic = mkOption {
+          type = types.str;
+          default = "";
+          description = "The BIC of the club account, optional within the EEA.";
+        };
+        name = mkOption {
+          type = types.str;
+          default = "";
+          description = "The holder of the club account.";
+        };
+      };
+
+      stock = mkSubmoduleOption {
+        allow_negative = mkOption {
+          type = types.bool;
+          default = true;
+          description = "Whether articles whose stock is tracked can still be bought when they are out of stock. The stock goes negative then and a warning is logged.";
+        };
+      };
+
       sounds = mkSubmoduleOption {
         failed = mkSoundListOption {
           description = "Sounds that play when a transaction fails";
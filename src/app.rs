@@ -50,6 +50,7 @@ pub fn App() -> impl IntoView {
             }>
                 <Route path=path!("/") view=routes::home::View />
                 <Route path=path!("/user/create") view=routes::user::Create />
+                <Route path=path!("/user/archived") view=routes::user::Archived />
                 <Route path=path!("/user/:id") view=routes::user::ShowUser />
                 <Route path=path!("/user/:id/settings") view=routes::user::settings::Show />
                 <Route
@@ -57,11 +58,17 @@ pub fn App() -> impl IntoView {
                     view=routes::user::extra_transactions::Show
                 />
                 <Route path=path!("/user/:id/send_money") view=routes::user::send_money::Show />
+                <Route
+                    path=path!("/user/:id/request_money")
+                    view=routes::user::request_money::Show
+                />
                 <Route path=path!("/articles") view=routes::articles::View />
                 <Route path=path!("/articles/create") view=routes::articles::Create />
                 <Route path=path!("/articles/:article_id") view=routes::articles::Edit />
 
                 <Route path=path!("/split_cost") view=routes::split_cost::Show />
+                <Route path=path!("/settle_up") view=routes::settle_up::Show />
+                <Route path=path!("/admin") view=routes::admin::Show />
             </Routes>
         </Router>
     }
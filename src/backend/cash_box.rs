@@ -0,0 +1,64 @@
+use std::time::Duration;
+
+use axum::{
+    http::{header, StatusCode},
+    response::IntoResponse,
+};
+use tracing::{error, info};
+
+use crate::{backend::ServerState, models::CashClosing};
+
+/// How often to check whether the day has to be closed.
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Makes the daily closing of the cash box for as long as the server runs.
+pub async fn close_cash_box_daily(state: ServerState) {
+    loop {
+        {
+            let db = state.db.lock().await;
+
+            match CashClosing::close_day(&db).await {
+                Ok(false) => {}
+                Ok(true) => info!("Made the daily closing of the cash box"),
+                Err(e) => error!("Failed to close the cash box: {}", e),
+            }
+        }
+
+        tokio::time::sleep(CHECK_INTERVAL).await;
+    }
+}
+
+/// Serves all closings of the cash box as a CSV file.
+pub async fn export_cash_closings(state: ServerState) -> impl IntoResponse {
+    let db = state.db.lock().await;
+
+    let closings = match db.get_conn().await {
+        Ok(mut conn) => CashClosing::get_latest(&mut *conn, 0).await,
+        Err(e) => Err(e),
+    };
+
+    match closings {
+        Ok(closings) => (
+            StatusCode::OK,
+            [
+                (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
+                (
+                    header::CONTENT_DISPOSITION,
+                    "attachment; filename=\"cash_closings.csv\"",
+                ),
+            ],
+            CashClosing::to_csv(&closings),
+        ),
+        Err(e) => {
+            error!("Failed to export cash closings: {e}");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                [
+                    (header::CONTENT_TYPE, "text/plain; charset=utf-8"),
+                    (header::CONTENT_DISPOSITION, "inline"),
+                ],
+                "Failed to export cash closings!".to_string(),
+            )
+        }
+    }
+}
@@ -1,6 +1,16 @@
+pub use cash_box::*;
+pub use reconcile::*;
+pub use recurring::*;
+pub use session::*;
 pub use settings::*;
+pub use sounds::*;
 pub use state::*;
 
+mod cash_box;
 pub mod db;
+mod reconcile;
+mod recurring;
+mod session;
 mod settings;
+mod sounds;
 mod state;
@@ -0,0 +1,51 @@
+use crate::{backend::db::DB, models::BalanceDrift};
+
+/// Prints all users whose stored balance drifts from the transactions and
+/// corrects them if `fix` is set. Returns whether all balances match in the end.
+pub async fn reconcile(db: &DB, fix: bool) -> Result<bool, String> {
+    let mut conn = db
+        .get_conn_transaction()
+        .await
+        .map_err(|e| format!("Failed to get database handle: {e}"))?;
+
+    let drifts = BalanceDrift::get_all(&mut *conn)
+        .await
+        .map_err(|e| format!("Failed to compute balances: {e}"))?;
+
+    if drifts.is_empty() {
+        println!("All balances match the transactions.");
+        return Ok(true);
+    }
+
+    let mut consistent = true;
+
+    for drift in drifts.iter() {
+        println!(
+            "{} ({}): stored {}, ledger {}, drift {}",
+            drift.nickname,
+            drift.user_id,
+            drift.stored.format_eur(),
+            drift.ledger.format_eur(),
+            drift.drift().format_eur_diff()
+        );
+
+        if !fix {
+            consistent = false;
+            continue;
+        }
+
+        match drift.fix(&mut *conn).await {
+            Ok(id) => println!("  booked correction #{id}"),
+            Err(e) => {
+                println!("  not corrected: {e}");
+                consistent = false;
+            }
+        }
+    }
+
+    conn.commit()
+        .await
+        .map_err(|e| format!("Failed to commit corrections: {e}"))?;
+
+    Ok(consistent)
+}
@@ -0,0 +1,26 @@
+use std::time::Duration;
+
+use tracing::{error, info};
+
+use crate::{backend::ServerState, models::RecurringCharge};
+
+/// How often to look for recurring charges that fell due.
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Books recurring charges for as long as the server runs. Periods missed
+/// while the server was down are booked on the first run.
+pub async fn book_recurring_charges(state: ServerState) {
+    loop {
+        {
+            let db = state.db.lock().await;
+
+            match RecurringCharge::book_due(&db, &state.settings).await {
+                Ok(0) => {}
+                Ok(booked) => info!("Booked {} recurring charges", booked),
+                Err(e) => error!("Failed to book recurring charges: {}", e),
+            }
+        }
+
+        tokio::time::sleep(CHECK_INTERVAL).await;
+    }
+}
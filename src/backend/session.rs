@@ -0,0 +1,163 @@
+use rand::distr::{Alphanumeric, SampleString};
+use std::{
+    collections::{HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
+use crate::models::UserId;
+
+pub const SESSION_COOKIE: &str = "strichliste_session";
+
+/// The users unlocked on one kiosk, several users can be unlocked at once
+/// to book something together, e.g. to settle up.
+struct Session {
+    user_ids: HashSet<UserId>,
+    expires_at: Instant,
+}
+
+/// Wrong PINs entered for a user since the last correct one.
+struct PinFailures {
+    count: u32,
+    locked_until: Option<Instant>,
+}
+
+/// The longest a user is locked out after entering wrong PINs.
+const MAX_PIN_LOCKOUT: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Keeps track of the users that entered their PIN on a kiosk.
+#[derive(Default)]
+pub struct Sessions {
+    sessions: HashMap<String, Session>,
+    pin_failures: HashMap<UserId, PinFailures>,
+}
+
+impl Sessions {
+    /// Returns the token that identifies the new session.
+    pub fn create(&mut self, user_id: UserId, timeout: Duration) -> String {
+        self.remove_expired();
+
+        let token = Alphanumeric.sample_string(&mut rand::rng(), 32);
+
+        _ = self.sessions.insert(
+            token.clone(),
+            Session {
+                user_ids: HashSet::from([user_id]),
+                expires_at: Instant::now() + timeout,
+            },
+        );
+
+        token
+    }
+
+    /// Adds the user to an existing session and extends it by `timeout`.
+    /// Returns false if there is no such session.
+    pub fn unlock(&mut self, token: &str, user_id: UserId, timeout: Duration) -> bool {
+        self.remove_expired();
+
+        match self.sessions.get_mut(token) {
+            Some(session) => {
+                _ = session.user_ids.insert(user_id);
+                session.expires_at = Instant::now() + timeout;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn is_valid(&mut self, token: &str, user_id: UserId) -> bool {
+        self.remove_expired();
+
+        self.sessions
+            .get(token)
+            .is_some_and(|session| session.user_ids.contains(&user_id))
+    }
+
+    /// Removes the user from the session, the session is removed with its
+    /// last user. Returns whether the session is still there.
+    pub fn lock(&mut self, token: &str, user_id: UserId) -> bool {
+        let Some(session) = self.sessions.get_mut(token) else {
+            return false;
+        };
+
+        _ = session.user_ids.remove(&user_id);
+
+        if session.user_ids.is_empty() {
+            _ = self.sessions.remove(token);
+            return false;
+        }
+
+        true
+    }
+
+    /// How long the user still has to wait before entering the PIN again.
+    pub fn pin_locked_for(&self, user_id: UserId) -> Option<Duration> {
+        self.pin_failures
+            .get(&user_id)
+            .and_then(|failures| failures.locked_until)
+            .and_then(|until| until.checked_duration_since(Instant::now()))
+    }
+
+    /// Counts a wrong PIN. From `max_attempts` wrong PINs on the user is
+    /// locked out, twice as long for every further wrong PIN.
+    pub fn record_pin_failure(&mut self, user_id: UserId, max_attempts: u32, lockout: Duration) {
+        let failures = self.pin_failures.entry(user_id).or_insert(PinFailures {
+            count: 0,
+            locked_until: None,
+        });
+
+        failures.count += 1;
+
+        if failures.count >= max_attempts {
+            let factor = 2_u32.saturating_pow(failures.count - max_attempts);
+            let duration = lockout.saturating_mul(factor).min(MAX_PIN_LOCKOUT);
+
+            failures.locked_until = Some(Instant::now() + duration);
+        }
+    }
+
+    pub fn clear_pin_failures(&mut self, user_id: UserId) {
+        _ = self.pin_failures.remove(&user_id);
+    }
+
+    fn remove_expired(&mut self) {
+        let now = Instant::now();
+        self.sessions.retain(|_, session| session.expires_at > now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::Sessions;
+    use crate::models::UserId;
+
+    const TIMEOUT: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn several_users_unlocked_at_once() {
+        let mut sessions = Sessions::default();
+
+        let token = sessions.create(UserId(1), TIMEOUT);
+        assert!(sessions.unlock(&token, UserId(2), TIMEOUT));
+
+        assert!(sessions.is_valid(&token, UserId(1)));
+        assert!(sessions.is_valid(&token, UserId(2)));
+        assert!(!sessions.is_valid(&token, UserId(3)));
+    }
+
+    #[test]
+    fn locking_keeps_the_other_users() {
+        let mut sessions = Sessions::default();
+
+        let token = sessions.create(UserId(1), TIMEOUT);
+        assert!(sessions.unlock(&token, UserId(2), TIMEOUT));
+
+        assert!(sessions.lock(&token, UserId(1)));
+        assert!(!sessions.is_valid(&token, UserId(1)));
+        assert!(sessions.is_valid(&token, UserId(2)));
+
+        assert!(!sessions.lock(&token, UserId(2)));
+        assert!(!sessions.unlock(&token, UserId(2), TIMEOUT));
+    }
+}
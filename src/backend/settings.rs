@@ -2,10 +2,26 @@ use config::ConfigError;
 use serde::Deserialize;
 use std::{collections::HashMap, path::PathBuf};
 
+use crate::models::TransactionKind;
+
 #[derive(Deserialize, Debug)]
 pub struct Settings {
     pub sounds: SoundSettings,
     pub accounts: AccountsSettings,
+    #[serde(default)]
+    pub pin: PinSettings,
+    #[serde(default)]
+    pub undo: UndoSettings,
+    #[serde(default)]
+    pub requests: RequestSettings,
+    #[serde(default)]
+    pub cash_box: CashBoxSettings,
+    #[serde(default)]
+    pub idempotency: IdempotencySettings,
+    #[serde(default)]
+    pub bank: BankSettings,
+    #[serde(default)]
+    pub stock: StockSettings,
 }
 
 #[derive(Deserialize, Debug)]
@@ -21,6 +37,116 @@ pub struct AccountsSettings {
     pub lower_limit: i64,
 }
 
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct PinSettings {
+    /// seconds until an entered PIN has to be entered again
+    pub session_timeout: u64,
+    /// wrong PINs after which a user is locked out
+    pub max_attempts: u32,
+    /// seconds a user is locked out, doubled for every further wrong PIN
+    pub lockout: u64,
+}
+
+impl Default for PinSettings {
+    fn default() -> Self {
+        Self {
+            session_timeout: 120,
+            max_attempts: 5,
+            lockout: 60,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct UndoSettings {
+    /// seconds after which a transaction can not be undone anymore, 0 = no limit
+    pub max_age: u64,
+    /// the kinds of transactions that can be undone at all
+    pub types: Vec<TransactionKind>,
+    /// only the most recent transactions of a user can be undone, 0 = no limit
+    pub last_n: u64,
+}
+
+impl Default for UndoSettings {
+    fn default() -> Self {
+        Self {
+            max_age: 120,
+            types: TransactionKind::ALL.to_vec(),
+            last_n: 0,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct RequestSettings {
+    /// days after which an unanswered money request expires
+    pub expire_after: u64,
+}
+
+impl Default for RequestSettings {
+    fn default() -> Self {
+        Self { expire_after: 14 }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct CashBoxSettings {
+    /// close the cash box automatically every night
+    pub daily_closing: bool,
+}
+
+impl Default for CashBoxSettings {
+    fn default() -> Self {
+        Self {
+            daily_closing: true,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct IdempotencySettings {
+    /// seconds in which a repeated idempotency key returns the original transaction
+    pub window: u64,
+}
+
+impl Default for IdempotencySettings {
+    fn default() -> Self {
+        Self { window: 86400 }
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+pub struct BankSettings {
+    /// IBAN of the club account, no top-up QR codes are shown while it is empty
+    pub iban: String,
+    /// BIC of the club account, optional within the EEA
+    pub bic: String,
+    /// name of the account holder
+    pub name: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(default)]
+pub struct StockSettings {
+    /// whether articles can be bought when they are out of stock, the stock
+    /// goes negative then and a warning is logged
+    pub allow_negative: bool,
+}
+
+impl Default for StockSettings {
+    fn default() -> Self {
+        Self {
+            allow_negative: true,
+        }
+    }
+}
+
 impl Settings {
     pub fn new(config_path: PathBuf) -> Result<Self, ConfigError> {
         use config::Config;
@@ -0,0 +1,131 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use tracing::info;
+
+use crate::{backend::ServerState, models::ArticleSound};
+
+/// Largest sound file that can be uploaded.
+pub const MAX_SOUND_SIZE: usize = 10 * 1024 * 1024;
+
+const SOUND_EXTENSIONS: [&str; 7] = ["wav", "mp3", "ogg", "oga", "opus", "flac", "m4a"];
+
+/// Where uploaded sounds are stored. Only files in here are removed when no
+/// sound refers to them anymore, so it holds nothing but uploads.
+pub fn uploaded_sounds_directory(data_dir: &Path) -> PathBuf {
+    data_dir.join("sounds").join("uploads")
+}
+
+/// Keeps the file name safe to store, fails for files that are no sounds by
+/// their extension or their contents.
+pub fn sound_file_name(file_name: &str, content: &[u8]) -> Result<String, String> {
+    let not_a_sound = || format!("Sounds have to be one of: {}", SOUND_EXTENSIONS.join(", "));
+
+    let file_name = Path::new(file_name)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(not_a_sound)?;
+
+    let extension = file_name
+        .rsplit_once('.')
+        .map(|(_, extension)| extension.to_lowercase())
+        .filter(|extension| SOUND_EXTENSIONS.contains(&extension.as_str()))
+        .ok_or_else(not_a_sound)?;
+
+    if !has_sound_signature(&extension, content) {
+        return Err(format!("'{file_name}' is not a {extension} file!"));
+    }
+
+    Ok(file_name
+        .chars()
+        .map(
+            |c| match c.is_ascii_alphanumeric() || c == '.' || c == '-' {
+                true => c,
+                false => '_',
+            },
+        )
+        .collect())
+}
+
+/// Checks the magic bytes at the start of `content` against the format the
+/// extension promises.
+fn has_sound_signature(extension: &str, content: &[u8]) -> bool {
+    match extension {
+        "wav" => content.starts_with(b"RIFF") && content.get(8..12) == Some(&b"WAVE"[..]),
+        // with an ID3 tag or starting with the sync word of a frame
+        "mp3" => {
+            content.starts_with(b"ID3")
+                || matches!(content, [0xff, second, ..] if second & 0xe0 == 0xe0)
+        }
+        "ogg" | "oga" | "opus" => content.starts_with(b"OggS"),
+        "flac" => content.starts_with(b"fLaC"),
+        "m4a" => content.get(4..8) == Some(&b"ftyp"[..]),
+        _ => false,
+    }
+}
+
+/// Removes the uploaded files neither a sound nor the settings refer to
+/// anymore, returns how many were removed.
+pub async fn remove_unused_sound_files(state: &ServerState) -> Result<usize, String> {
+    let directory = uploaded_sounds_directory(&state.data_dir);
+
+    // uploads hold the lock from writing the file until its sound is stored
+    let db = state.db.lock().await;
+    let mut conn = db.get_conn().await.map_err(|e| e.to_string())?;
+
+    let mut used = HashSet::new();
+
+    // sounds stored before the data directory was canonicalized may have a
+    // relative path
+    for sound in ArticleSound::get_all(&mut *conn)
+        .await
+        .map_err(|e| e.to_string())?
+    {
+        let path = PathBuf::from(sound.path);
+        used.insert(tokio::fs::canonicalize(&path).await.unwrap_or(path));
+    }
+
+    let sounds = &state.settings.sounds;
+
+    for path in sounds
+        .articles
+        .values()
+        .flatten()
+        .chain(sounds.generic.iter())
+        .chain(sounds.failed.iter())
+    {
+        let path = PathBuf::from(path);
+        used.insert(tokio::fs::canonicalize(&path).await.unwrap_or(path));
+    }
+
+    let mut entries = match tokio::fs::read_dir(&directory).await {
+        Ok(value) => value,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(format!("Failed to read '{}': {e}", directory.display())),
+    };
+
+    let mut removed = 0;
+
+    while let Some(entry) = entries
+        .next_entry()
+        .await
+        .map_err(|e| format!("Failed to read '{}': {e}", directory.display()))?
+    {
+        let path = entry.path();
+
+        if used.contains(&path) || !entry.file_type().await.is_ok_and(|kind| kind.is_file()) {
+            continue;
+        }
+
+        tokio::fs::remove_file(&path)
+            .await
+            .map_err(|e| format!("Failed to remove '{}': {e}", path.display()))?;
+
+        info!("Removed unused sound '{}'", path.display());
+        removed += 1;
+    }
+
+    Ok(removed)
+}
@@ -1,7 +1,7 @@
 #[cfg(feature = "ssr")]
 use {
-    crate::backend::{db::DB, Settings},
-    std::sync::Arc,
+    crate::backend::{db::DB, Sessions, Settings},
+    std::{path::PathBuf, sync::Arc},
     tokio::sync::Mutex,
 };
 
@@ -11,4 +11,7 @@ pub type ServerState = Arc<State>;
 pub struct State {
     pub db: Mutex<DB>,
     pub settings: Settings,
+    pub sessions: Mutex<Sessions>,
+    /// where the database and uploaded files are stored
+    pub data_dir: PathBuf,
 }
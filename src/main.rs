@@ -2,7 +2,7 @@
 use std::path::PathBuf;
 
 #[cfg(feature = "ssr")]
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
 #[cfg(feature = "ssr")]
 #[derive(Parser)]
@@ -14,6 +14,19 @@ struct Args {
 
     #[arg(short = 'c', long = "config", help = "The config file to use")]
     config: PathBuf,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[cfg(feature = "ssr")]
+#[derive(Subcommand)]
+enum Command {
+    /// Compare the stored balances with the ones computed from the transactions
+    Reconcile {
+        #[arg(long, help = "Book correcting transactions for all drifting users")]
+        fix: bool,
+    },
 }
 
 #[cfg(feature = "ssr")]
@@ -22,13 +35,16 @@ async fn main() {
     use std::process::exit;
     use std::sync::Arc;
 
-    use axum::Router;
+    use axum::{routing::get, Router};
     use leptos::logging::log;
     use leptos::prelude::*;
     use leptos_axum::{generate_route_list, LeptosRoutes};
     use strichliste_rs::app::*;
 
-    use strichliste_rs::backend::{db, ServerState, Settings, State};
+    use strichliste_rs::backend::{
+        book_recurring_charges, close_cash_box_daily, db, export_cash_closings, reconcile,
+        ServerState, Sessions, Settings, State,
+    };
 
     use tokio::sync::Mutex;
     use tracing::{error, Level};
@@ -79,11 +95,44 @@ async fn main() {
         }
     };
 
+    if let Some(Command::Reconcile { fix }) = args.command {
+        match reconcile(&db, fix).await {
+            Ok(true) => exit(0),
+            Ok(false) => exit(2),
+            Err(e) => {
+                error!("{e}");
+                exit(1);
+            }
+        }
+    }
+
+    // uploaded files are stored with their absolute path
+    let data_dir = match args.data_dir.canonicalize() {
+        Ok(value) => value,
+        Err(e) => {
+            error!(
+                "Failed to resolve data directory '{}': {e}",
+                args.data_dir.display()
+            );
+            exit(1);
+        }
+    };
+
     let server_state: ServerState = Arc::new(State {
         db: Mutex::new(db),
         settings,
+        sessions: Mutex::new(Sessions::default()),
+        data_dir,
     });
 
+    tokio::spawn(book_recurring_charges(server_state.clone()));
+
+    if server_state.settings.cash_box.daily_closing {
+        tokio::spawn(close_cash_box_daily(server_state.clone()));
+    }
+
+    let export_state = server_state.clone();
+
     let conf = get_configuration(None).unwrap();
     let addr = conf.leptos_options.site_addr;
     let leptos_options = conf.leptos_options;
@@ -91,6 +140,10 @@ async fn main() {
     let routes = generate_route_list(App);
 
     let app = Router::new()
+        .route(
+            "/admin/cash_box/closings.csv",
+            get(move || export_cash_closings(export_state.clone())),
+        )
         .leptos_routes_with_context(
             &leptos_options,
             routes,
@@ -1,10 +1,10 @@
 use crate::models::Money;
 
-use super::{ArticleSound, Barcode};
+use super::{ArticleSound, ArticleTag, Barcode};
 
 #[cfg(feature = "ssr")]
 use {
-    super::{BarcodeDB, BarcodeDiff},
+    super::{BarcodeDB, BarcodeDiff, SoundDiff, TagDiff},
     crate::backend::db::{DBError, DB},
     crate::backend::db::{DatabaseResponse, DatabaseType},
     crate::models::{DatabaseId, UserId},
@@ -25,10 +25,33 @@ pub struct Article {
     pub cost: Money,
     pub sounds: Vec<ArticleSound>,
     pub barcodes: Vec<Barcode>,
+    pub tags: Vec<ArticleTag>,
+    /// inactive articles can't be bought anymore but still show up in the
+    /// history
+    pub is_active: bool,
+    /// units in stock, `None` while the stock isn't tracked
+    pub stock: Option<i64>,
 }
 
 impl Article {
     pub const DEFAULT_ARTICLE_AMOUNT: usize = 9;
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|elem| elem.name == tag)
+    }
+
+    /// The names of all tags of `articles`, sorted and without duplicates.
+    pub fn tag_names(articles: &[Article]) -> Vec<String> {
+        let mut names = articles
+            .iter()
+            .flat_map(|article| article.tags.iter().map(|tag| tag.name.clone()))
+            .collect::<Vec<_>>();
+
+        names.sort();
+        names.dedup();
+
+        names
+    }
 }
 
 #[cfg(feature = "ssr")]
@@ -45,14 +68,23 @@ impl Article {
         Ok(article.expect("Newly created article should exist!"))
     }
 
-    pub async fn get_all(db: &DB, limit: Option<i64>) -> DatabaseResponse<Vec<Self>> {
+    pub async fn get_all(
+        db: &DB,
+        limit: Option<i64>,
+        include_inactive: bool,
+    ) -> DatabaseResponse<Vec<Self>> {
         let mut conn = db.get_conn().await?;
 
-        let articles = ArticleDB::get_all(&mut *conn, limit).await?;
+        let articles = ArticleDB::get_all(&mut *conn, limit, include_inactive).await?;
 
         let mut article_no_db = Vec::new();
         for article in articles {
-            let ArticleDB { id, name } = article;
+            let ArticleDB {
+                id,
+                name,
+                is_active,
+                stock,
+            } = article;
             let article_sounds = ArticleDB::get_sounds(&mut *conn, id).await?;
             debug!("Fetched sounds");
             let article_barcodes = ArticleDB::get_barcodes(&mut *conn, id)
@@ -61,6 +93,7 @@ impl Article {
                 .map(|elem| Barcode(elem.barcode_content))
                 .collect();
             debug!("Fetched barcodes");
+            let article_tags = ArticleDB::get_tags(&mut *conn, id).await?;
             let cost = ArticleDB::get_latest_cost(&mut *conn, id).await?;
             debug!("Fetched cost");
 
@@ -70,6 +103,9 @@ impl Article {
                 cost: cost.into(),
                 sounds: article_sounds,
                 barcodes: article_barcodes,
+                tags: article_tags,
+                is_active,
+                stock,
             });
         }
         Ok(article_no_db)
@@ -86,16 +122,25 @@ impl Article {
                     .into_iter()
                     .map(|elem| Barcode(elem.barcode_content))
                     .collect();
+                let article_tags = ArticleDB::get_tags(&mut *conn, article.id).await?;
 
                 let cost = ArticleDB::get_latest_cost(&mut *conn, article.id).await?;
 
-                let ArticleDB { id, name } = article;
+                let ArticleDB {
+                    id,
+                    name,
+                    is_active,
+                    stock,
+                } = article;
                 Ok(Some(Article {
                     id,
                     name,
                     cost: cost.into(),
                     sounds: article_sounds,
                     barcodes: article_barcodes,
+                    tags: article_tags,
+                    is_active,
+                    stock,
                 }))
             }
             None => Ok(None),
@@ -138,6 +183,17 @@ impl Article {
         Ok(())
     }
 
+    pub async fn set_active<T>(&mut self, conn: &mut T, is_active: bool) -> DatabaseResponse<()>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        ArticleDB::set_active(conn, self.id, is_active).await?;
+
+        self.is_active = is_active;
+
+        Ok(())
+    }
+
     pub async fn set_barcodes<T>(
         &mut self,
         conn: &mut T,
@@ -167,6 +223,58 @@ impl Article {
         Ok(())
     }
 
+    pub async fn set_tags<T>(
+        &mut self,
+        conn: &mut T,
+        tag_diff: Vec<TagDiff>,
+    ) -> DatabaseResponse<()>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        for diff in tag_diff.into_iter() {
+            match diff {
+                TagDiff::Removed(name) => {
+                    ArticleDB::remove_tag(&mut *conn, self.id, name).await?;
+                }
+
+                TagDiff::Added(name) => {
+                    ArticleDB::add_tag(&mut *conn, self.id, name).await?;
+                }
+            }
+        }
+
+        self.tags = ArticleDB::get_tags(&mut *conn, self.id).await?;
+
+        Ok(())
+    }
+
+    pub async fn set_sounds<T>(
+        &mut self,
+        conn: &mut T,
+        sound_diff: Vec<SoundDiff>,
+    ) -> DatabaseResponse<()>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        for diff in sound_diff.into_iter() {
+            match diff {
+                SoundDiff::Removed(sound_id) => {
+                    ArticleDB::remove_sound(&mut *conn, self.id, sound_id).await?;
+                }
+
+                SoundDiff::Added(sound_id) => {
+                    ArticleDB::add_sound(&mut *conn, self.id, sound_id).await?;
+                }
+            }
+        }
+
+        ArticleSound::remove_unused(&mut *conn).await?;
+
+        self.sounds = ArticleDB::get_sounds(&mut *conn, self.id).await?;
+
+        Ok(())
+    }
+
     pub async fn get_articles_for_user(db: &DB, user_id: UserId) -> DatabaseResponse<Vec<Self>> {
         let mut conn = db.get_conn().await?;
 
@@ -174,19 +282,21 @@ impl Article {
             ArticleDB::get_articles_for_user(&mut *conn, user_id).await?;
 
         //sort by most bought
-        articles_amount_bought.sort_by(|a, b| b.1.cmp(&a.1));
+        articles_amount_bought.sort_by_key(|b| std::cmp::Reverse(b.1));
 
         let mut full_articles = Vec::<Article>::new();
 
         for (article_id, _amount_bought) in articles_amount_bought.iter() {
-            full_articles.push(
-                Article::get(db, *article_id)
-                    .await?
-                    .expect("fetched article should exist!"),
-            );
+            let article = Article::get(db, *article_id)
+                .await?
+                .expect("fetched article should exist!");
+
+            if article.is_active {
+                full_articles.push(article);
+            }
         }
 
-        let mut articles = Self::get_all(db, None).await?;
+        let mut articles = Self::get_all(db, None, false).await?;
 
         for article in full_articles.iter() {
             articles.retain(|value| value.id != article.id);
@@ -209,6 +319,8 @@ impl Article {
 pub struct ArticleDB {
     pub id: DatabaseId,
     pub name: String,
+    pub is_active: bool,
+    pub stock: Option<i64>,
 }
 
 #[cfg(feature = "ssr")]
@@ -294,7 +406,11 @@ impl ArticleDB {
         Ok(result)
     }
 
-    pub async fn get_all<T>(conn: &mut T, limit: Option<i64>) -> DatabaseResponse<Vec<Self>>
+    pub async fn get_all<T>(
+        conn: &mut T,
+        limit: Option<i64>,
+        include_inactive: bool,
+    ) -> DatabaseResponse<Vec<Self>>
     where
         for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
     {
@@ -303,8 +419,10 @@ impl ArticleDB {
                 Self,
                 "
                         select * from Articles
+                        where is_active or ?
                         limit ?
                     ",
+                include_inactive,
                 limit
             )
             .fetch_all(&mut *conn)
@@ -315,7 +433,9 @@ impl ArticleDB {
                 Self,
                 "
                     select * from Articles
-                "
+                    where is_active or ?
+                ",
+                include_inactive
             )
             .fetch_all(&mut *conn)
             .await
@@ -362,6 +482,63 @@ impl ArticleDB {
         Ok(sounds)
     }
 
+    pub async fn add_sound<T>(
+        conn: &mut T,
+        article_id: DatabaseId,
+        sound_id: DatabaseId,
+    ) -> DatabaseResponse<()>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        if !ArticleSound::exists(&mut *conn, sound_id).await? {
+            return Err(DBError::new(format!("Unknown sound id '{sound_id}'")));
+        }
+
+        _ = query!(
+            "
+                insert into ArticleSoundMap
+                    (sound_id, article_id)
+                select ?, ?
+                where not exists (
+                    select 1 from ArticleSoundMap
+                    where sound_id = ? and article_id = ?
+                )
+            ",
+            sound_id,
+            article_id,
+            sound_id,
+            article_id
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(DBError::new)?;
+
+        Ok(())
+    }
+
+    pub async fn remove_sound<T>(
+        conn: &mut T,
+        article_id: DatabaseId,
+        sound_id: DatabaseId,
+    ) -> DatabaseResponse<()>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        _ = query!(
+            "
+                delete from ArticleSoundMap
+                where article_id = ? and sound_id = ?
+            ",
+            article_id,
+            sound_id
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(DBError::new)?;
+
+        Ok(())
+    }
+
     pub async fn get_latest_cost<T>(conn: &mut T, article_id: DatabaseId) -> DatabaseResponse<i64>
     where
         for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
@@ -426,6 +603,139 @@ impl ArticleDB {
         .map_err(DBError::new)
     }
 
+    pub async fn get_tags<T>(
+        conn: &mut T,
+        article_id: DatabaseId,
+    ) -> DatabaseResponse<Vec<ArticleTag>>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        query_as!(
+            ArticleTag,
+            "
+                select ArticleTags.id, ArticleTags.name
+                from ArticleTags
+                join ArticleTagMap on ArticleTagMap.tag_id = ArticleTags.id
+                where ArticleTagMap.article_id = ?
+                order by ArticleTags.name
+            ",
+            article_id
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(DBError::new)
+    }
+
+    /// Tags the article, the tag is created if no article has it yet.
+    pub async fn add_tag<T>(
+        conn: &mut T,
+        article_id: DatabaseId,
+        name: String,
+    ) -> DatabaseResponse<()>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        let name = name.trim().to_string();
+
+        if name.is_empty() {
+            return Err(DBError::new("The tag must not be empty!"));
+        }
+
+        let existing = query!(
+            "
+                select id from ArticleTags
+                where name = ?
+            ",
+            name
+        )
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(DBError::new)?;
+
+        let tag_id = match existing {
+            Some(value) => value.id,
+            None => {
+                query!(
+                    "
+                        insert into ArticleTags
+                            (name)
+                        values
+                            (?)
+                        returning id
+                    ",
+                    name
+                )
+                .fetch_one(&mut *conn)
+                .await
+                .map_err(DBError::new)?
+                .id
+            }
+        };
+
+        _ = query!(
+            "
+                insert into ArticleTagMap
+                    (tag_id, article_id)
+                select ?, ?
+                where not exists (
+                    select 1 from ArticleTagMap
+                    where tag_id = ? and article_id = ?
+                )
+            ",
+            tag_id,
+            article_id,
+            tag_id,
+            article_id
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(DBError::new)?;
+
+        Ok(())
+    }
+
+    pub async fn remove_tag<T>(
+        conn: &mut T,
+        article_id: DatabaseId,
+        name: String,
+    ) -> DatabaseResponse<()>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        _ = query!(
+            "
+                delete from ArticleTagMap
+                where article_id = ? and tag_id in (
+                    select id from ArticleTags where name = ?
+                )
+            ",
+            article_id,
+            name
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(DBError::new)?;
+
+        Self::remove_unused_tags(&mut *conn).await
+    }
+
+    async fn remove_unused_tags<T>(conn: &mut T) -> DatabaseResponse<()>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        _ = query!(
+            "
+                delete from ArticleTags
+                where id not in (select tag_id from ArticleTagMap)
+            "
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(DBError::new)?;
+
+        Ok(())
+    }
+
     pub async fn set_name<T>(
         conn: &mut T,
         article_id: DatabaseId,
@@ -450,6 +760,104 @@ impl ArticleDB {
         Ok(())
     }
 
+    pub async fn set_active<T>(
+        conn: &mut T,
+        article_id: DatabaseId,
+        is_active: bool,
+    ) -> DatabaseResponse<()>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        _ = query!(
+            "
+                update Articles
+                    set is_active = ?
+                where id = ?
+            ",
+            is_active,
+            article_id,
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(DBError::new)?;
+
+        Ok(())
+    }
+
+    /// Whether any transaction, including undone ones, or any stock movement
+    /// refers to the article.
+    pub async fn is_used<T>(conn: &mut T, article_id: DatabaseId) -> DatabaseResponse<bool>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        use crate::backend::db::DBGROUP_SNACKBAR_ID;
+
+        let result = query!(
+            "
+                select id from Transactions
+                where t_type_data = ? and (sender = ? or receiver = ?)
+                union all
+                select id from StockMovements
+                where article_id = ?
+                limit 1
+            ",
+            article_id,
+            DBGROUP_SNACKBAR_ID.0,
+            DBGROUP_SNACKBAR_ID.0,
+            article_id
+        )
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(DBError::new)?;
+
+        Ok(result.is_some())
+    }
+
+    /// Removes the article with its barcodes, sounds, tags and prices.
+    pub async fn delete<T>(conn: &mut T, article_id: DatabaseId) -> DatabaseResponse<()>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        query!(
+            "delete from ArticleBarcodes where article_id = ?",
+            article_id
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(DBError::new)?;
+
+        query!(
+            "delete from ArticleSoundMap where article_id = ?",
+            article_id
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(DBError::new)?;
+
+        query!("delete from ArticleTagMap where article_id = ?", article_id)
+            .execute(&mut *conn)
+            .await
+            .map_err(DBError::new)?;
+
+        Self::remove_unused_tags(&mut *conn).await?;
+        ArticleSound::remove_unused(&mut *conn).await?;
+
+        query!(
+            "delete from ArticleCostMap where article_id = ?",
+            article_id
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(DBError::new)?;
+
+        query!("delete from Articles where id = ?", article_id)
+            .execute(&mut *conn)
+            .await
+            .map_err(DBError::new)?;
+
+        Ok(())
+    }
+
     pub async fn add_barcode<T>(
         conn: &mut T,
         article_id: DatabaseId,
@@ -521,7 +929,8 @@ impl ArticleDB {
         let result = query!(
             "
                 select article_id from ArticleBarcodes
-                where barcode_content = ?
+                join Articles on Articles.id = ArticleBarcodes.article_id
+                where barcode_content = ? and Articles.is_active
             ",
             barcode
         )
@@ -550,7 +959,13 @@ impl ArticleDB {
                 from
                     Transactions
                 where
-                    sender = ? and is_undone = 0 and receiver = ?
+                    sender = ? and receiver = ?
+                    and reverses_id is null
+                    and not exists (
+                        select 1
+                        from Transactions as Reversals
+                        where Reversals.reverses_id = Transactions.id
+                    )
                 group by t_type_data
                 order by timestamp desc
                 limit 50
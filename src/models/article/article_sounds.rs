@@ -1,4 +1,11 @@
 use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "ssr")]
+use {
+    crate::backend::db::{DBError, DatabaseResponse, DatabaseType},
+    sqlx::{query, query_as, Executor},
+};
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 #[cfg_attr(feature = "ssr", derive(sqlx::Type, sqlx::FromRow))]
 pub struct ArticleSound {
@@ -6,3 +13,85 @@ pub struct ArticleSound {
     pub name: String,
     pub path: String,
 }
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum SoundDiff {
+    Removed(i64),
+    Added(i64),
+}
+
+#[cfg(feature = "ssr")]
+impl ArticleSound {
+    pub async fn create<T>(conn: &mut T, name: String, path: String) -> DatabaseResponse<Self>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        query_as!(
+            ArticleSound,
+            "
+                insert into ArticleSounds
+                    (name, path)
+                values
+                    (?, ?)
+                returning id, name, path
+            ",
+            name,
+            path
+        )
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(DBError::new)
+    }
+
+    pub async fn get_all<T>(conn: &mut T) -> DatabaseResponse<Vec<Self>>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        query_as!(
+            ArticleSound,
+            "
+                select * from ArticleSounds
+                order by name
+            "
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(DBError::new)
+    }
+
+    /// Deletes the sounds no article uses anymore, like `remove_unused_tags`.
+    /// Their files are removed by the backend once the change is committed.
+    pub async fn remove_unused<T>(conn: &mut T) -> DatabaseResponse<()>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        _ = query!(
+            "
+                delete from ArticleSounds
+                where id not in (select sound_id from ArticleSoundMap)
+            "
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(DBError::new)?;
+
+        Ok(())
+    }
+
+    pub async fn exists<T>(conn: &mut T, id: i64) -> DatabaseResponse<bool>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        query!(
+            "
+                select id from ArticleSounds
+                where id = ?
+            ",
+            id
+        )
+        .fetch_optional(&mut *conn)
+        .await
+        .map(|row| row.is_some())
+        .map_err(DBError::new)
+    }
+}
@@ -1,7 +1,9 @@
 mod article_core;
 mod article_cost;
 mod article_sounds;
+mod article_tags;
 mod barcode;
 pub use article_core::*;
 pub use article_sounds::*;
+pub use article_tags::*;
 pub use barcode::*;
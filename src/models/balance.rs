@@ -0,0 +1,99 @@
+use serde::{Deserialize, Serialize};
+
+use super::{Money, UserId};
+
+#[cfg(feature = "ssr")]
+use {
+    super::{DatabaseId, Transaction, TransactionDB, User, UserDB},
+    crate::backend::db::{
+        DBError, DatabaseResponse, DatabaseType, DBGROUP_AUFLADUNG_ID, DBUSER_AUFLADUNG_ID,
+    },
+    crate::models::Group,
+    sqlx::Executor,
+};
+
+/// A user whose stored balance doesn't match the one computed from the
+/// transactions.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct BalanceDrift {
+    pub user_id: UserId,
+    pub nickname: String,
+    pub is_system_user: bool,
+    /// the balance stored with the user
+    pub stored: Money,
+    /// the balance computed from the transactions
+    pub ledger: Money,
+}
+
+impl BalanceDrift {
+    pub fn drift(&self) -> Money {
+        self.stored - self.ledger
+    }
+}
+
+#[cfg(feature = "ssr")]
+impl BalanceDrift {
+    pub async fn get_all<T>(conn: &mut T) -> DatabaseResponse<Vec<Self>>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        let balances = Transaction::get_ledger_balances(&mut *conn).await?;
+
+        Ok(UserDB::get_all_unfiltered(&mut *conn)
+            .await?
+            .into_iter()
+            .map(|user| BalanceDrift {
+                user_id: UserId(user.id),
+                ledger: balances
+                    .get(&UserId(user.id))
+                    .copied()
+                    .unwrap_or_default()
+                    .into(),
+                stored: user.money.into(),
+                nickname: user.nickname,
+                is_system_user: user.is_system_user,
+            })
+            .filter(|drift| drift.stored != drift.ledger)
+            .collect())
+    }
+
+    /// Books an adjustment from or to the top-up account so that the ledger
+    /// matches the stored balance of the user. The stored balance of the
+    /// top-up account changes accordingly, so it stays consistent as well.
+    pub async fn fix<T>(&self, conn: &mut T) -> DatabaseResponse<DatabaseId>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        if self.is_system_user {
+            return Err(DBError::new(format!(
+                "Can't correct the balance of system user '{}'",
+                self.nickname
+            )));
+        }
+
+        let user_group = Group::get_user_group_id(&mut *conn, self.user_id).await?;
+        let drift = self.drift().value;
+
+        let (sender, receiver) = match drift > 0 {
+            true => (DBGROUP_AUFLADUNG_ID, user_group),
+            false => (user_group, DBGROUP_AUFLADUNG_ID),
+        };
+
+        let id =
+            TransactionDB::create_correction(&mut *conn, sender, receiver, drift.abs()).await?;
+
+        TransactionDB::insert_share(&mut *conn, id, self.user_id, drift).await?;
+        TransactionDB::insert_share(&mut *conn, id, DBUSER_AUFLADUNG_ID, -drift).await?;
+
+        let mut aufladung = match User::get(&mut *conn, DBUSER_AUFLADUNG_ID).await? {
+            Some(value) => value,
+            None => return Err(DBError::new("Failed to find the top-up user")),
+        };
+
+        aufladung
+            .add_money(&mut *conn, Money { value: -drift })
+            .await?;
+
+        Ok(id)
+    }
+}
@@ -0,0 +1,625 @@
+use serde::{Deserialize, Serialize};
+
+use super::{Money, UserId};
+
+#[cfg(feature = "ssr")]
+use {
+    super::{DatabaseId, UserDB},
+    crate::backend::db::{DatabaseResponse, DatabaseType},
+    chrono::{NaiveDate, Utc},
+    sqlx::{query, Executor},
+    std::collections::HashMap,
+};
+
+/// Users put `SL-<id>` on their bank transfers, so the import can tell who
+/// topped up.
+const PAYMENT_REFERENCE_PREFIX: &str = "SL";
+
+pub fn payment_reference(user_id: UserId) -> String {
+    format!("{}-{}", PAYMENT_REFERENCE_PREFIX, user_id.0)
+}
+
+/// A single payment of a bank statement.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct BankLine {
+    /// identifies the line across imports and export formats, so it is
+    /// booked only once
+    pub import_id: String,
+    /// the booking date as `YYYY-MM-DD`
+    pub date: String,
+    /// negative for payments that left the account
+    pub money: Money,
+    pub name: Option<String>,
+    /// the IBAN of the payer
+    pub iban: Option<String>,
+    pub text: String,
+}
+
+/// A row of a CSV export that is not a payment, like the closing balance some
+/// banks append.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SkippedRow {
+    /// starting at 1 for the first row of the file
+    pub row: usize,
+    pub content: String,
+    pub reason: String,
+}
+
+/// The incoming payments of a bank statement.
+#[cfg(feature = "ssr")]
+#[derive(Debug)]
+pub struct BankStatement {
+    pub lines: Vec<BankLine>,
+    pub skipped: Vec<SkippedRow>,
+}
+
+/// A line of a bank statement as shown for review before booking.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct BankImportLine {
+    pub line: BankLine,
+    pub user: Option<(UserId, String)>,
+    pub is_booked: bool,
+}
+
+/// The reviewed lines of a statement and the rows that were skipped.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct BankImportPreview {
+    pub lines: Vec<BankImportLine>,
+    pub skipped: Vec<SkippedRow>,
+}
+
+/// Parses a CAMT.053 XML or CSV export and keeps the incoming payments.
+#[cfg(feature = "ssr")]
+pub fn parse_bank_statement(content: &str) -> Result<BankStatement, String> {
+    let mut statement = match content.trim_start().starts_with('<') {
+        true => BankStatement {
+            lines: parse_camt(content)?,
+            skipped: Vec::new(),
+        },
+        false => parse_csv(content)?,
+    };
+
+    statement.lines.retain(|line| line.money.value > 0);
+
+    Ok(statement)
+}
+
+/// Identifies a line by the booking date, the amount, the reference and the
+/// IBAN of the payer, so a payment gets the same id in every export format.
+/// Equal lines of the same statement are numbered.
+#[cfg(feature = "ssr")]
+fn import_id(
+    seen: &mut HashMap<String, u32>,
+    date: &str,
+    money: Money,
+    text: &str,
+    iban: &Option<String>,
+) -> String {
+    let id = format!(
+        "{}|{}|{}|{}",
+        date,
+        money.value,
+        text.split_whitespace().collect::<Vec<_>>().join(" "),
+        iban.as_deref().unwrap_or_default()
+    );
+
+    let count = seen.entry(id.clone()).or_default();
+    *count += 1;
+
+    format!("{}#{}", id, count)
+}
+
+/// Reads `2026-10-01`, `01.10.2026` and `01.10.26`, a time after the date is
+/// ignored.
+#[cfg(feature = "ssr")]
+fn parse_date(date: &str) -> Result<String, String> {
+    let date = date.trim();
+    let day = date.split(['T', ' ']).next().unwrap_or_default();
+
+    let formats: &[&str] = match day.len() {
+        8 => &["%d.%m.%y"],
+        _ => &["%Y-%m-%d", "%d.%m.%Y"],
+    };
+
+    formats
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(day, format).ok())
+        .map(|date| date.format("%Y-%m-%d").to_string())
+        .ok_or_else(|| format!("Invalid date '{}'", date))
+}
+
+#[cfg(feature = "ssr")]
+fn normalize_iban(iban: &str) -> Option<String> {
+    let iban = iban.replace(' ', "").to_uppercase();
+
+    (!iban.is_empty()).then_some(iban)
+}
+
+/// Yields the contents of all `tag` elements, elements nested in an element
+/// of the same name are not supported.
+#[cfg(feature = "ssr")]
+fn xml_elements<'a>(xml: &'a str, tag: &str) -> impl Iterator<Item = &'a str> {
+    let open = format!("<{tag}");
+    let close = format!("</{tag}>");
+    let mut rest = xml;
+
+    std::iter::from_fn(move || loop {
+        let start = rest.find(&open)?;
+        let after_name = &rest[start + open.len()..];
+
+        // skip elements that only start with the same name
+        if !after_name.starts_with(['>', '/', ' ', '\t', '\r', '\n']) {
+            rest = after_name;
+            continue;
+        }
+
+        let end_of_tag = after_name.find('>')?;
+
+        if after_name[..end_of_tag].ends_with('/') {
+            rest = &after_name[end_of_tag + 1..];
+            return Some("");
+        }
+
+        let content = &after_name[end_of_tag + 1..];
+        let end = content.find(&close)?;
+        rest = &content[end + close.len()..];
+
+        return Some(content[..end].trim());
+    })
+}
+
+#[cfg(feature = "ssr")]
+fn xml_element<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    xml_elements(xml, tag).next()
+}
+
+#[cfg(feature = "ssr")]
+fn xml_unescape(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Every `Ntry` of the statement is one line, batched entries are not split.
+#[cfg(feature = "ssr")]
+fn parse_camt(content: &str) -> Result<Vec<BankLine>, String> {
+    let mut seen = HashMap::new();
+    let mut lines = Vec::new();
+
+    for entry in xml_elements(content, "Ntry") {
+        let amount = xml_element(entry, "Amt").ok_or("Found an entry without an amount")?;
+
+        let mut money = parse_amount(amount)?;
+
+        if xml_element(entry, "CdtDbtInd") == Some("DBIT") {
+            money = -money;
+        }
+
+        let date = xml_element(entry, "BookgDt")
+            .and_then(|date| xml_element(date, "Dt").or_else(|| xml_element(date, "DtTm")))
+            .ok_or("Found an entry without a booking date")?;
+        let date = parse_date(date)?;
+
+        let name = xml_element(entry, "Dbtr")
+            .and_then(|debtor| xml_element(debtor, "Nm"))
+            .map(xml_unescape);
+
+        let iban = xml_element(entry, "DbtrAcct")
+            .and_then(|account| xml_element(account, "IBAN"))
+            .and_then(normalize_iban);
+
+        let text = xml_elements(entry, "Ustrd")
+            .map(xml_unescape)
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        lines.push(BankLine {
+            import_id: import_id(&mut seen, &date, money, &text, &iban),
+            date,
+            money,
+            name,
+            iban,
+            text,
+        });
+    }
+
+    if lines.is_empty() && !content.contains("<Stmt") {
+        return Err("The file is not a CAMT.053 statement!".to_string());
+    }
+
+    Ok(lines)
+}
+
+/// Splits a row at `delimiter`, fields may be quoted but not span rows.
+#[cfg(feature = "ssr")]
+fn csv_fields(row: &str, delimiter: char) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut is_quoted = false;
+    let mut chars = row.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if is_quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => is_quoted = !is_quoted,
+            c if c == delimiter && !is_quoted => {
+                fields.push(std::mem::take(&mut field).trim().to_string())
+            }
+            c => field.push(c),
+        }
+    }
+
+    fields.push(field.trim().to_string());
+
+    fields
+}
+
+/// Accepts `1234.56`, `1.234,56` and `1,234.56` with an optional sign and
+/// currency.
+#[cfg(feature = "ssr")]
+fn parse_amount(amount: &str) -> Result<Money, String> {
+    let mut value = amount.replace(['€', ' ', '\u{a0}'], "").replace("EUR", "");
+
+    if let Some(rest) = value.strip_prefix('+') {
+        value = rest.to_string();
+    }
+
+    let thousands = match (value.rfind('.'), value.rfind(',')) {
+        (Some(dot), Some(comma)) if dot < comma => Some('.'),
+        (Some(_), Some(_)) => Some(','),
+        _ => None,
+    };
+
+    if let Some(separator) = thousands {
+        value = value.replace(separator, "");
+    }
+
+    value
+        .try_into()
+        .map_err(|e| format!("Invalid amount '{}': {}", amount, e))
+}
+
+/// Returns the index of the first column that starts with one of the
+/// `names`, earlier names are preferred.
+#[cfg(feature = "ssr")]
+fn csv_column(header: &[String], names: &[&str]) -> Option<usize> {
+    names.iter().find_map(|name| {
+        header
+            .iter()
+            .position(|column| column.to_lowercase().starts_with(name))
+    })
+}
+
+/// Rows before the header, like the account summary some banks put on top,
+/// are skipped silently. Rows after it without a valid date and amount are
+/// skipped and reported.
+#[cfg(feature = "ssr")]
+fn parse_csv(content: &str) -> Result<BankStatement, String> {
+    let mut rows = content
+        .lines()
+        .map(|row| row.trim_start_matches('\u{feff}'))
+        .enumerate()
+        .filter(|(_, row)| !row.trim().is_empty());
+
+    let mut columns = None;
+
+    for (_, row) in rows.by_ref() {
+        let delimiter = match row.matches(';').count() >= row.matches(',').count() {
+            true => ';',
+            false => ',',
+        };
+
+        let header = csv_fields(row, delimiter);
+
+        let date = csv_column(
+            &header,
+            &[
+                "buchungstag",
+                "buchungsdatum",
+                "booking date",
+                "date",
+                "datum",
+            ],
+        );
+        let amount = csv_column(&header, &["betrag", "amount", "umsatz"]);
+        let text = csv_column(
+            &header,
+            &[
+                "verwendungszweck",
+                "reference",
+                "purpose",
+                "description",
+                "buchungstext",
+            ],
+        );
+        let name = csv_column(
+            &header,
+            &[
+                "name",
+                "beguenstigter/zahlungspflichtiger",
+                "zahlungspflichtige",
+                "auftraggeber",
+                "payer",
+                "counterparty",
+            ],
+        );
+
+        let iban = csv_column(&header, &["iban", "kontonummer", "counterparty iban"]);
+
+        if let (Some(date), Some(amount), Some(text)) = (date, amount, text) {
+            columns = Some((delimiter, date, amount, text, name, iban));
+            break;
+        }
+    }
+
+    let Some((delimiter, date, amount, text, name, iban)) = columns else {
+        return Err("Found no header with date, amount and reference columns!".to_string());
+    };
+
+    let mut seen = HashMap::new();
+    let mut statement = BankStatement {
+        lines: Vec::new(),
+        skipped: Vec::new(),
+    };
+
+    for (index, row) in rows {
+        let fields = csv_fields(row, delimiter);
+        let field = |index: usize| fields.get(index).cloned().unwrap_or_default();
+
+        let parsed =
+            parse_date(&field(date)).and_then(|date| Ok((date, parse_amount(&field(amount))?)));
+
+        let (date, money) = match parsed {
+            Ok(value) => value,
+            Err(reason) => {
+                statement.skipped.push(SkippedRow {
+                    row: index + 1,
+                    content: row.to_string(),
+                    reason,
+                });
+                continue;
+            }
+        };
+
+        let text = field(text);
+        let name = name.map(field).filter(|name| !name.is_empty());
+        let iban = iban.map(field).and_then(|iban| normalize_iban(&iban));
+
+        statement.lines.push(BankLine {
+            import_id: import_id(&mut seen, &date, money, &text, &iban),
+            date,
+            money,
+            name,
+            iban,
+            text,
+        });
+    }
+
+    Ok(statement)
+}
+
+/// Whether `needle` occurs in `haystack` as a whole word.
+#[cfg(feature = "ssr")]
+fn contains_word(haystack: &str, needle: &str) -> bool {
+    if needle.is_empty() {
+        return false;
+    }
+
+    haystack.match_indices(needle).any(|(start, _)| {
+        let before = haystack[..start].chars().next_back();
+        let after = haystack[start + needle.len()..].chars().next();
+
+        !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+    })
+}
+
+/// Reads the user id of a payment reference like `SL-12` or `SL 12`.
+#[cfg(feature = "ssr")]
+fn find_payment_reference(text: &str) -> Option<i64> {
+    let text = text.to_uppercase();
+
+    text.match_indices(PAYMENT_REFERENCE_PREFIX)
+        .find_map(|(start, _)| {
+            if text[..start]
+                .chars()
+                .next_back()
+                .is_some_and(char::is_alphanumeric)
+            {
+                return None;
+            }
+
+            let rest =
+                text[start + PAYMENT_REFERENCE_PREFIX.len()..].trim_start_matches(['-', ' ']);
+            let digits = rest
+                .chars()
+                .take_while(char::is_ascii_digit)
+                .collect::<String>();
+
+            match rest[digits.len()..].chars().next() {
+                Some(c) if c.is_alphanumeric() => None,
+                _ => digits.parse().ok(),
+            }
+        })
+}
+
+/// Finds the user a payment is meant for, by the payment reference or else by
+/// the only nickname that appears in the reference or the name of the payer.
+#[cfg(feature = "ssr")]
+fn match_user<'a>(line: &BankLine, users: &'a [(UserId, String)]) -> Option<&'a (UserId, String)> {
+    if let Some(id) = find_payment_reference(&line.text) {
+        if let Some(user) = users.iter().find(|(user_id, _)| user_id.0 == id) {
+            return Some(user);
+        }
+    }
+
+    let haystack =
+        format!("{} {}", line.text, line.name.as_deref().unwrap_or_default()).to_lowercase();
+
+    let mut matches = users
+        .iter()
+        .filter(|(_, nickname)| contains_word(&haystack, &nickname.to_lowercase()));
+
+    match (matches.next(), matches.next()) {
+        (Some(user), None) => Some(user),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "ssr")]
+impl BankImportLine {
+    /// Matches the lines to active users and looks up which were booked.
+    pub async fn review<T>(conn: &mut T, lines: Vec<BankLine>) -> DatabaseResponse<Vec<Self>>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        let users = UserDB::get_all_unfiltered(&mut *conn)
+            .await?
+            .into_iter()
+            .filter(|user| !user.is_system_user && !user.is_archived)
+            .map(|user| (UserId(user.id), user.nickname))
+            .collect::<Vec<_>>();
+
+        let mut reviewed = Vec::with_capacity(lines.len());
+
+        for line in lines {
+            reviewed.push(BankImportLine {
+                user: match_user(&line, &users).cloned(),
+                is_booked: Self::is_booked(&mut *conn, &line.import_id).await?,
+                line,
+            });
+        }
+
+        Ok(reviewed)
+    }
+
+    pub async fn is_booked<T>(conn: &mut T, import_id: &str) -> DatabaseResponse<bool>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        query!(
+            "
+                select import_id from BankImportLines
+                where import_id = ?
+            ",
+            import_id
+        )
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(From::from)
+        .map(|elem| elem.is_some())
+    }
+
+    pub async fn set_booked<T>(
+        conn: &mut T,
+        import_id: &str,
+        transaction_id: DatabaseId,
+    ) -> DatabaseResponse<()>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        let now = Utc::now();
+
+        query!(
+            "
+                insert into BankImportLines (import_id, transaction_id, imported_at)
+                values (?, ?, ?)
+            ",
+            import_id,
+            transaction_id,
+            now
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(From::from)
+        .map(|_| ())
+    }
+}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+
+    const CSV: &str = "\
+Kontostand vom 31.10.2026;1.234,56 EUR
+
+Buchungstag;Name Zahlungspflichtige;IBAN Zahlungspflichtige;Verwendungszweck;Betrag (EUR)
+01.10.2026;Erika Mustermann;DE02 1203 0000 0000 2020 51;SL-12 Aufladung;1.020,50
+02.10.26;Max Mustermann;DE02120300000000202051;Miete;-300,00
+Endsaldo;;;;1.234,56 EUR
+";
+
+    const CAMT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Document><BkToCstmrStmt><Stmt>
+<Ntry>
+  <Amt Ccy="EUR">1020.50</Amt>
+  <CdtDbtInd>CRDT</CdtDbtInd>
+  <BookgDt><Dt>2026-10-01</Dt></BookgDt>
+  <AcctSvcrRef>2026100112345</AcctSvcrRef>
+  <NtryDtls><TxDtls>
+    <RltdPties>
+      <Dbtr><Nm>Erika Mustermann</Nm></Dbtr>
+      <DbtrAcct><Id><IBAN>DE02120300000000202051</IBAN></Id></DbtrAcct>
+    </RltdPties>
+    <RmtInf><Ustrd>SL-12</Ustrd><Ustrd>Aufladung</Ustrd></RmtInf>
+  </TxDtls></NtryDtls>
+</Ntry>
+</Stmt></BkToCstmrStmt></Document>
+"#;
+
+    #[test]
+    fn parses_german_amounts() {
+        for (amount, value) in [
+            ("1.020,50", 102050),
+            ("-300,00", -30000),
+            ("12,5", 1250),
+            ("1,234.56", 123456),
+            ("+7.00 EUR", 700),
+        ] {
+            assert_eq!(parse_amount(amount), Ok(Money { value }), "{amount}");
+        }
+    }
+
+    #[test]
+    fn csv_skips_and_reports_rows_that_are_no_payments() {
+        let statement = parse_csv(CSV).unwrap();
+
+        assert_eq!(statement.lines.len(), 2);
+        assert_eq!(statement.lines[0].date, "2026-10-01");
+        assert_eq!(statement.lines[0].money, Money { value: 102050 });
+        assert_eq!(
+            statement.lines[0].iban.as_deref(),
+            Some("DE02120300000000202051")
+        );
+        assert_eq!(statement.lines[1].date, "2026-10-02");
+        assert_eq!(statement.lines[1].money, Money { value: -30000 });
+
+        assert_eq!(statement.skipped.len(), 1);
+        assert_eq!(statement.skipped[0].row, 6);
+        assert_eq!(statement.skipped[0].content, "Endsaldo;;;;1.234,56 EUR");
+    }
+
+    #[test]
+    fn statement_keeps_incoming_payments() {
+        let statement = parse_bank_statement(CSV).unwrap();
+
+        assert_eq!(statement.lines.len(), 1);
+        assert_eq!(statement.lines[0].text, "SL-12 Aufladung");
+        assert_eq!(statement.skipped.len(), 1);
+    }
+
+    #[test]
+    fn camt_and_csv_lines_have_the_same_id() {
+        let camt = parse_bank_statement(CAMT).unwrap();
+        let csv = parse_bank_statement(CSV).unwrap();
+
+        assert_eq!(camt.lines.len(), 1);
+        assert_eq!(camt.lines[0].name.as_deref(), Some("Erika Mustermann"));
+        assert_eq!(camt.lines[0].import_id, csv.lines[0].import_id);
+    }
+}
@@ -0,0 +1,498 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{DatabaseId, Money};
+
+#[cfg(feature = "ssr")]
+use {
+    crate::backend::db::{DBError, DatabaseResponse, DatabaseType, DB, DBUSER_AUFLADUNG_ID},
+    sqlx::{query, query_as, Executor},
+};
+
+/// The coins and notes in the box, in cents.
+pub const DENOMINATIONS: [i64; 15] = [
+    50000, 20000, 10000, 5000, 2000, 1000, 500, 200, 100, 50, 20, 10, 5, 2, 1,
+];
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CashClosingKind {
+    /// made automatically at midnight
+    Daily,
+    /// made by hand
+    AdHoc,
+}
+
+impl CashClosingKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CashClosingKind::Daily => "daily",
+            CashClosingKind::AdHoc => "adhoc",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        [CashClosingKind::Daily, CashClosingKind::AdHoc]
+            .into_iter()
+            .find(|kind| kind.as_str() == value)
+    }
+}
+
+/// A count of the cash in the box. The difference to the expected cash is
+/// stored with the count only, no transaction is booked for it. Later counts
+/// and closings add it to the expected cash, so the expected cash afterwards
+/// is what was counted.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CashCount {
+    pub id: DatabaseId,
+    pub counted_at: DateTime<Utc>,
+    pub expected: Money,
+    pub counted: Money,
+    pub note: Option<String>,
+    /// `(denomination, quantity)`, largest denomination first
+    pub denominations: Vec<(i64, u32)>,
+}
+
+impl CashCount {
+    pub fn difference(&self) -> Money {
+        self.counted - self.expected
+    }
+}
+
+/// The state of the box at the end of a period. The periods of the closings
+/// follow each other without gaps.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CashClosing {
+    pub id: DatabaseId,
+    pub closed_at: DateTime<Utc>,
+    pub kind: CashClosingKind,
+    /// the expected cash at the previous closing
+    pub opening: Money,
+    pub deposits: Money,
+    pub withdrawals: Money,
+    /// the differences of the counts during the period
+    pub differences: Money,
+    /// the expected cash at this closing
+    pub closing: Money,
+}
+
+#[cfg(feature = "ssr")]
+pub struct CashClosingDB {
+    pub id: DatabaseId,
+    pub closed_at: DateTime<Utc>,
+    pub kind: String,
+    pub opening: i64,
+    pub deposits: i64,
+    pub withdrawals: i64,
+    pub differences: i64,
+    pub closing: i64,
+}
+
+#[cfg(feature = "ssr")]
+impl TryFrom<CashClosingDB> for CashClosing {
+    type Error = DBError;
+
+    fn try_from(value: CashClosingDB) -> Result<Self, Self::Error> {
+        let kind = match CashClosingKind::parse(&value.kind) {
+            Some(value) => value,
+            None => {
+                return Err(DBError::new(format!(
+                    "Invalid kind '{}' of cash closing {}",
+                    value.kind, value.id
+                )))
+            }
+        };
+
+        Ok(CashClosing {
+            id: value.id,
+            closed_at: value.closed_at,
+            kind,
+            opening: value.opening.into(),
+            deposits: value.deposits.into(),
+            withdrawals: value.withdrawals.into(),
+            differences: value.differences.into(),
+            closing: value.closing.into(),
+        })
+    }
+}
+
+/// The cash that went in and out of the box, computed from the shares of the
+/// top-up account. Undoing a deposit counts as a withdrawal and vice versa.
+/// Bank transfers booked by the bank import and balance corrections never pass
+/// the box.
+#[cfg(feature = "ssr")]
+pub struct CashMovements {
+    pub deposits: i64,
+    pub withdrawals: i64,
+}
+
+#[cfg(feature = "ssr")]
+impl CashMovements {
+    /// The movements after `from` up to and including `to`.
+    pub async fn get<T>(
+        conn: &mut T,
+        from: Option<DateTime<Utc>>,
+        to: DateTime<Utc>,
+    ) -> DatabaseResponse<Self>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        let from = from.unwrap_or(DateTime::<Utc>::MIN_UTC);
+
+        query!(
+            r#"
+                select
+                    coalesce(sum(case when delta < 0 then -delta else 0 end), 0) as "deposits!: i64",
+                    coalesce(sum(case when delta > 0 then delta else 0 end), 0) as "withdrawals!: i64"
+                from TransactionShares
+                join Transactions on Transactions.id = TransactionShares.transaction_id
+                where TransactionShares.user_id = ?
+                    and Transactions.timestamp > ?
+                    and Transactions.timestamp <= ?
+                    and not Transactions.is_correction
+                    and coalesce(Transactions.reverses_id, Transactions.id) not in (
+                        select transaction_id from BankImportLines
+                    )
+            "#,
+            DBUSER_AUFLADUNG_ID.0,
+            from,
+            to
+        )
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(From::from)
+        .map(|row| CashMovements {
+            deposits: row.deposits,
+            withdrawals: row.withdrawals,
+        })
+    }
+}
+
+#[cfg(feature = "ssr")]
+impl CashCount {
+    /// The sum of the differences of all counts after `from` up to and
+    /// including `to`.
+    pub async fn get_differences<T>(
+        conn: &mut T,
+        from: Option<DateTime<Utc>>,
+        to: DateTime<Utc>,
+    ) -> DatabaseResponse<i64>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        let from = from.unwrap_or(DateTime::<Utc>::MIN_UTC);
+
+        query!(
+            r#"
+                select coalesce(sum(counted - expected), 0) as "differences!: i64"
+                from CashCounts
+                where counted_at > ? and counted_at <= ?
+            "#,
+            from,
+            to
+        )
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(From::from)
+        .map(|row| row.differences)
+    }
+
+    /// The cash that should be in the box at `at`.
+    pub async fn get_expected<T>(conn: &mut T, at: DateTime<Utc>) -> DatabaseResponse<Money>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        let movements = CashMovements::get(&mut *conn, None, at).await?;
+        let differences = CashCount::get_differences(&mut *conn, None, at).await?;
+
+        Ok(Money {
+            value: movements.deposits - movements.withdrawals + differences,
+        })
+    }
+
+    /// Stores a count of the box, `denominations` holds the quantity of each
+    /// coin or note.
+    pub async fn create<T>(
+        conn: &mut T,
+        denominations: &[(i64, u32)],
+        note: Option<String>,
+    ) -> DatabaseResponse<DatabaseId>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        if let Some((denomination, _)) = denominations
+            .iter()
+            .find(|(denomination, _)| !DENOMINATIONS.contains(denomination))
+        {
+            return Err(DBError::new(format!(
+                "Invalid denomination {}",
+                Money::format_eur_value(*denomination)
+            )));
+        }
+
+        let now = Utc::now();
+        let expected = CashCount::get_expected(&mut *conn, now).await?;
+        let counted = denominations
+            .iter()
+            .map(|(denomination, quantity)| denomination * *quantity as i64)
+            .sum::<i64>();
+
+        let id = query!(
+            "
+                insert into CashCounts
+                    (counted_at, expected, counted, note)
+                values
+                    (?, ?, ?, ?)
+                returning id
+            ",
+            now,
+            expected.value,
+            counted,
+            note
+        )
+        .fetch_one(&mut *conn)
+        .await?
+        .id;
+
+        for (denomination, quantity) in denominations.iter().filter(|(_, quantity)| *quantity > 0) {
+            query!(
+                "
+                    insert into CashCountDenominations
+                        (count_id, denomination, quantity)
+                    values
+                        (?, ?, ?)
+                ",
+                id,
+                denomination,
+                quantity
+            )
+            .execute(&mut *conn)
+            .await?;
+        }
+
+        Ok(id)
+    }
+
+    /// The latest counts, newest first.
+    pub async fn get_latest<T>(conn: &mut T, limit: u64) -> DatabaseResponse<Vec<Self>>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        let limit = limit as i64;
+
+        let rows = query!(
+            r#"
+                select
+                    id,
+                    counted_at as "counted_at: DateTime<Utc>",
+                    expected,
+                    counted,
+                    note
+                from CashCounts
+                order by counted_at desc, id desc
+                limit ?
+            "#,
+            limit
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let mut counts = Vec::with_capacity(rows.len());
+
+        for row in rows.into_iter() {
+            let denominations = query!(
+                "
+                    select denomination, quantity
+                    from CashCountDenominations
+                    where count_id = ?
+                    order by denomination desc
+                ",
+                row.id
+            )
+            .fetch_all(&mut *conn)
+            .await?
+            .into_iter()
+            .map(|elem| (elem.denomination, elem.quantity as u32))
+            .collect();
+
+            counts.push(CashCount {
+                id: row.id,
+                counted_at: row.counted_at,
+                expected: row.expected.into(),
+                counted: row.counted.into(),
+                note: row.note,
+                denominations,
+            });
+        }
+
+        Ok(counts)
+    }
+}
+
+#[cfg(feature = "ssr")]
+impl CashClosing {
+    async fn get_previous<T>(
+        conn: &mut T,
+        at: DateTime<Utc>,
+    ) -> DatabaseResponse<Option<CashClosingDB>>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        query_as!(
+            CashClosingDB,
+            r#"
+                select
+                    id,
+                    closed_at as "closed_at: DateTime<Utc>",
+                    kind,
+                    opening,
+                    deposits,
+                    withdrawals,
+                    differences,
+                    closing
+                from CashClosings
+                where closed_at <= ?
+                order by closed_at desc, id desc
+                limit 1
+            "#,
+            at
+        )
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(From::from)
+    }
+
+    /// Closes the period since the previous closing at `at`.
+    pub async fn create<T>(
+        conn: &mut T,
+        kind: CashClosingKind,
+        at: DateTime<Utc>,
+    ) -> DatabaseResponse<DatabaseId>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        let previous = CashClosing::get_previous(&mut *conn, at).await?;
+        let (from, opening) = match previous {
+            Some(value) => (Some(value.closed_at), value.closing),
+            None => (None, 0),
+        };
+
+        let movements = CashMovements::get(&mut *conn, from, at).await?;
+        let differences = CashCount::get_differences(&mut *conn, from, at).await?;
+        let closing = opening + movements.deposits - movements.withdrawals + differences;
+        let kind = kind.as_str();
+
+        query!(
+            "
+                insert into CashClosings
+                    (closed_at, kind, opening, deposits, withdrawals, differences, closing)
+                values
+                    (?, ?, ?, ?, ?, ?, ?)
+                returning id
+            ",
+            at,
+            kind,
+            opening,
+            movements.deposits,
+            movements.withdrawals,
+            differences,
+            closing
+        )
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(From::from)
+        .map(|row| row.id)
+    }
+
+    /// All closings, newest first, `limit` 0 means no limit.
+    pub async fn get_latest<T>(conn: &mut T, limit: u64) -> DatabaseResponse<Vec<Self>>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        let limit = match limit {
+            0 => -1,
+            limit => limit as i64,
+        };
+
+        query_as!(
+            CashClosingDB,
+            r#"
+                select
+                    id,
+                    closed_at as "closed_at: DateTime<Utc>",
+                    kind,
+                    opening,
+                    deposits,
+                    withdrawals,
+                    differences,
+                    closing
+                from CashClosings
+                order by closed_at desc, id desc
+                limit ?
+            "#,
+            limit
+        )
+        .fetch_all(&mut *conn)
+        .await?
+        .into_iter()
+        .map(TryInto::try_into)
+        .collect()
+    }
+
+    /// Makes the daily closing of the last day if there is no closing since
+    /// midnight yet. Days the server was down for are covered by the next
+    /// daily closing. Returns whether a closing was made.
+    pub async fn close_day(db: &DB) -> DatabaseResponse<bool> {
+        let midnight = Utc::now()
+            .date_naive()
+            .and_hms_opt(0, 0, 0)
+            .map(|value| value.and_utc())
+            .ok_or_else(|| DBError::new("Failed to compute midnight"))?;
+
+        let mut db_trans = db.get_conn_transaction().await?;
+
+        let exists = query!(
+            r#"
+                select count(*) as "count!: i64"
+                from CashClosings
+                where closed_at >= ?
+            "#,
+            midnight
+        )
+        .fetch_one(&mut *db_trans)
+        .await?
+        .count
+            > 0;
+
+        if exists {
+            return Ok(false);
+        }
+
+        CashClosing::create(&mut *db_trans, CashClosingKind::Daily, midnight).await?;
+
+        db_trans.commit().await.map_err(DBError::new)?;
+
+        Ok(true)
+    }
+
+    /// The closings as CSV, amounts in euros.
+    pub fn to_csv(closings: &[CashClosing]) -> String {
+        let mut csv =
+            String::from("id,closed_at,kind,opening,deposits,withdrawals,differences,closing\n");
+
+        for closing in closings.iter() {
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{},{}\n",
+                closing.id,
+                closing.closed_at.to_rfc3339(),
+                closing.kind.as_str(),
+                closing.opening.format(),
+                closing.deposits.format(),
+                closing.withdrawals.format(),
+                closing.differences.format(),
+                closing.closing.format(),
+            ));
+        }
+
+        csv
+    }
+}
@@ -85,6 +85,7 @@ impl From<DatabaseId> for GroupDB {
 }
 
 impl GroupDB {
+    /// Members ordered by id, so remainder cents of equal shares always go to the same users.
     pub async fn get_members<T>(conn: &mut T, gid: DatabaseId) -> DatabaseResponse<Vec<UserDB>>
     where
         for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
@@ -96,6 +97,7 @@ impl GroupDB {
                     from UserGroupMap
                 join Users on Users.id = UserGroupMap.uid 
                     where UserGroupMap.gid = ?
+                order by Users.id
             ",
             gid
         )
@@ -140,6 +142,85 @@ impl GroupDB {
         .map(From::from)
     }
 
+    pub async fn unlink_user<T>(&self, conn: &mut T, user_id: UserId) -> DatabaseResponse<()>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        query!(
+            "
+                delete from UserGroupMap
+                where gid = ? and uid = ?
+            ",
+            self.id,
+            user_id.0
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(From::from)
+        .map(|_| ())
+    }
+
+    /// The group must not be referenced by any transactions anymore.
+    pub async fn delete<T>(self, conn: &mut T) -> DatabaseResponse<()>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        query!(
+            "
+                delete from UserGroupMap
+                where gid = ?
+            ",
+            self.id
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        query!(
+            "
+                delete from Groups
+                where id = ?
+            ",
+            self.id
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(From::from)
+        .map(|_| ())
+    }
+
+    /// Finds the group that consists of exactly the given users.
+    pub async fn get_group_by_members<T>(
+        conn: &mut T,
+        user_ids: &[UserId],
+    ) -> DatabaseResponse<Option<Self>>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        let mut user_ids = user_ids.iter().map(|id| id.0).collect_vec();
+        user_ids.sort();
+        user_ids.dedup();
+
+        let first = match user_ids.first() {
+            Some(value) => *value,
+            None => return Ok(None),
+        };
+
+        for group in GroupDB::get_groups(&mut *conn, UserId(first)).await? {
+            let mut members = GroupDB::get_members(&mut *conn, group.id)
+                .await?
+                .into_iter()
+                .map(|member| member.id)
+                .collect_vec();
+            members.sort();
+
+            if members == user_ids {
+                return Ok(Some(group));
+            }
+        }
+
+        Ok(None)
+    }
+
     pub async fn link_user<T>(&self, conn: &mut T, user_id: UserId) -> DatabaseResponse<()>
     where
         for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
@@ -0,0 +1,168 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use chrono::Utc;
+
+#[cfg(feature = "ssr")]
+use {
+    super::{DatabaseId, UserId},
+    crate::backend::db::{DBError, DatabaseResponse, DatabaseType},
+    chrono::Duration,
+    serde::Serialize,
+    sqlx::{query, Executor},
+};
+
+static KEY_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Creates a key for a single booking attempt. It is sent along with every
+/// retry of that attempt, so the server books it only once.
+pub fn new_idempotency_key() -> String {
+    format!(
+        "{}-{}",
+        Utc::now().timestamp_nanos_opt().unwrap_or_default(),
+        KEY_COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/// Remembers which transaction a client-generated key of a user booked.
+#[cfg(feature = "ssr")]
+pub struct IdempotencyKey;
+
+/// Why a booking with [`IdempotencyKey::book_once`] failed.
+#[cfg(feature = "ssr")]
+#[derive(Debug)]
+pub enum BookOnceError<E> {
+    /// the key already booked a request with other parameters
+    KeyReused,
+    /// looking up or storing the key failed
+    Database(DBError),
+    /// the booking itself failed
+    Booking(E),
+}
+
+#[cfg(feature = "ssr")]
+impl IdempotencyKey {
+    /// Books with `book` unless `key` already booked a transaction in the last
+    /// `window` seconds and returns the booked transaction. `params` are the
+    /// parameters of the request, a key sent again with other parameters is
+    /// rejected. Without a key `book` always books.
+    pub async fn book_once<T, E>(
+        conn: &mut T,
+        user_id: UserId,
+        key: Option<&str>,
+        params: &impl Serialize,
+        window: u64,
+        book: impl AsyncFnOnce(&mut T) -> Result<DatabaseId, E>,
+    ) -> Result<DatabaseId, BookOnceError<E>>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        let key = match key.filter(|key| !key.is_empty()) {
+            Some(value) => value,
+            None => return book(&mut *conn).await.map_err(BookOnceError::Booking),
+        };
+        // stored as is, so a retry compares equal after restarts and upgrades
+        let params =
+            serde_json::to_string(params).map_err(|e| BookOnceError::Database(DBError::new(e)))?;
+
+        match IdempotencyKey::get_transaction(&mut *conn, user_id, key, window)
+            .await
+            .map_err(BookOnceError::Database)?
+        {
+            Some((transaction_id, booked_params)) if booked_params == params => {
+                return Ok(transaction_id)
+            }
+            Some(_) => return Err(BookOnceError::KeyReused),
+            None => {}
+        }
+
+        let transaction_id = book(&mut *conn).await.map_err(BookOnceError::Booking)?;
+
+        IdempotencyKey::store(&mut *conn, user_id, key, &params, transaction_id, window)
+            .await
+            .map_err(BookOnceError::Database)?;
+
+        Ok(transaction_id)
+    }
+
+    /// Returns the transaction booked with `key` in the last `window` seconds
+    /// and the parameters it was booked with.
+    async fn get_transaction<T>(
+        conn: &mut T,
+        user_id: UserId,
+        key: &str,
+        window: u64,
+    ) -> DatabaseResponse<Option<(DatabaseId, String)>>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        let since = Utc::now() - Duration::seconds(window as i64);
+
+        query!(
+            "
+                select transaction_id, params from IdempotencyKeys
+                where key = ? and user_id = ? and created_at >= ?
+            ",
+            key,
+            user_id.0,
+            since
+        )
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(From::from)
+        .map(|elem| elem.map(|elem| (elem.transaction_id, elem.params)))
+    }
+
+    /// Stores the transaction booked with `key`, keys older than `window`
+    /// seconds are dropped on the way.
+    async fn store<T>(
+        conn: &mut T,
+        user_id: UserId,
+        key: &str,
+        params: &str,
+        transaction_id: DatabaseId,
+        window: u64,
+    ) -> DatabaseResponse<()>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        let now = Utc::now();
+        let since = now - Duration::seconds(window as i64);
+
+        query!("delete from IdempotencyKeys where created_at < ?", since)
+            .execute(&mut *conn)
+            .await?;
+
+        query!(
+            "
+                insert into IdempotencyKeys (key, user_id, params, transaction_id, created_at)
+                values (?, ?, ?, ?, ?)
+                on conflict (key, user_id) do update
+                set
+                    params = excluded.params,
+                    transaction_id = excluded.transaction_id,
+                    created_at = excluded.created_at
+            ",
+            key,
+            user_id.0,
+            params,
+            transaction_id,
+            now
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(From::from)
+        .map(|_| ())
+    }
+
+    /// Drops the keys of a user, e.g. when the user is merged into another.
+    pub async fn delete_user<T>(conn: &mut T, user_id: UserId) -> DatabaseResponse<()>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        query!("delete from IdempotencyKeys where user_id = ?", user_id.0)
+            .execute(&mut *conn)
+            .await
+            .map_err(From::from)
+            .map(|_| ())
+    }
+}
@@ -1,9 +1,20 @@
 pub use article::*;
 pub use audio::*;
+pub use balance::*;
+pub use bank_import::*;
+pub use cash_box::*;
+#[cfg(feature = "ssr")]
+pub use epc_qr::*;
 #[cfg(feature = "ssr")]
 pub use group::*;
+pub use idempotency::*;
 pub use money::*;
+pub use money_request::*;
 pub use page::*;
+pub use recurring::*;
+pub use settle_up::*;
+pub use split::*;
+pub use stock::*;
 pub use transaction::*;
 pub use user::*;
 
@@ -11,10 +22,21 @@ mod article;
 #[cfg(feature = "ssr")]
 mod group;
 mod money;
+mod money_request;
 mod transaction;
 mod user;
 
 mod audio;
+mod balance;
+mod bank_import;
+mod cash_box;
+#[cfg(feature = "ssr")]
+mod epc_qr;
+mod idempotency;
 mod page;
+mod recurring;
+mod settle_up;
+mod split;
+mod stock;
 
 pub type DatabaseId = i64;
@@ -91,6 +91,12 @@ impl TryFrom<String> for Money {
         #[allow(unused_assignments)] // euros is never read
         let string = value.replace(",", ".");
 
+        // the sign applies to the whole value, not just the euros
+        let (negative, string) = match string.strip_prefix("-") {
+            Some(rest) => (true, rest.to_string()),
+            None => (false, string),
+        };
+
         let (euros, mut cents) = match string.rsplit_once(".") {
             Some(split) => (split.0.to_string(), split.1.to_string()),
             None => (string, 0.to_string()),
@@ -116,6 +122,20 @@ impl TryFrom<String> for Money {
             cents.push('0');
         }
 
+        if euros.starts_with(['-', '+']) {
+            return Err(MoneyParseError::InvalidEuros(format!(
+                "Failed to parse euros: {}",
+                euros
+            )));
+        }
+
+        if !cents.chars().all(|c| c.is_ascii_digit()) {
+            return Err(MoneyParseError::InvalidCents(format!(
+                "Failed to parse cents: {}",
+                cents
+            )));
+        }
+
         let real_euros = match euros.parse::<i64>() {
             Ok(real_euros) => real_euros,
             Err(_) => {
@@ -136,7 +156,11 @@ impl TryFrom<String> for Money {
             }
         };
 
-        let final_cents = real_euros * 100 + real_cents;
+        let mut final_cents = real_euros * 100 + real_cents;
+
+        if negative {
+            final_cents = -final_cents;
+        }
 
         Ok(Money { value: final_cents })
     }
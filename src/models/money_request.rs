@@ -0,0 +1,392 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{DatabaseId, Money, UserId};
+
+#[cfg(feature = "ssr")]
+use {
+    super::{GroupId, Transaction, TransactionType},
+    crate::backend::{
+        db::{DBError, DatabaseResponse, DatabaseType},
+        Settings,
+    },
+    crate::models::GroupDB,
+    crate::routes::user::CreateTransactionError,
+    sqlx::{query, query_as, Executor},
+};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MoneyRequestStatus {
+    Pending,
+    Accepted,
+    Declined,
+    /// a pending request that wasn't answered in time, this is never stored
+    Expired,
+}
+
+impl MoneyRequestStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MoneyRequestStatus::Pending => "pending",
+            MoneyRequestStatus::Accepted => "accepted",
+            MoneyRequestStatus::Declined => "declined",
+            MoneyRequestStatus::Expired => "expired",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        [
+            MoneyRequestStatus::Pending,
+            MoneyRequestStatus::Accepted,
+            MoneyRequestStatus::Declined,
+            MoneyRequestStatus::Expired,
+        ]
+        .into_iter()
+        .find(|status| status.as_str() == value)
+    }
+}
+
+/// A request of `requester` to be paid `money` by `debtor`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct MoneyRequest {
+    pub id: DatabaseId,
+    pub requester: UserId,
+    pub requester_name: String,
+    pub debtor: UserId,
+    pub debtor_name: String,
+    pub money: Money,
+    pub description: Option<String>,
+    pub status: MoneyRequestStatus,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub resolved_at: Option<DateTime<Utc>>,
+}
+
+#[cfg(feature = "ssr")]
+pub struct MoneyRequestDB {
+    pub id: DatabaseId,
+    pub requester: i64,
+    pub requester_name: String,
+    pub debtor: i64,
+    pub debtor_name: String,
+    pub money: i64,
+    pub description: Option<String>,
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub resolved_at: Option<DateTime<Utc>>,
+}
+
+#[cfg(feature = "ssr")]
+impl TryFrom<MoneyRequestDB> for MoneyRequest {
+    type Error = DBError;
+
+    fn try_from(value: MoneyRequestDB) -> Result<Self, Self::Error> {
+        let status = match MoneyRequestStatus::parse(&value.status) {
+            Some(MoneyRequestStatus::Pending) if value.expires_at < Utc::now() => {
+                MoneyRequestStatus::Expired
+            }
+            Some(status) => status,
+            None => {
+                return Err(DBError::new(format!(
+                    "Invalid status '{}' of money request {}",
+                    value.status, value.id
+                )))
+            }
+        };
+
+        Ok(MoneyRequest {
+            id: value.id,
+            requester: UserId(value.requester),
+            requester_name: value.requester_name,
+            debtor: UserId(value.debtor),
+            debtor_name: value.debtor_name,
+            money: value.money.into(),
+            description: value.description,
+            status,
+            created_at: value.created_at,
+            expires_at: value.expires_at,
+            resolved_at: value.resolved_at,
+        })
+    }
+}
+
+#[cfg(feature = "ssr")]
+impl MoneyRequestDB {
+    pub async fn create<T>(
+        conn: &mut T,
+        requester: UserId,
+        debtor: UserId,
+        money: Money,
+        description: Option<String>,
+        expires_at: DateTime<Utc>,
+    ) -> DatabaseResponse<DatabaseId>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        let now = Utc::now();
+
+        query!(
+            "
+                insert into MoneyRequests
+                    (requester, debtor, money, description, created_at, expires_at)
+                values
+                    (?, ?, ?, ?, ?, ?)
+                returning id
+            ",
+            requester.0,
+            debtor.0,
+            money.value,
+            description,
+            now,
+            expires_at
+        )
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(From::from)
+        .map(|elem| elem.id)
+    }
+
+    pub async fn get<T>(conn: &mut T, id: DatabaseId) -> DatabaseResponse<Option<Self>>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        query_as!(
+            MoneyRequestDB,
+            r#"
+                select
+                    MoneyRequests.id,
+                    requester,
+                    Requesters.nickname as requester_name,
+                    debtor,
+                    Debtors.nickname as debtor_name,
+                    MoneyRequests.money,
+                    description,
+                    status,
+                    created_at as "created_at: DateTime<Utc>",
+                    expires_at as "expires_at: DateTime<Utc>",
+                    resolved_at as "resolved_at: DateTime<Utc>"
+                from MoneyRequests
+                join Users as Requesters on Requesters.id = MoneyRequests.requester
+                join Users as Debtors on Debtors.id = MoneyRequests.debtor
+                where MoneyRequests.id = ?
+            "#,
+            id
+        )
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(From::from)
+    }
+
+    /// All open requests the user made or received and the latest `limit`
+    /// answered or expired ones.
+    pub async fn get_for_user<T>(
+        conn: &mut T,
+        user_id: UserId,
+        limit: u64,
+    ) -> DatabaseResponse<Vec<Self>>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        let limit = limit as i64;
+        let now = Utc::now();
+
+        query_as!(
+            MoneyRequestDB,
+            r#"
+                select
+                    MoneyRequests.id,
+                    requester,
+                    Requesters.nickname as requester_name,
+                    debtor,
+                    Debtors.nickname as debtor_name,
+                    MoneyRequests.money,
+                    description,
+                    status,
+                    created_at as "created_at: DateTime<Utc>",
+                    expires_at as "expires_at: DateTime<Utc>",
+                    resolved_at as "resolved_at: DateTime<Utc>"
+                from MoneyRequests
+                join Users as Requesters on Requesters.id = MoneyRequests.requester
+                join Users as Debtors on Debtors.id = MoneyRequests.debtor
+                where (requester = ? or debtor = ?)
+                    and (
+                        (status = 'pending' and expires_at >= ?)
+                        or MoneyRequests.id in (
+                            select Closed.id from MoneyRequests as Closed
+                            where (Closed.requester = ? or Closed.debtor = ?)
+                                and (Closed.status != 'pending' or Closed.expires_at < ?)
+                            order by Closed.created_at desc, Closed.id desc
+                            limit ?
+                        )
+                    )
+                order by created_at desc, MoneyRequests.id desc
+            "#,
+            user_id.0,
+            user_id.0,
+            now,
+            user_id.0,
+            user_id.0,
+            now,
+            limit
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(From::from)
+    }
+
+    /// Hands the requests of `from` over to `to`. Pending requests between
+    /// the two are declined, nobody can pay themselves.
+    pub async fn move_user<T>(conn: &mut T, from: UserId, to: UserId) -> DatabaseResponse<()>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        let now = Utc::now();
+
+        query!(
+            "
+                update MoneyRequests
+                set status = 'declined', resolved_at = ?
+                where status = 'pending'
+                    and ((requester = ? and debtor = ?) or (requester = ? and debtor = ?))
+            ",
+            now,
+            from.0,
+            to.0,
+            to.0,
+            from.0
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        query!(
+            "
+                update MoneyRequests
+                set requester = ?
+                where requester = ?
+            ",
+            to.0,
+            from.0
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        query!(
+            "
+                update MoneyRequests
+                set debtor = ?
+                where debtor = ?
+            ",
+            to.0,
+            from.0
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(From::from)
+        .map(|_| ())
+    }
+
+    /// Answers a pending request, returns false if it was already answered.
+    pub async fn resolve<T>(
+        conn: &mut T,
+        id: DatabaseId,
+        status: MoneyRequestStatus,
+        transaction_id: Option<DatabaseId>,
+    ) -> DatabaseResponse<bool>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        let now = Utc::now();
+        let status = status.as_str();
+
+        query!(
+            "
+                update MoneyRequests
+                set status = ?, resolved_at = ?, transaction_id = ?
+                where id = ? and status = 'pending'
+            ",
+            status,
+            now,
+            transaction_id,
+            id
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(From::from)
+        .map(|result| result.rows_affected() == 1)
+    }
+}
+
+#[cfg(feature = "ssr")]
+impl MoneyRequest {
+    pub async fn get<T>(conn: &mut T, id: DatabaseId) -> DatabaseResponse<Option<Self>>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        MoneyRequestDB::get(&mut *conn, id)
+            .await?
+            .map(TryInto::try_into)
+            .transpose()
+    }
+
+    pub async fn get_for_user<T>(
+        conn: &mut T,
+        user_id: UserId,
+        limit: u64,
+    ) -> DatabaseResponse<Vec<Self>>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        MoneyRequestDB::get_for_user(&mut *conn, user_id, limit)
+            .await?
+            .into_iter()
+            .map(TryInto::try_into)
+            .collect()
+    }
+
+    /// Pays the request by sending the money from the debtor to the requester.
+    pub async fn accept<T>(
+        &self,
+        conn: &mut T,
+        settings: &Settings,
+    ) -> Result<DatabaseId, CreateTransactionError>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        type Error = CreateTransactionError;
+
+        if self.status != MoneyRequestStatus::Pending {
+            return Err(Error::new(&format!(
+                "The request is {}!",
+                self.status.as_str()
+            )));
+        }
+
+        let debtor_group = GroupId(GroupDB::get_single_group(&mut *conn, self.debtor).await?);
+        let requester_group = GroupId(GroupDB::get_single_group(&mut *conn, self.requester).await?);
+
+        let transaction_id = Transaction::create(
+            &mut *conn,
+            debtor_group,
+            requester_group,
+            TransactionType::Sent(requester_group),
+            self.description.clone(),
+            self.money,
+            settings,
+        )
+        .await?;
+
+        if !MoneyRequestDB::resolve(
+            &mut *conn,
+            self.id,
+            MoneyRequestStatus::Accepted,
+            Some(transaction_id),
+        )
+        .await?
+        {
+            return Err(Error::new("The request was already answered!"));
+        }
+
+        Ok(transaction_id)
+    }
+}
@@ -0,0 +1,609 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{DatabaseId, Money, UserId};
+
+#[cfg(feature = "ssr")]
+use {
+    super::{GroupId, Transaction, TransactionType},
+    crate::backend::{
+        db::{DBError, DatabaseResponse, DatabaseType, DB},
+        Settings,
+    },
+    crate::models::GroupDB,
+    chrono::{Duration, Months},
+    sqlx::{query, query_as, Executor},
+    tracing::{error, info},
+};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChargeInterval {
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+impl ChargeInterval {
+    pub const ALL: [ChargeInterval; 3] = [
+        ChargeInterval::Weekly,
+        ChargeInterval::Monthly,
+        ChargeInterval::Yearly,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChargeInterval::Weekly => "weekly",
+            ChargeInterval::Monthly => "monthly",
+            ChargeInterval::Yearly => "yearly",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        ChargeInterval::ALL
+            .into_iter()
+            .find(|interval| interval.as_str() == value)
+    }
+
+    /// The start of the `period`th interval after `start`.
+    #[cfg(feature = "ssr")]
+    pub fn period_start(&self, start: DateTime<Utc>, period: u32) -> Option<DateTime<Utc>> {
+        match self {
+            ChargeInterval::Weekly => start.checked_add_signed(Duration::weeks(period as i64)),
+            ChargeInterval::Monthly => start.checked_add_months(Months::new(period)),
+            ChargeInterval::Yearly => {
+                start.checked_add_months(Months::new(period.checked_mul(12)?))
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct RecurringChargeUser {
+    pub user_id: UserId,
+    pub nickname: String,
+    /// the user is charged for all periods starting after this
+    pub since: DateTime<Utc>,
+    /// why booking the charge failed, it isn't booked again until retried
+    pub failure: Option<String>,
+    pub failed_at: Option<DateTime<Utc>>,
+}
+
+/// A charge that is booked from each of its users to a system account once
+/// per interval.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct RecurringCharge {
+    pub id: DatabaseId,
+    pub description: String,
+    pub money: Money,
+    pub receiver: UserId,
+    pub receiver_name: String,
+    pub interval: ChargeInterval,
+    pub start: DateTime<Utc>,
+    pub is_active: bool,
+    pub users: Vec<RecurringChargeUser>,
+}
+
+#[cfg(feature = "ssr")]
+pub struct RecurringChargeDB {
+    pub id: DatabaseId,
+    pub description: String,
+    pub money: i64,
+    pub receiver: i64,
+    pub interval: String,
+    pub start: DateTime<Utc>,
+    pub is_active: bool,
+}
+
+#[cfg(feature = "ssr")]
+impl RecurringChargeDB {
+    pub async fn create<T>(
+        conn: &mut T,
+        description: String,
+        money: Money,
+        receiver: UserId,
+        interval: ChargeInterval,
+        start: DateTime<Utc>,
+    ) -> DatabaseResponse<DatabaseId>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        let interval = interval.as_str();
+
+        query!(
+            "
+                insert into RecurringCharges
+                    (description, money, receiver, interval, start)
+                values
+                    (?, ?, ?, ?, ?)
+                returning id
+            ",
+            description,
+            money.value,
+            receiver.0,
+            interval,
+            start
+        )
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(From::from)
+        .map(|elem| elem.id)
+    }
+
+    pub async fn get_all<T>(conn: &mut T) -> DatabaseResponse<Vec<Self>>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        query_as!(
+            RecurringChargeDB,
+            r#"
+                select
+                    id,
+                    description,
+                    money,
+                    receiver,
+                    interval,
+                    start as "start: DateTime<Utc>",
+                    is_active
+                from RecurringCharges
+                order by id
+            "#
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(From::from)
+    }
+
+    pub async fn set_active<T>(
+        conn: &mut T,
+        id: DatabaseId,
+        is_active: bool,
+    ) -> DatabaseResponse<()>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        query!(
+            "
+                update RecurringCharges
+                set is_active = ?
+                where id = ?
+            ",
+            is_active,
+            id
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(From::from)
+        .map(|_| ())
+    }
+
+    pub async fn add_user<T>(
+        conn: &mut T,
+        id: DatabaseId,
+        user_id: UserId,
+        since: DateTime<Utc>,
+    ) -> DatabaseResponse<()>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        query!(
+            "
+                insert into RecurringChargeUsers
+                    (charge_id, user_id, since)
+                values
+                    (?, ?, ?)
+            ",
+            id,
+            user_id.0,
+            since
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(From::from)
+        .map(|_| ())
+    }
+
+    pub async fn remove_user<T>(
+        conn: &mut T,
+        id: DatabaseId,
+        user_id: UserId,
+    ) -> DatabaseResponse<()>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        query!(
+            "
+                delete from RecurringChargeUsers
+                where charge_id = ? and user_id = ?
+            ",
+            id,
+            user_id.0
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(From::from)
+        .map(|_| ())
+    }
+
+    pub async fn get_users<T>(
+        conn: &mut T,
+        id: DatabaseId,
+    ) -> DatabaseResponse<Vec<RecurringChargeUser>>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        query!(
+            r#"
+                select
+                    Users.id,
+                    Users.nickname,
+                    RecurringChargeUsers.since as "since: DateTime<Utc>",
+                    RecurringChargeUsers.failure,
+                    RecurringChargeUsers.failed_at as "failed_at: DateTime<Utc>"
+                from RecurringChargeUsers
+                join Users on Users.id = RecurringChargeUsers.user_id
+                where RecurringChargeUsers.charge_id = ?
+                order by Users.nickname
+            "#,
+            id
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(From::from)
+        .map(|rows| {
+            rows.into_iter()
+                .map(|row| RecurringChargeUser {
+                    user_id: UserId(row.id),
+                    nickname: row.nickname,
+                    since: row.since,
+                    failure: row.failure,
+                    failed_at: row.failed_at,
+                })
+                .collect()
+        })
+    }
+
+    pub async fn get_last_period<T>(
+        conn: &mut T,
+        id: DatabaseId,
+        user_id: UserId,
+    ) -> DatabaseResponse<Option<i64>>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        query!(
+            r#"
+                select max(period) as "period: i64"
+                from RecurringChargeBookings
+                where charge_id = ? and user_id = ?
+            "#,
+            id,
+            user_id.0
+        )
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(From::from)
+        .map(|row| row.period)
+    }
+
+    /// Hands the charges, subscriptions and bookings of `from` over to `to`.
+    /// Subscriptions and bookings `to` already has are dropped.
+    pub async fn move_user<T>(conn: &mut T, from: UserId, to: UserId) -> DatabaseResponse<()>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        query!(
+            "
+                update RecurringCharges
+                set receiver = ?
+                where receiver = ?
+            ",
+            to.0,
+            from.0
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        query!(
+            "
+                delete from RecurringChargeUsers
+                where user_id = ? and charge_id in (
+                    select charge_id from RecurringChargeUsers
+                    where user_id = ?
+                )
+            ",
+            from.0,
+            to.0
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        query!(
+            "
+                update RecurringChargeUsers
+                set user_id = ?
+                where user_id = ?
+            ",
+            to.0,
+            from.0
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        query!(
+            "
+                delete from RecurringChargeBookings
+                where user_id = ? and exists (
+                    select * from RecurringChargeBookings as Existing
+                    where Existing.user_id = ?
+                        and Existing.charge_id = RecurringChargeBookings.charge_id
+                        and Existing.period = RecurringChargeBookings.period
+                )
+            ",
+            from.0,
+            to.0
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        query!(
+            "
+                update RecurringChargeBookings
+                set user_id = ?
+                where user_id = ?
+            ",
+            to.0,
+            from.0
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(From::from)
+        .map(|_| ())
+    }
+
+    /// Stops charging the user until [`Self::clear_failure`] is called.
+    pub async fn set_failure<T>(
+        conn: &mut T,
+        id: DatabaseId,
+        user_id: UserId,
+        failure: String,
+    ) -> DatabaseResponse<()>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        let now = Utc::now();
+
+        query!(
+            "
+                update RecurringChargeUsers
+                set failure = ?, failed_at = ?
+                where charge_id = ? and user_id = ?
+            ",
+            failure,
+            now,
+            id,
+            user_id.0
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(From::from)
+        .map(|_| ())
+    }
+
+    /// The charge is booked for the user again on the next run.
+    pub async fn clear_failure<T>(
+        conn: &mut T,
+        id: DatabaseId,
+        user_id: UserId,
+    ) -> DatabaseResponse<()>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        query!(
+            "
+                update RecurringChargeUsers
+                set failure = null, failed_at = null
+                where charge_id = ? and user_id = ?
+            ",
+            id,
+            user_id.0
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(From::from)
+        .map(|_| ())
+    }
+
+    /// Fails if the user was already charged for this period.
+    pub async fn insert_booking<T>(
+        conn: &mut T,
+        id: DatabaseId,
+        user_id: UserId,
+        period: i64,
+        transaction_id: DatabaseId,
+    ) -> DatabaseResponse<()>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        query!(
+            "
+                insert into RecurringChargeBookings
+                    (charge_id, user_id, period, transaction_id)
+                values
+                    (?, ?, ?, ?)
+            ",
+            id,
+            user_id.0,
+            period,
+            transaction_id
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(From::from)
+        .map(|_| ())
+    }
+}
+
+#[cfg(feature = "ssr")]
+impl RecurringCharge {
+    pub async fn get_all<T>(conn: &mut T) -> DatabaseResponse<Vec<Self>>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        use crate::models::UserDB;
+
+        let mut charges = Vec::new();
+
+        for charge in RecurringChargeDB::get_all(&mut *conn).await? {
+            let interval = match ChargeInterval::parse(&charge.interval) {
+                Some(value) => value,
+                None => {
+                    return Err(DBError::new(format!(
+                        "Invalid interval '{}' of recurring charge {}",
+                        charge.interval, charge.id
+                    )))
+                }
+            };
+
+            let receiver_name = match UserDB::get(&mut *conn, charge.receiver).await? {
+                Some(value) => value.nickname,
+                None => String::new(),
+            };
+
+            charges.push(RecurringCharge {
+                id: charge.id,
+                description: charge.description,
+                money: charge.money.into(),
+                receiver: UserId(charge.receiver),
+                receiver_name,
+                interval,
+                start: charge.start,
+                is_active: charge.is_active,
+                users: RecurringChargeDB::get_users(&mut *conn, charge.id).await?,
+            });
+        }
+
+        Ok(charges)
+    }
+
+    /// The periods that are due for the user, oldest first.
+    fn due_periods(&self, user: &RecurringChargeUser, last_period: Option<i64>) -> Vec<u32> {
+        let now = Utc::now();
+
+        let first_period = (0..)
+            .map_while(|period| {
+                self.interval
+                    .period_start(self.start, period)
+                    .filter(|start| *start <= now)
+                    .map(|start| (period, start))
+            })
+            .find(|(_, start)| *start >= user.since)
+            .map(|(period, _)| period);
+
+        let first_period = match (first_period, last_period) {
+            (None, _) => return Vec::new(),
+            (Some(first), None) => first,
+            (Some(first), Some(last)) => first.max(last as u32 + 1),
+        };
+
+        (first_period..)
+            .map_while(|period| {
+                self.interval
+                    .period_start(self.start, period)
+                    .filter(|start| *start <= now)
+                    .map(|_| period)
+            })
+            .collect()
+    }
+
+    /// Books all periods that fell due since the last run, each in its own
+    /// database transaction. Bookings for a user stop at the first failure,
+    /// which is stored with the user and shown to the admins, and aren't
+    /// retried until an admin clears it. Returns the number of booked charges.
+    pub async fn book_due(db: &DB, settings: &Settings) -> DatabaseResponse<usize> {
+        let charges = RecurringCharge::get_all(&mut *db.get_conn().await?).await?;
+        let mut booked = 0;
+
+        for charge in charges.iter().filter(|charge| charge.is_active) {
+            let receiver_group = {
+                let mut conn = db.get_conn().await?;
+                GroupId(GroupDB::get_single_group(&mut *conn, charge.receiver).await?)
+            };
+
+            for user in charge.users.iter().filter(|user| user.failure.is_none()) {
+                let last_period = RecurringChargeDB::get_last_period(
+                    &mut *db.get_conn().await?,
+                    charge.id,
+                    user.user_id,
+                )
+                .await?;
+
+                for period in charge.due_periods(user, last_period) {
+                    let mut db_trans = db.get_conn_transaction().await?;
+
+                    let user_group =
+                        GroupId(GroupDB::get_single_group(&mut *db_trans, user.user_id).await?);
+
+                    let period_start = charge
+                        .interval
+                        .period_start(charge.start, period)
+                        .unwrap_or(charge.start);
+
+                    let transaction_id = match Transaction::create(
+                        &mut *db_trans,
+                        user_group,
+                        receiver_group,
+                        TransactionType::Sent(receiver_group),
+                        Some(format!(
+                            "{} ({})",
+                            charge.description,
+                            period_start.format("%d.%m.%Y")
+                        )),
+                        charge.money,
+                        settings,
+                    )
+                    .await
+                    {
+                        Ok(value) => value,
+                        Err(e) => {
+                            error!(
+                                "Failed to book recurring charge {} ('{}') for user '{}': {}",
+                                charge.id, charge.description, user.nickname, e
+                            );
+
+                            drop(db_trans);
+                            RecurringChargeDB::set_failure(
+                                &mut *db.get_conn().await?,
+                                charge.id,
+                                user.user_id,
+                                e.to_string(),
+                            )
+                            .await?;
+
+                            break;
+                        }
+                    };
+
+                    RecurringChargeDB::insert_booking(
+                        &mut *db_trans,
+                        charge.id,
+                        user.user_id,
+                        period as i64,
+                        transaction_id,
+                    )
+                    .await?;
+
+                    db_trans.commit().await.map_err(DBError::new)?;
+
+                    info!(
+                        "Booked recurring charge {} for user '{}' (period {})",
+                        charge.id, user.nickname, period
+                    );
+
+                    booked += 1;
+                }
+            }
+        }
+
+        Ok(booked)
+    }
+}
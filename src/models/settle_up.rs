@@ -0,0 +1,160 @@
+use serde::{Deserialize, Serialize};
+
+use super::{Money, UserId};
+
+/// `from` pays `money` to `to` outside of the list. Booking it moves the
+/// balance the other way, so both accounts get closer to zero.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Settlement {
+    pub from: UserId,
+    pub from_name: String,
+    pub to: UserId,
+    pub to_name: String,
+    pub money: Money,
+}
+
+/// Matches the largest debt with the largest credit until one side is used
+/// up, which needs at most one transfer less than there are participants.
+/// Returns `(debtor, creditor, amount)` triples.
+#[cfg(feature = "ssr")]
+pub fn compute_settlements(balances: &[(UserId, i64)]) -> Vec<(UserId, UserId, i64)> {
+    let mut debtors = balances
+        .iter()
+        .filter(|(_, balance)| *balance < 0)
+        .map(|(user_id, balance)| (*user_id, -balance))
+        .collect::<Vec<_>>();
+    let mut creditors = balances
+        .iter()
+        .filter(|(_, balance)| *balance > 0)
+        .map(|(user_id, balance)| (*user_id, *balance))
+        .collect::<Vec<_>>();
+
+    let mut settlements = Vec::new();
+
+    loop {
+        debtors.sort_by(|a, b| b.1.cmp(&a.1).then(a.0 .0.cmp(&b.0 .0)));
+        creditors.sort_by(|a, b| b.1.cmp(&a.1).then(a.0 .0.cmp(&b.0 .0)));
+
+        let (Some(debtor), Some(creditor)) = (debtors.first_mut(), creditors.first_mut()) else {
+            break;
+        };
+
+        let amount = debtor.1.min(creditor.1);
+
+        if amount == 0 {
+            break;
+        }
+
+        settlements.push((debtor.0, creditor.0, amount));
+
+        debtor.1 -= amount;
+        creditor.1 -= amount;
+
+        debtors.retain(|(_, balance)| *balance > 0);
+        creditors.retain(|(_, balance)| *balance > 0);
+    }
+
+    settlements
+}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{compute_settlements, UserId};
+
+    const CASES: [&[i64]; 4] = [
+        &[-100, 50, 50],
+        &[300, -100, -100, -100],
+        &[-1, -2, -3, 4, 2],
+        &[1234, -999, 0, -235, 17, -17],
+    ];
+
+    fn balances(values: &[i64]) -> Vec<(UserId, i64)> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(index, value)| (UserId(index as i64), *value))
+            .collect()
+    }
+
+    /// Applies the transfers to the balances.
+    fn settle(balances: &[(UserId, i64)]) -> HashMap<UserId, i64> {
+        let mut result = balances.iter().copied().collect::<HashMap<_, _>>();
+
+        for (debtor, creditor, amount) in compute_settlements(balances) {
+            assert!(amount > 0);
+            *result.get_mut(&debtor).unwrap() += amount;
+            *result.get_mut(&creditor).unwrap() -= amount;
+        }
+
+        result
+    }
+
+    #[test]
+    fn nothing_to_settle() {
+        assert!(compute_settlements(&balances(&[])).is_empty());
+        assert!(compute_settlements(&balances(&[0, 0, 0])).is_empty());
+    }
+
+    #[test]
+    fn single_debt() {
+        assert_eq!(
+            compute_settlements(&balances(&[-500, 500])),
+            vec![(UserId(0), UserId(1), 500)]
+        );
+    }
+
+    #[test]
+    fn largest_debt_pays_largest_credit() {
+        assert_eq!(
+            compute_settlements(&balances(&[-300, 1000, -700])),
+            vec![(UserId(2), UserId(1), 700), (UserId(0), UserId(1), 300)]
+        );
+    }
+
+    #[test]
+    fn balances_end_at_zero() {
+        for values in CASES {
+            assert_eq!(values.iter().sum::<i64>(), 0);
+
+            let settled = settle(&balances(values));
+
+            assert!(settled.values().all(|value| *value == 0), "{values:?}");
+        }
+    }
+
+    #[test]
+    fn at_most_one_transfer_less_than_participants() {
+        for values in CASES {
+            let participants = values.iter().filter(|value| **value != 0).count();
+
+            assert!(
+                compute_settlements(&balances(values)).len() < participants,
+                "{values:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn balances_not_adding_up_to_zero() {
+        // the balances come from the accounts, so one side is usually left
+        assert_eq!(
+            compute_settlements(&balances(&[-500, 200, 100, -50])),
+            vec![(UserId(0), UserId(1), 200), (UserId(0), UserId(2), 100)]
+        );
+
+        for values in [
+            &[-500, 200, 100, -50][..],
+            &[1000, -10, -20, -30],
+            &[5, 5, -1],
+        ] {
+            let participants = values.iter().filter(|value| **value != 0).count();
+
+            assert!(
+                compute_settlements(&balances(values)).len() < participants,
+                "{values:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use serde::{Deserialize, Serialize};
+
+/// How a cost is divided between the participants of a split.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SplitMode {
+    #[default]
+    Equal,
+    /// every participant pays proportionally to a weight, e.g. portions
+    Weights,
+    /// every participant pays an explicit amount
+    Amounts,
+}
+
+impl SplitMode {
+    pub const ALL: [SplitMode; 3] = [SplitMode::Equal, SplitMode::Weights, SplitMode::Amounts];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SplitMode::Equal => "equal",
+            SplitMode::Weights => "weights",
+            SplitMode::Amounts => "amounts",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        SplitMode::ALL
+            .into_iter()
+            .find(|mode| mode.as_str() == value)
+    }
+}
+
+/// Divides `total` proportionally to `weights`. The cents that can't be divided
+/// go to the largest remainders, so the shares always add up to `total`.
+/// Returns `None` if all weights are zero.
+#[cfg(feature = "ssr")]
+pub fn split_by_weights(total: i64, weights: &[u64]) -> Option<Vec<i64>> {
+    let weight_sum = weights.iter().map(|weight| *weight as i128).sum::<i128>();
+
+    if weight_sum == 0 {
+        return None;
+    }
+
+    let mut shares = Vec::with_capacity(weights.len());
+    let mut remainders = Vec::with_capacity(weights.len());
+
+    for (index, weight) in weights.iter().enumerate() {
+        let exact = total as i128 * *weight as i128;
+        shares.push((exact / weight_sum) as i64);
+        remainders.push((exact % weight_sum, index));
+    }
+
+    let mut missing = total - shares.iter().sum::<i64>();
+
+    remainders.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+
+    for (_, index) in remainders.into_iter().cycle() {
+        if missing == 0 {
+            break;
+        }
+
+        if weights[index] > 0 {
+            shares[index] += 1;
+            missing -= 1;
+        }
+    }
+
+    Some(shares)
+}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::split_by_weights;
+
+    #[test]
+    fn equal_split_without_remainder() {
+        assert_eq!(split_by_weights(900, &[1, 1, 1]), Some(vec![300, 300, 300]));
+    }
+
+    #[test]
+    fn remainder_goes_to_the_first_of_equal_shares() {
+        assert_eq!(
+            split_by_weights(1000, &[1, 1, 1]),
+            Some(vec![334, 333, 333])
+        );
+        assert_eq!(
+            split_by_weights(1001, &[1, 1, 1]),
+            Some(vec![334, 334, 333])
+        );
+    }
+
+    #[test]
+    fn remainder_goes_to_the_largest_remainders() {
+        // exact shares are 142.86, 285.71 and 571.43
+        assert_eq!(
+            split_by_weights(1000, &[1, 2, 4]),
+            Some(vec![143, 286, 571])
+        );
+    }
+
+    #[test]
+    fn zero_weights_get_nothing() {
+        assert_eq!(split_by_weights(1001, &[0, 1, 1]), Some(vec![0, 501, 500]));
+        assert_eq!(split_by_weights(5, &[0, 0]), None);
+        assert_eq!(split_by_weights(5, &[]), None);
+    }
+
+    #[test]
+    fn shares_add_up_to_the_total() {
+        let weights = [[1, 1, 1], [3, 5, 7], [1, 0, 2], [10, 1, 1], [2, 2, 5]];
+
+        for total in [0, 1, 2, 99, 100, 1001, 12_345, 999_999] {
+            for weights in weights.iter() {
+                let shares = split_by_weights(total, weights).unwrap();
+
+                assert_eq!(shares.iter().sum::<i64>(), total, "{total} {weights:?}");
+                assert!(shares.iter().all(|share| *share >= 0));
+            }
+        }
+    }
+}
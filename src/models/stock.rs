@@ -0,0 +1,319 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{DatabaseId, Money};
+
+#[cfg(feature = "ssr")]
+use {
+    super::ArticleDB,
+    crate::{
+        backend::{
+            db::{DBError, DatabaseResponse, DatabaseType},
+            StockSettings,
+        },
+        routes::user::CreateTransactionError,
+    },
+    sqlx::{query, Executor},
+    tracing::warn,
+};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StockMovementKind {
+    /// units that were bought for the snackbar
+    Restock,
+    /// the difference found when counting the stock
+    Correction,
+}
+
+impl StockMovementKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StockMovementKind::Restock => "restock",
+            StockMovementKind::Correction => "correction",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        [StockMovementKind::Restock, StockMovementKind::Correction]
+            .into_iter()
+            .find(|kind| kind.as_str() == value)
+    }
+}
+
+/// A change of the stock that isn't a purchase, purchases are recorded by
+/// their transactions.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct StockMovement {
+    pub id: DatabaseId,
+    pub article_id: DatabaseId,
+    pub article_name: String,
+    pub kind: StockMovementKind,
+    /// units added to the stock, negative when a count found less
+    pub quantity: i64,
+    /// price per unit, only known for restocks
+    pub purchase_price: Option<Money>,
+    pub moved_on: NaiveDate,
+    pub created_at: DateTime<Utc>,
+}
+
+#[cfg(feature = "ssr")]
+impl StockMovement {
+    async fn create<T>(
+        conn: &mut T,
+        article_id: DatabaseId,
+        kind: StockMovementKind,
+        quantity: i64,
+        purchase_price: Option<Money>,
+        moved_on: NaiveDate,
+    ) -> DatabaseResponse<DatabaseId>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        let kind = kind.as_str();
+        let purchase_price = purchase_price.map(|money| money.value);
+        let now = Utc::now();
+
+        let id = query!(
+            "
+                insert into StockMovements
+                    (article_id, kind, quantity, purchase_price, moved_on, created_at)
+                values
+                    (?, ?, ?, ?, ?, ?)
+                returning id
+            ",
+            article_id,
+            kind,
+            quantity,
+            purchase_price,
+            moved_on,
+            now
+        )
+        .fetch_one(&mut *conn)
+        .await?
+        .id;
+
+        Ok(id)
+    }
+
+    /// Adds `quantity` units to the stock, tracking the stock of the article
+    /// from now on.
+    pub async fn restock<T>(
+        conn: &mut T,
+        article_id: DatabaseId,
+        quantity: i64,
+        moved_on: NaiveDate,
+        purchase_price: Option<Money>,
+    ) -> DatabaseResponse<DatabaseId>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        if quantity <= 0 {
+            return Err(DBError::new("The quantity has to be positive!"));
+        }
+
+        if ArticleDB::get_single(&mut *conn, article_id)
+            .await?
+            .is_none()
+        {
+            return Err(DBError::new(format!("Unknown article id '{article_id}'")));
+        }
+
+        let id = Self::create(
+            &mut *conn,
+            article_id,
+            StockMovementKind::Restock,
+            quantity,
+            purchase_price,
+            moved_on,
+        )
+        .await?;
+
+        _ = query!(
+            "
+                update Articles
+                    set stock = coalesce(stock, 0) + ?
+                where id = ?
+            ",
+            quantity,
+            article_id
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(id)
+    }
+
+    /// Sets the stock to the `counted` units and books the difference.
+    pub async fn correct<T>(
+        conn: &mut T,
+        article_id: DatabaseId,
+        counted: i64,
+    ) -> DatabaseResponse<DatabaseId>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        if counted < 0 {
+            return Err(DBError::new("The counted stock can't be negative!"));
+        }
+
+        let article = match ArticleDB::get_single(&mut *conn, article_id).await? {
+            Some(value) => value,
+            None => return Err(DBError::new(format!("Unknown article id '{article_id}'"))),
+        };
+
+        let id = Self::create(
+            &mut *conn,
+            article_id,
+            StockMovementKind::Correction,
+            counted - article.stock.unwrap_or_default(),
+            None,
+            Utc::now().date_naive(),
+        )
+        .await?;
+
+        _ = query!(
+            "
+                update Articles
+                    set stock = ?
+                where id = ?
+            ",
+            counted,
+            article_id
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(id)
+    }
+
+    pub async fn get_latest<T>(conn: &mut T, limit: u64) -> DatabaseResponse<Vec<Self>>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        let limit = limit as i64;
+
+        let rows = query!(
+            r#"
+                select
+                    StockMovements.id,
+                    article_id,
+                    Articles.name as article_name,
+                    kind,
+                    quantity,
+                    purchase_price,
+                    moved_on as "moved_on: NaiveDate",
+                    created_at as "created_at: DateTime<Utc>"
+                from StockMovements
+                join Articles on Articles.id = article_id
+                order by created_at desc, StockMovements.id desc
+                limit ?
+            "#,
+            limit
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                let kind = match StockMovementKind::parse(&row.kind) {
+                    Some(value) => value,
+                    None => {
+                        return Err(DBError::new(format!(
+                            "Invalid kind '{}' of stock movement {}",
+                            row.kind, row.id
+                        )))
+                    }
+                };
+
+                Ok(StockMovement {
+                    id: row.id,
+                    article_id: row.article_id,
+                    article_name: row.article_name,
+                    kind,
+                    quantity: row.quantity,
+                    purchase_price: row.purchase_price.map(Money::from),
+                    moved_on: row.moved_on,
+                    created_at: row.created_at,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(feature = "ssr")]
+impl ArticleDB {
+    /// Takes bought units out of the stock. Buying more than is in stock is
+    /// refused unless the settings allow a negative stock.
+    pub async fn take_stock<T>(
+        conn: &mut T,
+        article_id: DatabaseId,
+        quantity: i64,
+        settings: &StockSettings,
+    ) -> Result<(), CreateTransactionError>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        let article = match ArticleDB::get_single(&mut *conn, article_id).await? {
+            Some(value) => value,
+            None => return Ok(()),
+        };
+
+        let Some(stock) = article.stock else {
+            return Ok(());
+        };
+
+        if stock - quantity < 0 {
+            if !settings.allow_negative {
+                return Err(CreateTransactionError::OutOfStockError(
+                    article.name,
+                    stock.max(0),
+                ));
+            }
+
+            warn!(
+                "Stock of '{}' went negative: {}",
+                article.name,
+                stock - quantity
+            );
+        }
+
+        _ = query!(
+            "
+                update Articles
+                    set stock = stock - ?
+                where id = ?
+            ",
+            quantity,
+            article_id
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(DBError::new)?;
+
+        Ok(())
+    }
+
+    /// Puts units back into the stock when their purchase is undone.
+    pub async fn put_back_stock<T>(
+        conn: &mut T,
+        article_id: DatabaseId,
+        quantity: i64,
+    ) -> DatabaseResponse<()>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        _ = query!(
+            "
+                update Articles
+                    set stock = stock + ?
+                where id = ? and stock is not null
+            ",
+            quantity,
+            article_id
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+}
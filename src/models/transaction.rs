@@ -6,7 +6,7 @@ use leptos::prelude::RwSignal;
 
 #[cfg(feature = "ssr")]
 use crate::{
-    backend::Settings,
+    backend::{Settings, UndoSettings},
     models::{Page, PageRequestParams, User},
     routes::user::CreateTransactionError,
 };
@@ -18,17 +18,17 @@ use super::{DatabaseId, GroupId, Money};
 
 #[cfg(feature = "ssr")]
 use {
-    super::ArticleDB,
+    super::{split_by_weights, ArticleDB},
     crate::backend::db::{DBError, DB},
     crate::backend::db::{DatabaseResponse, DatabaseType},
     crate::backend::db::{DBGROUP_AUFLADUNG_ID, DBGROUP_SNACKBAR_ID},
     crate::models::{Group, GroupDB},
     itertools::Itertools,
     sqlx::query,
-    sqlx::{query_as, Executor},
+    sqlx::{query_as, Executor, QueryBuilder},
 };
 
-#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum TransactionType {
     Deposit,
     Withdraw,
@@ -36,6 +36,189 @@ pub enum TransactionType {
     Received(GroupId),
     Sent(GroupId),
     SentAndReceived(GroupId), // sending group is stored as group_id in Transaction
+    /// an adjustment booked when the balance didn't match the ledger, true
+    /// if the user was credited
+    Correction(bool),
+}
+
+/// The kind of a transaction without the attached data.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionKind {
+    Deposit,
+    Withdraw,
+    Bought,
+    Received,
+    Sent,
+    SentAndReceived,
+    Correction,
+}
+
+impl TransactionKind {
+    pub const ALL: [TransactionKind; 7] = [
+        TransactionKind::Deposit,
+        TransactionKind::Withdraw,
+        TransactionKind::Bought,
+        TransactionKind::Received,
+        TransactionKind::Sent,
+        TransactionKind::SentAndReceived,
+        TransactionKind::Correction,
+    ];
+}
+
+impl From<TransactionType> for TransactionKind {
+    fn from(value: TransactionType) -> Self {
+        match value {
+            TransactionType::Deposit => TransactionKind::Deposit,
+            TransactionType::Withdraw => TransactionKind::Withdraw,
+            TransactionType::Bought(_) => TransactionKind::Bought,
+            TransactionType::Received(_) => TransactionKind::Received,
+            TransactionType::Sent(_) => TransactionKind::Sent,
+            TransactionType::SentAndReceived(_) => TransactionKind::SentAndReceived,
+            TransactionType::Correction(_) => TransactionKind::Correction,
+        }
+    }
+}
+
+/// Optional criteria to narrow down the transactions of a user, unset fields
+/// match everything.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct TransactionFilter {
+    /// `Sent` and `Received` also match transactions to oneself
+    pub kind: Option<TransactionKind>,
+    pub from: Option<DateTime<Utc>>,
+    /// exclusive
+    pub to: Option<DateTime<Utc>>,
+    pub article_id: Option<i64>,
+    /// compared to the share of the user in the transaction
+    pub min_amount: Option<Money>,
+    pub max_amount: Option<Money>,
+    /// searched for in the description
+    pub text: Option<String>,
+}
+
+#[cfg(feature = "ssr")]
+impl TransactionFilter {
+    /// Pushes the conditions selecting the transactions of `user_id` that
+    /// match this filter.
+    fn push_conditions<'a>(
+        &'a self,
+        builder: &mut QueryBuilder<'a, DatabaseType>,
+        user_id: UserId,
+    ) {
+        builder
+            .push(
+                "
+                where exists (
+                    select 1 from UserGroupMap as UGM
+                    where UGM.uid = ",
+            )
+            .push_bind(user_id.0)
+            .push(
+                "
+                        and (Transactions.receiver = UGM.gid or Transactions.sender = UGM.gid)
+                )",
+            );
+
+        if let Some(kind) = self.kind {
+            match kind {
+                TransactionKind::Deposit => {
+                    builder
+                        .push(" and not Transactions.is_correction and Transactions.sender = ")
+                        .push_bind(DBGROUP_AUFLADUNG_ID.0);
+                }
+                TransactionKind::Withdraw => {
+                    builder
+                        .push(" and not Transactions.is_correction and Transactions.receiver = ")
+                        .push_bind(DBGROUP_AUFLADUNG_ID.0);
+                }
+                TransactionKind::Correction => {
+                    builder.push(" and Transactions.is_correction");
+                }
+                TransactionKind::Bought => {
+                    builder
+                        .push(" and Transactions.receiver = ")
+                        .push_bind(DBGROUP_SNACKBAR_ID.0);
+                }
+                TransactionKind::Sent
+                | TransactionKind::Received
+                | TransactionKind::SentAndReceived => {
+                    for column in ["sender", "receiver"] {
+                        builder
+                            .push(format!(" and Transactions.{column} not in ("))
+                            .push_bind(DBGROUP_SNACKBAR_ID.0)
+                            .push(", ")
+                            .push_bind(DBGROUP_AUFLADUNG_ID.0)
+                            .push(")");
+                    }
+
+                    let columns: &[&str] = match kind {
+                        TransactionKind::Sent => &["sender"],
+                        TransactionKind::Received => &["receiver"],
+                        _ => &["sender", "receiver"],
+                    };
+
+                    for column in columns {
+                        builder
+                            .push(format!(
+                                " and Transactions.{column} in (select gid from UserGroupMap where uid = "
+                            ))
+                            .push_bind(user_id.0)
+                            .push(")");
+                    }
+                }
+            }
+        }
+
+        if let Some(from) = self.from {
+            builder
+                .push(" and Transactions.timestamp >= ")
+                .push_bind(from);
+        }
+
+        if let Some(to) = self.to {
+            builder.push(" and Transactions.timestamp < ").push_bind(to);
+        }
+
+        if let Some(article_id) = self.article_id {
+            builder
+                .push(" and Transactions.receiver = ")
+                .push_bind(DBGROUP_SNACKBAR_ID.0)
+                .push(" and Transactions.t_type_data = ")
+                .push_bind(article_id);
+        }
+
+        // the share of the user, sending to oneself leaves no share
+        let push_amount = |builder: &mut QueryBuilder<'a, DatabaseType>| {
+            builder
+                .push(
+                    "
+                    and coalesce((
+                        select nullif(abs(TransactionShares.delta), 0)
+                        from TransactionShares
+                        where TransactionShares.transaction_id = Transactions.id
+                            and TransactionShares.user_id = ",
+                )
+                .push_bind(user_id.0)
+                .push("), Transactions.money)");
+        };
+
+        if let Some(min_amount) = self.min_amount {
+            push_amount(builder);
+            builder.push(" >= ").push_bind(min_amount.value);
+        }
+
+        if let Some(max_amount) = self.max_amount {
+            push_amount(builder);
+            builder.push(" <= ").push_bind(max_amount.value);
+        }
+
+        if let Some(text) = self.text.as_ref().filter(|text| !text.trim().is_empty()) {
+            builder
+                .push(" and Transactions.description like ")
+                .push_bind(format!("%{}%", text.trim()));
+        }
+    }
 }
 
 #[cfg(feature = "ssr")]
@@ -59,11 +242,19 @@ pub struct TransactionDB {
     pub id: i64,
     pub sender: i64,
     pub receiver: i64,
-    pub is_undone: bool,
     pub t_type_data: Option<i64>,
     pub money: u64,
+    /// number of articles bought, `money` is the total of all of them
+    pub quantity: i64,
     pub description: Option<String>,
     pub timestamp: DateTime<Utc>,
+    /// set if this transaction undoes another one
+    pub reverses_id: Option<i64>,
+    pub undone_by: Option<i64>,
+    pub undo_reason: Option<String>,
+    pub is_correction: bool,
+    /// the transaction that undoes this one, not stored in the table
+    pub reversed_by: Option<i64>,
 }
 
 #[cfg(feature = "ssr")]
@@ -71,13 +262,18 @@ impl From<Transaction> for TransactionDB {
     fn from(value: Transaction) -> Self {
         let Transaction {
             id,
-            is_undone,
+            is_undone: _,
             t_type,
             money,
+            quantity,
             description,
             timestamp,
             is_undone_signal: _,
             group_id,
+            reverses_id,
+            reversed_by,
+            undo_reason,
+            undoable_until: _,
         } = value;
 
         let (sender, receiver) = match t_type {
@@ -87,13 +283,14 @@ impl From<Transaction> for TransactionDB {
             TransactionType::Received(from) => (from, group_id),
             TransactionType::Sent(to) => (group_id, to),
             TransactionType::SentAndReceived(to) => (group_id, to),
+            TransactionType::Correction(true) => (DBGROUP_AUFLADUNG_ID, group_id),
+            TransactionType::Correction(false) => (group_id, DBGROUP_AUFLADUNG_ID),
         };
 
         Self {
             id,
             sender: sender.0,
             receiver: receiver.0,
-            is_undone,
             t_type_data: match t_type {
                 TransactionType::Sent(var)
                 | TransactionType::SentAndReceived(var)
@@ -103,8 +300,14 @@ impl From<Transaction> for TransactionDB {
                 _ => None,
             },
             money: money.value as u64,
+            quantity: quantity as i64,
             description,
             timestamp,
+            reverses_id,
+            undone_by: None,
+            undo_reason,
+            is_correction: matches!(t_type, TransactionType::Correction(_)),
+            reversed_by,
         }
     }
 }
@@ -125,11 +328,16 @@ where
                 id,
                 sender,
                 receiver,
-                is_undone,
                 t_type_data,
                 money,
+                quantity,
                 description,
                 timestamp,
+                reverses_id,
+                undone_by: _,
+                undo_reason,
+                is_correction,
+                reversed_by,
             },
             group_ids,
         ) = self;
@@ -153,10 +361,13 @@ where
                     ));
                 }
             },
-            is_undone,
+            is_undone: reversed_by.is_some(),
             t_type: {
                 use crate::backend::db::DBGROUP_SNACKBAR_ID;
                 match (sender, receiver) {
+                    _ if is_correction => {
+                        TransactionType::Correction(sender == DBGROUP_AUFLADUNG_ID)
+                    }
                     (DBGROUP_AUFLADUNG_ID, _) => TransactionType::Deposit,
                     (_, DBGROUP_AUFLADUNG_ID) => TransactionType::Withdraw,
                     (_, DBGROUP_SNACKBAR_ID) => TransactionType::Bought(t_type_data.unwrap()),
@@ -173,9 +384,14 @@ where
                 }
             },
             money: money.into(),
+            quantity: quantity as u32,
             description,
             timestamp,
-            is_undone_signal: RwSignal::new(is_undone), // might fail on server
+            is_undone_signal: RwSignal::new(reversed_by.is_some()), // might fail on server
+            reverses_id,
+            reversed_by,
+            undo_reason,
+            undoable_until: None,
         })
     }
 }
@@ -189,6 +405,7 @@ impl TransactionDB {
         t_type_data: Option<i64>,
         description: Option<String>,
         money: i64,
+        quantity: i64,
     ) -> DatabaseResponse<DatabaseId>
     where
         for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
@@ -197,16 +414,16 @@ impl TransactionDB {
         query!(
             "
                 insert into Transactions
-                    (receiver, sender, is_undone, t_type_data, money, description, timestamp)
+                    (receiver, sender, t_type_data, money, quantity, description, timestamp)
                 values
                     (?, ?, ?, ?, ?, ?, ?)
                 returning id
             ",
             receiver.0,
             sender.0,
-            false,
             t_type_data,
             money,
+            quantity,
             description,
             now
         )
@@ -216,6 +433,236 @@ impl TransactionDB {
         .map(|elem| elem.id)
     }
 
+    /// Makes all transactions of the group `from` belong to the group `to`.
+    ///
+    /// Transactions are otherwise never updated, undoing books a reversal.
+    /// Merging users is the only exception: it rewrites the groups here, the
+    /// shares in [`Self::move_shares`] and the user who undid a transaction in
+    /// [`Self::move_undone_by`]. Amounts are never touched.
+    pub async fn move_group<T>(conn: &mut T, from: GroupId, to: GroupId) -> DatabaseResponse<()>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        query!(
+            "
+                update Transactions
+                set sender = ?
+                where sender = ?
+            ",
+            to.0,
+            from.0
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        query!(
+            "
+                update Transactions
+                set receiver = ?
+                where receiver = ?
+            ",
+            to.0,
+            from.0
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        // t_type_data contains the article id for bought articles
+        query!(
+            "
+                update Transactions
+                set t_type_data = ?
+                where t_type_data = ? and receiver != ?
+            ",
+            to.0,
+            from.0,
+            DBGROUP_SNACKBAR_ID.0
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(From::from)
+        .map(|_| ())
+    }
+
+    /// Creates a balance correction between the group of a user and the
+    /// top-up account, the balances have to be updated by the caller.
+    pub async fn create_correction<T>(
+        conn: &mut T,
+        sender: GroupId,
+        receiver: GroupId,
+        money: i64,
+    ) -> DatabaseResponse<DatabaseId>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        let now = Utc::now();
+        query!(
+            "
+                insert into Transactions
+                    (receiver, sender, money, quantity, description, timestamp, is_correction)
+                values
+                    (?, ?, ?, 1, 'Balance correction', ?, true)
+                returning id
+            ",
+            receiver.0,
+            sender.0,
+            money,
+            now
+        )
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(From::from)
+        .map(|elem| elem.id)
+    }
+
+    /// Creates a transaction that undoes `original`, the balances have to be
+    /// updated by the caller.
+    pub async fn create_reversal<T>(
+        conn: &mut T,
+        original: &TransactionDB,
+        undone_by: UserId,
+        undo_reason: Option<String>,
+    ) -> DatabaseResponse<DatabaseId>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        let now = Utc::now();
+        let money = original.money as i64;
+        query!(
+            "
+                insert into Transactions
+                    (receiver, sender, t_type_data, money, quantity, description, timestamp,
+                     reverses_id, undone_by, undo_reason)
+                values
+                    (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                returning id
+            ",
+            original.receiver,
+            original.sender,
+            original.t_type_data,
+            money,
+            original.quantity,
+            original.description,
+            now,
+            original.id,
+            undone_by.0,
+            undo_reason
+        )
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(From::from)
+        .map(|elem| elem.id)
+    }
+
+    /// Stores the amount a user paid (negative) or received (positive) with
+    /// the transaction.
+    pub async fn insert_share<T>(
+        conn: &mut T,
+        transaction_id: DatabaseId,
+        user_id: UserId,
+        delta: i64,
+    ) -> DatabaseResponse<()>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        query!(
+            "
+                insert into TransactionShares
+                    (transaction_id, user_id, delta)
+                values
+                    (?, ?, ?)
+            ",
+            transaction_id,
+            user_id.0,
+            delta
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(From::from)
+        .map(|_| ())
+    }
+
+    /// The stored shares of the transaction, every user that took part has
+    /// one.
+    pub async fn get_shares<T>(
+        conn: &mut T,
+        transaction_id: DatabaseId,
+    ) -> DatabaseResponse<HashMap<UserId, i64>>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        query!(
+            "
+                select user_id, delta
+                from TransactionShares
+                where transaction_id = ?
+            ",
+            transaction_id
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(From::from)
+        .map(|rows| {
+            rows.into_iter()
+                .map(|row| (UserId(row.user_id), row.delta))
+                .collect()
+        })
+    }
+
+    /// Moves the reversals done by `from` to `to`, needed when users are merged.
+    pub async fn move_undone_by<T>(conn: &mut T, from: UserId, to: UserId) -> DatabaseResponse<()>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        query!(
+            "
+                update Transactions
+                set undone_by = ?
+                where undone_by = ?
+            ",
+            to.0,
+            from.0
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(From::from)
+        .map(|_| ())
+    }
+
+    /// Moves the shares of `from` to `to`, needed when users are merged.
+    pub async fn move_shares<T>(conn: &mut T, from: UserId, to: UserId) -> DatabaseResponse<()>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        query!(
+            "
+                insert into TransactionShares
+                    (transaction_id, user_id, delta)
+                select transaction_id, ?, delta
+                from TransactionShares
+                where user_id = ?
+                on conflict (transaction_id, user_id) do update
+                set delta = delta + excluded.delta
+            ",
+            to.0,
+            from.0
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        query!(
+            "
+                delete from TransactionShares
+                where user_id = ?
+            ",
+            from.0
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(From::from)
+        .map(|_| ())
+    }
+
     pub async fn get<T>(conn: &mut T, id: DatabaseId) -> DatabaseResponse<Option<TransactionDB>>
     where
         for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
@@ -227,11 +674,20 @@ impl TransactionDB {
                     id as "id: i64",
                     sender as "sender: i64",
                     receiver as "receiver: i64",
-                    is_undone,
                     t_type_data,
                     money as "money: u64",
+                    quantity,
                     description,
-                    timestamp as "timestamp: DateTime<Utc>"
+                    timestamp as "timestamp: DateTime<Utc>",
+                    reverses_id,
+                    undone_by,
+                    undo_reason,
+                    is_correction as "is_correction: bool",
+                    (
+                        select Reversals.id
+                        from Transactions as Reversals
+                        where Reversals.reverses_id = Transactions.id
+                    ) as "reversed_by?: i64"
                 from Transactions
                 where id = ?
             "#,
@@ -253,84 +709,87 @@ impl TransactionDB {
         conn: &mut T,
         user_id: UserId,
         page_request_params: PageRequestParams,
+        filter: &TransactionFilter,
     ) -> DatabaseResponse<Page<Self>>
     where
         for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
     {
-        let result = sqlx::query_as::<_, Self>(
+        let mut builder = QueryBuilder::<DatabaseType>::new(
             "
-            select Transactions.* from Transactions
-            join Users on Users.id=?
-            join UserGroupMap as UGM on UGM.uid = Users.id
-            where Transactions.receiver = UGM.gid or Transactions.sender = UGM.gid
-            order by timestamp desc
-            limit ?
-            offset ?
-        ",
-        )
-        .bind(user_id.0)
-        .bind(page_request_params.limit as i64)
-        .bind(page_request_params.offset as i64);
+            select
+                Transactions.*,
+                (
+                    select Reversals.id
+                    from Transactions as Reversals
+                    where Reversals.reverses_id = Transactions.id
+                ) as reversed_by
+            from Transactions
+            ",
+        );
 
-        let result = result
+        filter.push_conditions(&mut builder, user_id);
+
+        builder
+            .push(" order by timestamp desc limit ")
+            .push_bind(page_request_params.limit as i64)
+            .push(" offset ")
+            .push_bind(page_request_params.offset as i64);
+
+        let result = builder
+            .build_query_as::<Self>()
             .fetch_all(&mut *conn)
             .await
             .map_err(Into::<DBError>::into)?;
 
-        let count = sqlx::query_as::<_, (u64,)>(
-            r#"
-                select count(*) from Transactions
-            join Users on Users.id=?
-            join UserGroupMap as UGM on UGM.uid = Users.id
-            where Transactions.receiver = UGM.gid or Transactions.sender = UGM.gid
-            "#,
-        )
-        .bind(user_id.0)
-        .fetch_one(&mut *conn)
-        .await
-        .map_err(Into::<DBError>::into)?;
+        let mut builder = QueryBuilder::<DatabaseType>::new("select count(*) from Transactions");
 
-        Ok(Page::new(page_request_params, count.0 as usize, result))
-    }
+        filter.push_conditions(&mut builder, user_id);
 
-    pub async fn set_money<T>(conn: &mut T, id: DatabaseId, new_value: i64) -> DatabaseResponse<()>
-    where
-        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
-    {
-        _ = query!(
-            "
-                update Transactions
-                set money = ?
-                where id = ?
-            ",
-            new_value,
-            id
-        )
-        .execute(&mut *conn)
-        .await
-        .map_err(DBError::new)?;
+        let count = builder
+            .build_query_as::<(u64,)>()
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(Into::<DBError>::into)?;
 
-        Ok(())
+        Ok(Page::new(page_request_params, count.0 as usize, result))
     }
 
-    pub async fn set_undone<T>(conn: &mut T, id: i64, new_value: bool) -> DatabaseResponse<()>
+    /// The ids of the latest transactions of a user, ignoring undos and
+    /// undone transactions.
+    pub async fn get_latest_user_transaction_ids<T>(
+        conn: &mut T,
+        user_id: UserId,
+        limit: u64,
+    ) -> DatabaseResponse<Vec<DatabaseId>>
     where
         for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
     {
-        _ = query!(
+        let limit = limit as i64;
+
+        query!(
             "
-                update Transactions
-                set is_undone = ?
-                where id = ?
+                select Transactions.id
+                from Transactions
+                where exists (
+                        select 1 from UserGroupMap as UGM
+                        where UGM.uid = ?
+                            and (Transactions.receiver = UGM.gid or Transactions.sender = UGM.gid)
+                    )
+                    and Transactions.reverses_id is null
+                    and not exists (
+                        select 1 from Transactions as Reversals
+                        where Reversals.reverses_id = Transactions.id
+                    )
+                order by Transactions.timestamp desc, Transactions.id desc
+                limit ?
             ",
-            new_value,
-            id
+            user_id.0,
+            limit
         )
-        .execute(&mut *conn)
+        .fetch_all(&mut *conn)
         .await
-        .map_err(DBError::new)?;
-
-        Ok(())
+        .map_err(From::from)
+        .map(|rows| rows.into_iter().map(|row| row.id).collect())
     }
 }
 
@@ -341,10 +800,19 @@ pub struct Transaction {
     pub group_id: GroupId,
     pub is_undone: bool,
     pub t_type: TransactionType,
+    /// the total for all articles of a purchase
     pub money: Money,
+    pub quantity: u32,
     pub description: Option<String>,
     pub timestamp: DateTime<Utc>,
     pub is_undone_signal: RwSignal<bool>,
+    /// the transaction that is undone by this one
+    pub reverses_id: Option<DatabaseId>,
+    /// the transaction that undoes this one
+    pub reversed_by: Option<DatabaseId>,
+    pub undo_reason: Option<String>,
+    /// until when the transaction may be undone, None if it can't be undone
+    pub undoable_until: Option<DateTime<Utc>>,
 }
 
 #[cfg(feature = "ssr")]
@@ -376,6 +844,7 @@ impl Transaction {
         db: &DB,
         user_id: UserId,
         page_request_params: PageRequestParams,
+        filter: &TransactionFilter,
     ) -> DatabaseResponse<Page<Self>> {
         use itertools::Itertools;
         use std::collections::HashMap;
@@ -388,7 +857,8 @@ impl Transaction {
         let Page {
             items,
             params: PageResponseParams { total, .. },
-        } = TransactionDB::get_user_transactions(&mut *conn, user_id, page_request_params).await?;
+        } = TransactionDB::get_user_transactions(&mut *conn, user_id, page_request_params, filter)
+            .await?;
         let mut transactions = items
             .into_iter()
             .map(|elem| (elem, user_groups.as_ref()).try_into())
@@ -424,14 +894,16 @@ impl Transaction {
                         Some(value) => value.clone(),
                     };
 
-                    transaction.money = price.into();
+                    transaction.money = (price * transaction.quantity as i64).into();
                     transaction.description = Some(article_name);
                 }
-                TransactionType::Sent(_) => {
-                    let sender_group = Group::get(&mut *conn, transaction.group_id).await?;
+                TransactionType::Sent(_) | TransactionType::Received(_) => {
+                    let shares = TransactionDB::get_shares(&mut *conn, transaction.id).await?;
 
                     // this shows the user his transferred amount when a group transaction was made
-                    transaction.money.value /= sender_group.members.len() as i64;
+                    if let Some(delta) = shares.get(&user_id) {
+                        transaction.money.value = delta.abs();
+                    }
                 }
 
                 _ => {}
@@ -441,150 +913,318 @@ impl Transaction {
         Ok(Page::new(page_request_params, total, transactions))
     }
 
-    pub async fn set_money<T>(&mut self, conn: &mut T, new_value: i64) -> DatabaseResponse<()>
+    /// Checks the undo policy and returns until when the transaction may be
+    /// undone. `recent_ids` are the latest transactions of the user, see
+    /// [`TransactionDB::get_latest_user_transaction_ids`].
+    pub fn check_undoable(
+        &self,
+        settings: &UndoSettings,
+        recent_ids: &[DatabaseId],
+    ) -> Result<DateTime<Utc>, String> {
+        if self.reverses_id.is_some() {
+            return Err("An undo can not be undone!".to_string());
+        }
+
+        if self.reversed_by.is_some() {
+            return Err("The transaction is already undone!".to_string());
+        }
+
+        if let TransactionType::Correction(_) = self.t_type {
+            return Err("A balance correction can not be undone!".to_string());
+        }
+
+        if !settings.types.contains(&self.t_type.into()) {
+            return Err("Transactions of this type can not be undone!".to_string());
+        }
+
+        if settings.last_n > 0 && !recent_ids.contains(&self.id) {
+            return Err(format!(
+                "Only the last {} transactions can be undone!",
+                settings.last_n
+            ));
+        }
+
+        let until = match settings.max_age {
+            0 => DateTime::<Utc>::MAX_UTC,
+            max_age => self.timestamp + chrono::Duration::seconds(max_age as i64),
+        };
+
+        if Utc::now() > until {
+            return Err("The transaction is too old to be undone!".to_string());
+        }
+
+        Ok(until)
+    }
+
+    /// Fills in `undoable_until` of the transactions of a user.
+    pub async fn set_undo_deadlines<T>(
+        conn: &mut T,
+        user_id: UserId,
+        settings: &UndoSettings,
+        transactions: &mut [Transaction],
+    ) -> DatabaseResponse<()>
     where
         for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
     {
-        TransactionDB::set_money(&mut *conn, self.id, new_value).await
+        let recent_ids = match settings.last_n {
+            0 => Vec::new(),
+            last_n => TransactionDB::get_latest_user_transaction_ids(conn, user_id, last_n).await?,
+        };
+
+        for transaction in transactions.iter_mut() {
+            transaction.undoable_until = transaction.check_undoable(settings, &recent_ids).ok();
+        }
+
+        Ok(())
     }
 
-    pub async fn set_undone<T>(&mut self, conn: &mut T, new_value: bool) -> DatabaseResponse<()>
+    /// Recomputes the balance of every user from the shares of all
+    /// transactions, undone transactions cancel out with their reversals.
+    pub async fn get_ledger_balances<T>(conn: &mut T) -> DatabaseResponse<HashMap<UserId, i64>>
     where
         for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
     {
-        TransactionDB::set_undone(&mut *conn, self.id, new_value).await
+        query!(
+            r#"
+                select user_id, sum(delta) as "balance!: i64"
+                from TransactionShares
+                group by user_id
+            "#
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(From::from)
+        .map(|rows| {
+            rows.into_iter()
+                .map(|row| (UserId(row.user_id), row.balance))
+                .collect()
+        })
     }
 
-    async fn get_transaction_delta<T>(
+    /// Appends a reversal of `original` to the ledger and reverts its effect
+    /// on the balances and on the stock. The reverted balances are checked
+    /// like the ones of a new transaction.
+    pub async fn undo<T>(
         conn: &mut T,
-        sender_group: &Group,
-        receiver_group: &Group,
-        transaction_db: &TransactionDB,
-    ) -> Result<HashMap<User, TransactionDelta>, DBError>
+        original: &TransactionDB,
+        undone_by: UserId,
+        undo_reason: Option<String>,
+        settings: &Settings,
+    ) -> Result<DatabaseId, CreateTransactionError>
     where
         for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
     {
-        use crate::models::User;
-        use tracing::error;
+        let deltas = Transaction::get_transaction_delta(&mut *conn, original.id).await?;
 
-        let mut senders = Vec::<User>::new();
-        let mut receivers = Vec::<User>::new();
+        let id =
+            TransactionDB::create_reversal(&mut *conn, original, undone_by, undo_reason).await?;
 
-        for sender in sender_group.members.iter() {
-            let user_send = match User::get(&mut *conn, UserId(sender.id)).await? {
-                Some(val) => val,
-                None => {
-                    error!("Failed to find a user that should exist! Id: {}", sender.id);
-                    return Err(DBError::new("Failed to find user"));
-                }
-            };
+        if let (DBGROUP_SNACKBAR_ID, Some(article_id)) =
+            (GroupId(original.receiver), original.t_type_data)
+        {
+            ArticleDB::put_back_stock(&mut *conn, article_id, original.quantity).await?;
+        }
+
+        let mut reversed = HashMap::with_capacity(deltas.len());
+
+        for (key, value) in deltas.into_iter() {
+            TransactionDB::insert_share(&mut *conn, id, key.id, -value.delta).await?;
 
-            senders.push(user_send);
+            _ = reversed.insert(
+                key,
+                TransactionDelta {
+                    amount_pre: value.amount_pre,
+                    delta: -value.delta,
+                },
+            );
         }
 
-        for receiver in receiver_group.members.iter() {
-            let user_recv = match User::get(&mut *conn, UserId(receiver.id)).await? {
+        Transaction::apply_deltas(&mut *conn, reversed, settings).await?;
+
+        Ok(id)
+    }
+
+    /// The amounts the users paid or received with the transaction, as they
+    /// were stored when it was booked.
+    async fn get_transaction_delta<T>(
+        conn: &mut T,
+        transaction_id: DatabaseId,
+    ) -> Result<HashMap<User, TransactionDelta>, DBError>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        use tracing::error;
+
+        let mut delta_map = HashMap::new();
+
+        for (user_id, delta) in TransactionDB::get_shares(&mut *conn, transaction_id)
+            .await?
+            .into_iter()
+        {
+            let user = match User::get(&mut *conn, user_id).await? {
                 Some(val) => val,
                 None => {
-                    error!(
-                        "Failed to find a user that should exist! Id: {}",
-                        receiver.id
-                    );
+                    error!("Failed to find a user that should exist! Id: {}", user_id);
                     return Err(DBError::new("Failed to find user"));
                 }
             };
 
-            receivers.push(user_recv);
-        }
-
-        let mut delta_map = HashMap::new();
-
-        let mut full_cost = transaction_db.money;
-        let cost_share = transaction_db.money / sender_group.members.len() as u64;
-
-        for user in senders.iter().chain(receivers.iter()) {
             _ = delta_map.insert(
                 user.clone(),
                 TransactionDelta {
                     amount_pre: user.money.value,
-                    delta: 0,
+                    delta,
                 },
             );
         }
 
-        for sender in senders.iter() {
-            let user = match delta_map.get_mut(sender) {
-                Some(user) => user,
-                None => {
-                    error!("Failed to find user in HashMap where it should exist!");
-                    return Err(DBError::new("Failed to find user"));
-                }
+        if delta_map.is_empty() {
+            error!("Transaction {} has no shares!", transaction_id);
+            return Err(DBError::new("Failed to find the shares of the transaction"));
+        }
+
+        Ok(delta_map)
+    }
+
+    /// Divides `money` equally between the members of the sending and of the
+    /// receiving group. The cents that can't be divided are paid and received
+    /// by the first members.
+    fn equal_shares(
+        sender_group: &Group,
+        receiver_group: &Group,
+        money: Money,
+    ) -> Result<HashMap<UserId, i64>, CreateTransactionError> {
+        let mut shares = HashMap::<UserId, i64>::new();
+
+        for (group, sign) in [(sender_group, -1), (receiver_group, 1)] {
+            let amounts = match split_by_weights(money.value, &vec![1; group.members.len()]) {
+                Some(value) => value,
+                None => return Err(CreateTransactionError::new("Group has no members")),
             };
 
-            user.delta -= cost_share as i64;
-            full_cost -= cost_share;
+            for (member, amount) in group.members.iter().zip(amounts) {
+                *shares.entry(UserId(member.id)).or_default() += sign * amount;
+            }
         }
 
-        while full_cost > 0 {
-            for sender in senders.iter_mut() {
-                let user = match delta_map.get_mut(sender) {
-                    Some(user) => user,
-                    None => {
-                        error!("Failed to find user in HashMap where it should exist!");
-                        return Err(DBError::new("Failed to find user"));
-                    }
-                };
-                user.delta -= 1;
-                if full_cost == 0 {
-                    break;
-                }
+        Ok(shares)
+    }
 
-                full_cost -= 1;
-            }
+    pub async fn create<T>(
+        conn: &mut T,
+        sender: GroupId,
+        receiver: GroupId,
+        t_type: TransactionType,
+        description: Option<String>,
+        money: Money,
+        settings: &Settings,
+    ) -> Result<DatabaseId, CreateTransactionError>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        let ids = Transaction::create_batch(
+            &mut *conn,
+            sender,
+            receiver,
+            &[(t_type, description, money, 1)],
+            settings,
+        )
+        .await?;
+
+        match ids.first() {
+            Some(id) => Ok(*id),
+            None => Err(CreateTransactionError::new(
+                "Failed to find newly created transaction",
+            )),
         }
+    }
 
-        let cost_share = transaction_db.money / receiver_group.members.len() as u64;
+    /// Creates several transactions between the same groups. The limits are
+    /// checked once against the combined amount, so either all transactions
+    /// can be booked or none of them. The money of an entry is the total for
+    /// its quantity.
+    pub async fn create_batch<T>(
+        conn: &mut T,
+        sender: GroupId,
+        receiver: GroupId,
+        entries: &[(TransactionType, Option<String>, Money, u32)],
+        settings: &Settings,
+    ) -> Result<Vec<DatabaseId>, CreateTransactionError>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        use std::collections::hash_map::Entry;
 
-        for receiver in receivers.iter_mut() {
-            let user = match delta_map.get_mut(receiver) {
-                Some(user) => user,
-                None => {
-                    error!("Failed to find user in HashMap where it should exist!");
-                    return Err(DBError::new("Failed to find user"));
-                }
+        let (sender_group, receiver_group) = (
+            Group::get(&mut *conn, sender).await?,
+            Group::get(&mut *conn, receiver).await?,
+        );
+
+        let mut ids = Vec::with_capacity(entries.len());
+        let mut deltas = HashMap::<User, TransactionDelta>::new();
+
+        for (t_type, description, money, quantity) in entries.iter() {
+            let t_type_data = match t_type {
+                TransactionType::Bought(id) => Some(*id),
+                TransactionType::Received(id) => Some(id.0),
+
+                _ => None,
             };
-            user.delta += cost_share as i64;
-            full_cost += cost_share;
-        }
 
-        while full_cost < transaction_db.money {
-            for receiver in receivers.iter_mut() {
-                let user = match delta_map.get_mut(receiver) {
-                    Some(user) => user,
-                    None => {
-                        error!("Failed to find user in HashMap where it should exist!");
-                        return Err(DBError::new("Failed to find user"));
+            if let TransactionType::Bought(article_id) = t_type {
+                ArticleDB::take_stock(&mut *conn, *article_id, *quantity as i64, &settings.stock)
+                    .await?;
+            }
+
+            let t_id = TransactionDB::create(
+                &mut *conn,
+                sender,
+                receiver,
+                t_type_data,
+                description.clone(),
+                money.value,
+                *quantity as i64,
+            )
+            .await?;
+
+            let shares = Transaction::equal_shares(&sender_group, &receiver_group, *money)?;
+
+            for (user_id, delta) in shares.iter() {
+                TransactionDB::insert_share(&mut *conn, t_id, *user_id, *delta).await?;
+            }
+
+            let transaction_deltas = Transaction::get_transaction_delta(&mut *conn, t_id).await?;
+
+            for (key, value) in transaction_deltas.into_iter() {
+                match deltas.entry(key) {
+                    Entry::Occupied(mut entry) => entry.get_mut().delta += value.delta,
+                    Entry::Vacant(entry) => {
+                        entry.insert(value);
                     }
-                };
-                user.delta += 1;
-                if full_cost == transaction_db.money {
-                    break;
                 }
-
-                full_cost += 1;
             }
+
+            ids.push(t_id);
         }
 
-        Ok(delta_map)
+        Transaction::apply_deltas(&mut *conn, deltas, settings).await?;
+
+        Ok(ids)
     }
 
-    pub async fn create<T>(
+    /// Creates a transaction whose amounts per user are given by `shares`
+    /// instead of being divided equally between the group members. Positive
+    /// shares are received, negative ones are paid, they have to add up to
+    /// zero.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn create_with_shares<T>(
         conn: &mut T,
         sender: GroupId,
         receiver: GroupId,
         t_type: TransactionType,
         description: Option<String>,
         money: Money,
+        shares: &HashMap<UserId, i64>,
         settings: &Settings,
     ) -> Result<DatabaseId, CreateTransactionError>
     where
@@ -592,6 +1232,31 @@ impl Transaction {
     {
         type Error = CreateTransactionError;
 
+        let (sender_group, receiver_group) = (
+            Group::get(&mut *conn, sender).await?,
+            Group::get(&mut *conn, receiver).await?,
+        );
+
+        let is_member = |user_id: &UserId| {
+            sender_group
+                .members
+                .iter()
+                .chain(receiver_group.members.iter())
+                .any(|member| member.id == user_id.0)
+        };
+
+        if !shares.keys().all(is_member) {
+            return Err(Error::new(
+                "Shares may only be given to members of the groups",
+            ));
+        }
+
+        if shares.values().sum::<i64>() != 0
+            || shares.values().filter(|delta| **delta > 0).sum::<i64>() != money.value
+        {
+            return Err(Error::new("The shares don't add up to the amount"));
+        }
+
         let t_type_data = match t_type {
             TransactionType::Bought(id) => Some(id),
             TransactionType::Received(id) => Some(id.0),
@@ -599,6 +1264,10 @@ impl Transaction {
             _ => None,
         };
 
+        if let TransactionType::Bought(article_id) = t_type {
+            ArticleDB::take_stock(&mut *conn, article_id, 1, &settings.stock).await?;
+        }
+
         let t_id = TransactionDB::create(
             &mut *conn,
             sender,
@@ -606,55 +1275,86 @@ impl Transaction {
             t_type_data,
             description,
             money.value,
+            1,
         )
         .await?;
 
-        let transaction_db = match TransactionDB::get(&mut *conn, t_id).await? {
-            Some(val) => val,
-            None => return Err(Error::new("Failed to find newly created transaction")),
-        };
+        for (user_id, delta) in shares.iter() {
+            TransactionDB::insert_share(&mut *conn, t_id, *user_id, *delta).await?;
+        }
 
-        let (sender_group, receiver_group) = (
-            Group::get(&mut *conn, GroupId(transaction_db.sender)).await?,
-            Group::get(&mut *conn, GroupId(transaction_db.receiver)).await?,
-        );
+        let deltas = Transaction::get_transaction_delta(&mut *conn, t_id).await?;
 
-        let deltas = Transaction::get_transaction_delta(
-            &mut *conn,
-            &sender_group,
-            &receiver_group,
-            &transaction_db,
-        )
-        .await?;
+        Transaction::apply_deltas(&mut *conn, deltas, settings).await?;
 
-        let mut users_too_low = Vec::<String>::new();
-        let mut users_too_high = Vec::<String>::new();
+        Ok(t_id)
+    }
 
-        for (key, value) in deltas.iter() {
-            use crate::backend::db::{DBUSER_AUFLADUNG_ID, DBUSER_SNACKBAR_ID};
+    /// Checks the limits of all users and books the deltas if none is
+    /// exceeded.
+    async fn apply_deltas<T>(
+        conn: &mut T,
+        deltas: HashMap<User, TransactionDelta>,
+        settings: &Settings,
+    ) -> Result<(), CreateTransactionError>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        Transaction::check_deltas(&deltas, settings)?;
 
-            if key.id.0 == DBUSER_AUFLADUNG_ID.0 || key.id.0 == DBUSER_SNACKBAR_ID.0 {
+        for (mut key, value) in deltas.into_iter() {
+            key.add_money(&mut *conn, Money { value: value.delta })
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Fails if a delta touches an archived user or takes a user past their
+    /// limits, system users aren't checked.
+    fn check_deltas(
+        deltas: &HashMap<User, TransactionDelta>,
+        settings: &Settings,
+    ) -> Result<(), CreateTransactionError> {
+        let mut users_too_low = Vec::<(String, Money)>::new();
+        let mut users_too_high = Vec::<(String, Money)>::new();
+        let mut users_archived = Vec::<String>::new();
+
+        for (key, value) in deltas.iter() {
+            if key.is_system_user {
                 // don't do tracking on the system users
                 continue;
             }
 
-            if value.post_amount() > settings.accounts.upper_limit {
+            if key.is_archived {
+                users_archived.push(key.nickname.clone());
+                continue;
+            }
+
+            let upper_limit = key.get_upper_limit(settings);
+            let lower_limit = key.get_lower_limit(settings);
+
+            if value.post_amount() > upper_limit.value {
                 if value.delta < 0 {
                     // allow users to loose money
                     continue;
                 }
 
-                users_too_high.push(key.nickname.clone());
-            } else if value.post_amount() < settings.accounts.lower_limit {
+                users_too_high.push((key.nickname.clone(), upper_limit));
+            } else if value.post_amount() < lower_limit.value {
                 if value.delta > 0 {
                     // allow users to get money
                     continue;
                 }
 
-                users_too_low.push(key.nickname.clone());
+                users_too_low.push((key.nickname.clone(), lower_limit));
             }
         }
 
+        if !users_archived.is_empty() {
+            return Err(CreateTransactionError::ArchivedUserError(users_archived));
+        }
+
         if !users_too_low.is_empty() {
             return Err(CreateTransactionError::TooLittleMoneyError(users_too_low));
         }
@@ -663,11 +1363,96 @@ impl Transaction {
             return Err(CreateTransactionError::TooMuchMoneyError(users_too_high));
         }
 
-        for (mut key, value) in deltas.into_iter() {
-            key.add_money(&mut *conn, Money { value: value.delta })
-                .await?;
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{Transaction, TransactionDelta};
+    use crate::{
+        backend::{AccountsSettings, Settings, SoundSettings},
+        models::{Money, User, UserId},
+        routes::user::CreateTransactionError,
+    };
+
+    fn settings() -> Settings {
+        Settings {
+            sounds: SoundSettings {
+                articles: HashMap::new(),
+                generic: Vec::new(),
+                failed: Vec::new(),
+            },
+            accounts: AccountsSettings {
+                upper_limit: 10_000,
+                lower_limit: -5_000,
+            },
+            pin: Default::default(),
+            undo: Default::default(),
+            requests: Default::default(),
+            cash_box: Default::default(),
+            idempotency: Default::default(),
+            bank: Default::default(),
+            stock: Default::default(),
+        }
+    }
+
+    fn user(id: i64, money: i64, is_archived: bool) -> User {
+        User {
+            id: UserId(id),
+            nickname: format!("user{id}"),
+            cards: Vec::new(),
+            money: Money { value: money },
+            is_archived,
+            is_system_user: false,
+            lower_limit: None,
+            upper_limit: None,
+            has_pin: false,
         }
+    }
+
+    fn deltas(entries: &[(User, i64)]) -> HashMap<User, TransactionDelta> {
+        entries
+            .iter()
+            .map(|(user, delta)| {
+                (
+                    user.clone(),
+                    TransactionDelta {
+                        amount_pre: user.money.value,
+                        delta: *delta,
+                    },
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reverting_a_transfer_to_an_archived_user_fails() {
+        // B sent 500 to A, A spent it and was archived, B undoes the transfer
+        let reversal = deltas(&[(user(1, 0, true), -500), (user(2, 1_000, false), 500)]);
+
+        assert!(matches!(
+            Transaction::check_deltas(&reversal, &settings()),
+            Err(CreateTransactionError::ArchivedUserError(users)) if users == ["user1"]
+        ));
+    }
+
+    #[test]
+    fn reverting_past_the_lower_limit_fails() {
+        let reversal = deltas(&[(user(1, -4_800, false), -500), (user(2, 0, false), 500)]);
+
+        assert!(matches!(
+            Transaction::check_deltas(&reversal, &settings()),
+            Err(CreateTransactionError::TooLittleMoneyError(_))
+        ));
+    }
+
+    #[test]
+    fn reverting_within_the_limits_succeeds() {
+        let reversal = deltas(&[(user(1, 1_000, false), -500), (user(2, 0, false), 500)]);
 
-        Ok(transaction_db.id)
+        assert!(Transaction::check_deltas(&reversal, &settings()).is_ok());
     }
 }
@@ -7,16 +7,17 @@ use super::{DatabaseId, Money};
 
 #[cfg(feature = "ssr")]
 use {
-    super::TransactionDB,
+    super::{IdempotencyKey, MoneyRequestDB, RecurringChargeDB, TransactionDB},
     crate::backend::db::{DBError, DB},
     crate::backend::db::{DatabaseResponse, DatabaseType},
+    crate::backend::Settings,
     crate::models::GroupDB,
     sqlx::query,
     sqlx::query_as,
     sqlx::Executor,
 };
 
-#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct GroupId(pub DatabaseId);
 #[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct UserId(pub DatabaseId);
@@ -53,6 +54,10 @@ pub struct UserDB {
     pub nickname: String,
     pub money: i64,
     pub is_system_user: bool,
+    pub is_archived: bool,
+    pub lower_limit: Option<i64>,
+    pub upper_limit: Option<i64>,
+    pub pin_hash: Option<String>,
 }
 
 #[cfg(feature = "ssr")]
@@ -98,27 +103,73 @@ impl UserDB {
         .map(|elem| elem.id.into())
     }
 
+    pub async fn insert_system<T>(conn: &mut T, nickname: String) -> DatabaseResponse<UserId>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        query!(
+            "
+                insert into Users
+                    (nickname, money, is_system_user)
+                values
+                    (?, ?, true)
+                returning id
+            ",
+            nickname,
+            0
+        )
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(From::from)
+        .map(|elem| elem.id.into())
+    }
+
     pub async fn insert_card<T>(
         conn: &mut T,
         user_id: UserId,
         card_number: String,
-    ) -> DatabaseResponse<()>
+        label: Option<String>,
+    ) -> DatabaseResponse<DatabaseId>
     where
         for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
     {
         query!(
             "
                 insert into UserCardNumberMap
-                    (user_id, card_number)
+                    (user_id, card_number, label)
                 values
-                    (?, ?)
+                    (?, ?, ?)
+                returning id
             ",
             user_id.0,
-            card_number
+            card_number,
+            label
         )
         .fetch_one(&mut *conn)
         .await
         .map_err(From::from)
+        .map(|elem| elem.id)
+    }
+
+    pub async fn delete_card<T>(
+        conn: &mut T,
+        user_id: UserId,
+        card_id: DatabaseId,
+    ) -> DatabaseResponse<()>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        query!(
+            "
+                delete from UserCardNumberMap
+                where id = ? and user_id = ?
+            ",
+            card_id,
+            user_id.0
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(From::from)
         .map(|_| ())
     }
 
@@ -162,83 +213,60 @@ impl UserDB {
         .map(|_| ())
     }
 
-    async fn set_card_number<T>(
-        conn: &mut T,
-        user_id: UserId,
-        new_value: Option<String>,
-    ) -> DatabaseResponse<()>
+    async fn move_cards<T>(conn: &mut T, from: UserId, to: UserId) -> DatabaseResponse<()>
     where
         for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
     {
-        let user_id = user_id.0;
-        match new_value {
-            Some(new_value) => {
-                let card_number_exists = Self::get_card_number(&mut *conn, user_id).await?;
-
-                match card_number_exists {
-                    None => query!(
-                        "
-                                insert into UserCardNumberMap
-                                    (user_id, card_number)
-                                values
-                                    (?, ?)
-                            ",
-                        user_id,
-                        new_value
-                    )
-                    .execute(&mut *conn)
-                    .await
-                    .map_err(From::from)
-                    .map(|_| ()),
-
-                    Some(_) => query!(
-                        "
-                                update UserCardNumberMap
-                                set
-                                    card_number = ?
-                                where user_id = ?
-                            ",
-                        new_value,
-                        user_id
-                    )
-                    .execute(&mut *conn)
-                    .await
-                    .map_err(From::from)
-                    .map(|_| ()),
-                }
-            }
-
-            // No card number was submitted to the server
-            None => query!(
-                "
-                        delete from UserCardNumberMap
-                        where user_id = ?
-                    ",
-                user_id
-            )
-            .execute(&mut *conn)
-            .await
-            .map_err(From::from)
-            .map(|_| ()),
-        }
+        query!(
+            "
+                update UserCardNumberMap
+                set user_id = ?
+                where user_id = ?
+            ",
+            to.0,
+            from.0
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(From::from)
+        .map(|_| ())
     }
 
-    async fn get_card_number<T>(conn: &mut T, user_id: i64) -> DatabaseResponse<Option<String>>
+    /// The user must not be part of any group anymore.
+    async fn delete<T>(conn: &mut T, id: UserId) -> DatabaseResponse<()>
     where
         for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
     {
         query!(
             "
-                select card_number
+                delete from Users
+                where id = ?
+            ",
+            id.0
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(From::from)
+        .map(|_| ())
+    }
+
+    async fn get_cards<T>(conn: &mut T, user_id: i64) -> DatabaseResponse<Vec<UserCard>>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        query_as!(
+            UserCard,
+            "
+                select id, card_number, label
                 from UserCardNumberMap
                 where user_id = ?
+                order by id
             ",
             user_id
         )
-        .fetch_optional(&mut *conn)
+        .fetch_all(&mut *conn)
         .await
         .map_err(From::from)
-        .map(|result| result.map(|elem| elem.card_number))
     }
 
     pub async fn get<T>(conn: &mut T, id: i64) -> DatabaseResponse<Option<Self>>
@@ -268,7 +296,43 @@ impl UserDB {
             "
                 select *
                 from Users
-                where is_system_user = false
+                where is_system_user = false and is_archived = false
+            ",
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(From::from)
+    }
+
+    pub async fn get_all_system<T>(conn: &mut T) -> DatabaseResponse<Vec<Self>>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        query_as!(
+            UserDB,
+            "
+                select *
+                from Users
+                where is_system_user = true
+                order by id
+            ",
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(From::from)
+    }
+
+    /// All users, including system and archived users.
+    pub async fn get_all_unfiltered<T>(conn: &mut T) -> DatabaseResponse<Vec<Self>>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        query_as!(
+            UserDB,
+            "
+                select *
+                from Users
+                order by id
             ",
         )
         .fetch_all(&mut *conn)
@@ -276,6 +340,90 @@ impl UserDB {
         .map_err(From::from)
     }
 
+    async fn get_all_archived<T>(conn: &mut T) -> DatabaseResponse<Vec<Self>>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        query_as!(
+            UserDB,
+            "
+                select *
+                from Users
+                where is_system_user = false and is_archived = true
+            ",
+        )
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(From::from)
+    }
+
+    async fn set_archived<T>(conn: &mut T, id: UserId, new_value: bool) -> DatabaseResponse<()>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        query!(
+            "
+                update Users
+                set is_archived = ?
+                where id = ?
+            ",
+            new_value,
+            id.0
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(From::from)
+        .map(|_| ())
+    }
+
+    async fn set_limits<T>(
+        conn: &mut T,
+        id: UserId,
+        lower_limit: Option<i64>,
+        upper_limit: Option<i64>,
+    ) -> DatabaseResponse<()>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        query!(
+            "
+                update Users
+                set lower_limit = ?, upper_limit = ?
+                where id = ?
+            ",
+            lower_limit,
+            upper_limit,
+            id.0
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(From::from)
+        .map(|_| ())
+    }
+
+    async fn set_pin_hash<T>(
+        conn: &mut T,
+        id: UserId,
+        pin_hash: Option<String>,
+    ) -> DatabaseResponse<()>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        query!(
+            "
+                update Users
+                set pin_hash = ?
+                where id = ?
+            ",
+            pin_hash,
+            id.0
+        )
+        .execute(&mut *conn)
+        .await
+        .map_err(From::from)
+        .map(|_| ())
+    }
+
     async fn get_by_nick<T>(conn: &mut T, nick: &String) -> DatabaseResponse<Option<Self>>
     where
         for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
@@ -295,12 +443,26 @@ impl UserDB {
     }
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct UserCard {
+    pub id: DatabaseId,
+    pub card_number: String,
+    pub label: Option<String>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct User {
     pub id: UserId,
     pub nickname: String,
-    pub card_number: Option<String>,
+    pub cards: Vec<UserCard>,
     pub money: Money,
+    pub is_archived: bool,
+    pub is_system_user: bool,
+    /// overrides `accounts.lower_limit` from the settings if set
+    pub lower_limit: Option<Money>,
+    /// overrides `accounts.upper_limit` from the settings if set
+    pub upper_limit: Option<Money>,
+    pub has_pin: bool,
 }
 
 #[cfg(feature = "ssr")]
@@ -323,17 +485,132 @@ impl User {
         Ok(())
     }
 
-    pub async fn set_card_number<T>(
+    pub async fn set_archived<T>(&mut self, conn: &mut T, new_value: bool) -> DatabaseResponse<()>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        UserDB::set_archived(&mut *conn, self.id, new_value).await?;
+
+        self.is_archived = new_value;
+
+        Ok(())
+    }
+
+    pub async fn set_limits<T>(
+        &mut self,
+        conn: &mut T,
+        lower_limit: Option<Money>,
+        upper_limit: Option<Money>,
+    ) -> DatabaseResponse<()>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        UserDB::set_limits(
+            &mut *conn,
+            self.id,
+            lower_limit.map(|limit| limit.value),
+            upper_limit.map(|limit| limit.value),
+        )
+        .await?;
+
+        self.lower_limit = lower_limit;
+        self.upper_limit = upper_limit;
+
+        Ok(())
+    }
+
+    pub fn get_lower_limit(&self, settings: &Settings) -> Money {
+        self.lower_limit
+            .unwrap_or(settings.accounts.lower_limit.into())
+    }
+
+    pub fn get_upper_limit(&self, settings: &Settings) -> Money {
+        self.upper_limit
+            .unwrap_or(settings.accounts.upper_limit.into())
+    }
+
+    /// Stores a salted hash of the given PIN, `None` removes the PIN.
+    pub async fn set_pin<T>(&mut self, conn: &mut T, pin: Option<String>) -> DatabaseResponse<()>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        use argon2::{
+            password_hash::{rand_core::OsRng, SaltString},
+            Argon2, PasswordHasher,
+        };
+
+        let pin_hash = match pin {
+            None => None,
+            Some(pin) => {
+                let salt = SaltString::generate(&mut OsRng);
+                let hash = Argon2::default()
+                    .hash_password(pin.as_bytes(), &salt)
+                    .map_err(DBError::new)?;
+
+                Some(hash.to_string())
+            }
+        };
+
+        UserDB::set_pin_hash(&mut *conn, self.id, pin_hash.clone()).await?;
+
+        self.has_pin = pin_hash.is_some();
+
+        Ok(())
+    }
+
+    pub async fn verify_pin<T>(&self, conn: &mut T, pin: &str) -> DatabaseResponse<bool>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        use argon2::{password_hash::PasswordHash, Argon2, PasswordVerifier};
+
+        let pin_hash = match UserDB::get(&mut *conn, self.id.0).await? {
+            Some(UserDB {
+                pin_hash: Some(pin_hash),
+                ..
+            }) => pin_hash,
+            _ => return Ok(false),
+        };
+
+        let pin_hash = PasswordHash::new(&pin_hash).map_err(DBError::new)?;
+
+        Ok(Argon2::default()
+            .verify_password(pin.as_bytes(), &pin_hash)
+            .is_ok())
+    }
+
+    pub async fn add_card<T>(
+        &mut self,
+        conn: &mut T,
+        card_number: String,
+        label: Option<String>,
+    ) -> DatabaseResponse<()>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        let id =
+            UserDB::insert_card(&mut *conn, self.id, card_number.clone(), label.clone()).await?;
+
+        self.cards.push(UserCard {
+            id,
+            card_number,
+            label,
+        });
+
+        Ok(())
+    }
+
+    pub async fn remove_card<T>(
         &mut self,
         conn: &mut T,
-        new_value: Option<String>,
+        card_id: DatabaseId,
     ) -> DatabaseResponse<()>
     where
         for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
     {
-        UserDB::set_card_number(&mut *conn, self.id, new_value.clone()).await?;
+        UserDB::delete_card(&mut *conn, self.id, card_id).await?;
 
-        self.card_number = new_value;
+        self.cards.retain(|card| card.id != card_id);
 
         Ok(())
     }
@@ -350,7 +627,7 @@ impl User {
         match card_number {
             None => {}
             Some(card_number) => {
-                UserDB::insert_card(&mut *transaction, id, card_number).await?;
+                UserDB::insert_card(&mut *transaction, id, card_number, None).await?;
             }
         }
 
@@ -361,6 +638,20 @@ impl User {
         Ok(id)
     }
 
+    /// System accounts can't be used directly, they only receive money, e.g.
+    /// from recurring charges.
+    pub async fn create_system(db: &DB, nickname: String) -> DatabaseResponse<UserId> {
+        let mut transaction = db.get_conn_transaction().await?;
+
+        let id = UserDB::insert_system(&mut *transaction, nickname).await?;
+
+        let group = GroupDB::create(&mut *transaction).await?;
+        group.link_user(&mut *transaction, id).await?;
+
+        transaction.commit().await.map_err(DBError::new)?;
+        Ok(id)
+    }
+
     pub async fn get_all(db: &DB) -> Result<Vec<Self>, DBError> {
         let mut conn = db.get_conn().await?;
 
@@ -378,16 +669,38 @@ impl User {
         Ok(users)
     }
 
+    pub async fn get_all_archived(db: &DB) -> Result<Vec<Self>, DBError> {
+        let mut conn = db.get_conn().await?;
+
+        let users_db = UserDB::get_all_archived(&mut *conn).await?;
+        let mut users = Vec::<User>::new();
+
+        for user_db in users_db.into_iter() {
+            users.push(
+                Self::get(&mut *conn, UserId(user_db.id))
+                    .await?
+                    .expect("user should exist"),
+            )
+        }
+
+        Ok(users)
+    }
+
     pub async fn get_transactions(
         &self,
         db: &DB,
         limit: usize,
     ) -> DatabaseResponse<Page<TransactionDB>> {
-        use crate::models::PageRequestParams;
+        use crate::models::{PageRequestParams, TransactionFilter};
 
         let mut conn = db.get_conn().await?;
-        TransactionDB::get_user_transactions(&mut *conn, self.id, PageRequestParams::new(limit))
-            .await
+        TransactionDB::get_user_transactions(
+            &mut *conn,
+            self.id,
+            PageRequestParams::new(limit),
+            &TransactionFilter::default(),
+        )
+        .await
     }
 
     pub async fn get_by_card_number(
@@ -419,15 +732,25 @@ impl User {
                     id,
                     nickname,
                     money,
+                    is_archived,
+                    is_system_user,
+                    lower_limit,
+                    upper_limit,
+                    pin_hash,
                     ..
                 } = value;
-                let card_number = UserDB::get_card_number(&mut *conn, id).await?;
+                let cards = UserDB::get_cards(&mut *conn, id).await?;
 
                 Ok(Some(User {
                     id: id.into(),
                     nickname,
-                    card_number,
+                    cards,
                     money: money.into(),
+                    is_archived,
+                    is_system_user,
+                    lower_limit: lower_limit.map(Money::from),
+                    upper_limit: upper_limit.map(Money::from),
+                    has_pin: pin_hash.is_some(),
                 }))
             }
         }
@@ -444,20 +767,88 @@ impl User {
                     id,
                     nickname,
                     money,
+                    is_archived,
+                    is_system_user,
+                    lower_limit,
+                    upper_limit,
+                    pin_hash,
                     ..
                 } = value;
-                let card_number = UserDB::get_card_number(&mut *conn, id).await?;
+                let cards = UserDB::get_cards(&mut *conn, id).await?;
 
                 Ok(Some(User {
                     id: UserId(id),
                     nickname,
-                    card_number,
+                    cards,
                     money: money.into(),
+                    is_archived,
+                    is_system_user,
+                    lower_limit: lower_limit.map(Money::from),
+                    upper_limit: upper_limit.map(Money::from),
+                    has_pin: pin_hash.is_some(),
                 }))
             }
         }
     }
 
+    /// Moves the balance, transactions, groups and cards of `other` to this user
+    /// and deletes `other` afterwards.
+    pub async fn merge<T>(&mut self, conn: &mut T, other: User) -> DatabaseResponse<()>
+    where
+        for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+    {
+        let own_group = GroupDB::get_single_group(&mut *conn, self.id).await?;
+        let other_group = GroupDB::get_single_group(&mut *conn, other.id).await?;
+
+        TransactionDB::move_group(&mut *conn, other_group.into(), own_group.into()).await?;
+        GroupDB::from(other_group).delete(&mut *conn).await?;
+
+        // only groups with multiple users are left
+        for group in GroupDB::get_groups(&mut *conn, other.id).await? {
+            let mut members = GroupDB::get_members(&mut *conn, group.id)
+                .await?
+                .into_iter()
+                .map(|member| UserId(member.id))
+                .filter(|id| *id != other.id && *id != self.id)
+                .collect::<Vec<_>>();
+            members.push(self.id);
+
+            match GroupDB::get_group_by_members(&mut *conn, &members).await? {
+                Some(existing) => {
+                    TransactionDB::move_group(&mut *conn, (&group).into(), existing.into()).await?;
+                    group.delete(&mut *conn).await?;
+                }
+                None => {
+                    let contains_self = GroupDB::get_members(&mut *conn, group.id)
+                        .await?
+                        .iter()
+                        .any(|member| member.id == self.id.0);
+
+                    group.unlink_user(&mut *conn, other.id).await?;
+
+                    if !contains_self {
+                        group.link_user(&mut *conn, self.id).await?;
+                    }
+                }
+            }
+        }
+
+        TransactionDB::move_shares(&mut *conn, other.id, self.id).await?;
+        TransactionDB::move_undone_by(&mut *conn, other.id, self.id).await?;
+
+        RecurringChargeDB::move_user(&mut *conn, other.id, self.id).await?;
+        MoneyRequestDB::move_user(&mut *conn, other.id, self.id).await?;
+        IdempotencyKey::delete_user(&mut *conn, other.id).await?;
+
+        UserDB::move_cards(&mut *conn, other.id, self.id).await?;
+        self.cards.extend(other.cards);
+
+        self.add_money(&mut *conn, other.money).await?;
+        self.money.value += other.money.value;
+
+        UserDB::delete(&mut *conn, other.id).await
+    }
+
     pub async fn add_money<T>(&mut self, conn: &mut T, money: Money) -> DatabaseResponse<()>
     where
         for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
@@ -0,0 +1,356 @@
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{models::BankImportPreview, routes::admin::recurring::error_view};
+
+#[cfg(feature = "ssr")]
+use {
+    crate::backend::db::DBGROUP_AUFLADUNG_ID,
+    crate::models::{
+        parse_bank_statement, BankImportLine, Group, Transaction, TransactionType, User,
+    },
+    tracing::{error, info},
+};
+
+/// What the treasurer decided for a line of the statement, `accept` is only
+/// sent for checked lines.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct BankImportDecision {
+    pub import_id: String,
+    pub nickname: Option<String>,
+    pub accept: Option<String>,
+}
+
+#[server]
+pub async fn preview_bank_import(content: String) -> Result<BankImportPreview, ServerFnError> {
+    use crate::backend::ServerState;
+    let state: ServerState = expect_context();
+    use axum::http::StatusCode;
+    use leptos_axum::ResponseOptions;
+
+    let response_opts: ResponseOptions = expect_context();
+
+    let statement = match parse_bank_statement(&content) {
+        Ok(value) => value,
+        Err(e) => {
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(ServerFnError::new(e));
+        }
+    };
+
+    let db = state.db.lock().await;
+
+    let mut conn = match db.get_conn().await {
+        Ok(value) => value,
+        Err(e) => {
+            error!("Failed to get db handle: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(ServerFnError::new("Failed to get database!"));
+        }
+    };
+
+    match BankImportLine::review(&mut *conn, statement.lines).await {
+        Ok(lines) => Ok(BankImportPreview {
+            lines,
+            skipped: statement.skipped,
+        }),
+        Err(e) => {
+            error!("Failed to review bank statement: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            Err(ServerFnError::new("Failed to review bank statement!"))
+        }
+    }
+}
+
+/// Books the accepted lines as deposits, lines that were booked by an
+/// earlier import are skipped.
+#[server]
+pub async fn book_bank_import(
+    content: String,
+    lines: Option<Vec<BankImportDecision>>,
+) -> Result<usize, ServerFnError> {
+    use crate::backend::ServerState;
+    let state: ServerState = expect_context();
+    use axum::http::StatusCode;
+    use leptos_axum::ResponseOptions;
+
+    let response_opts: ResponseOptions = expect_context();
+
+    let statement = match parse_bank_statement(&content) {
+        Ok(value) => value,
+        Err(e) => {
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(ServerFnError::new(e));
+        }
+    };
+
+    let db = state.db.lock().await;
+
+    let mut db_trans = match db.get_conn_transaction().await {
+        Ok(value) => value,
+        Err(e) => {
+            error!("Failed to get db transaction: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(ServerFnError::new("Failed to get database!"));
+        }
+    };
+
+    let mut booked = 0;
+
+    for decision in lines.unwrap_or_default() {
+        if decision.accept.is_none() {
+            continue;
+        }
+
+        let Some(line) = statement
+            .lines
+            .iter()
+            .find(|line| line.import_id == decision.import_id)
+        else {
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(ServerFnError::new(
+                "The statement changed, please review it again!",
+            ));
+        };
+
+        match BankImportLine::is_booked(&mut *db_trans, &line.import_id).await {
+            Ok(false) => {}
+            Ok(true) => continue,
+            Err(e) => {
+                error!("Failed to look up bank import: {e}");
+                response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+                return Err(ServerFnError::new("Failed to look up bank import!"));
+            }
+        }
+
+        let nickname = decision.nickname.unwrap_or_default().trim().to_string();
+
+        if nickname.is_empty() {
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(ServerFnError::new(format!(
+                "Choose a user for the payment of {} on {}!",
+                line.money.format_eur(),
+                line.date
+            )));
+        }
+
+        let user = match User::get_by_nick(&mut *db_trans, &nickname).await {
+            Ok(Some(value)) if !value.is_system_user && !value.is_archived => value,
+            Ok(Some(_)) => {
+                response_opts.set_status(StatusCode::BAD_REQUEST);
+                return Err(ServerFnError::new(format!(
+                    "User '{nickname}' can't receive bank transfers!"
+                )));
+            }
+            Ok(None) => {
+                response_opts.set_status(StatusCode::BAD_REQUEST);
+                return Err(ServerFnError::new(format!(
+                    "User '{nickname}' was not found!"
+                )));
+            }
+            Err(e) => {
+                error!("Failed to lookup db: {e}");
+                response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+                return Err(ServerFnError::new("Failed to lookup db"));
+            }
+        };
+
+        let user_group = match Group::get_user_group_id(&mut *db_trans, user.id).await {
+            Ok(value) => value,
+            Err(e) => {
+                error!("Failed to get user group: {e}");
+                response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+                return Err(ServerFnError::new("Failed to get user group"));
+            }
+        };
+
+        let transaction_id = match Transaction::create(
+            &mut *db_trans,
+            DBGROUP_AUFLADUNG_ID,
+            user_group,
+            TransactionType::Deposit,
+            Some("Bank transfer".to_string()),
+            line.money,
+            &state.settings,
+        )
+        .await
+        {
+            Ok(value) => value,
+            Err(e) => {
+                response_opts.set_status(StatusCode::BAD_REQUEST);
+                return Err(ServerFnError::new(format!(
+                    "Failed to book the payment for {nickname}: {e}"
+                )));
+            }
+        };
+
+        if let Err(e) =
+            BankImportLine::set_booked(&mut *db_trans, &line.import_id, transaction_id).await
+        {
+            error!("Failed to store bank import: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(ServerFnError::new("Failed to store bank import!"));
+        }
+
+        booked += 1;
+    }
+
+    if let Err(e) = db_trans.commit().await {
+        error!("Failed to commit bank import: {e}");
+        response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+        return Err(ServerFnError::new("Failed to book bank import!"));
+    }
+
+    info!("Booked {booked} bank transfers");
+
+    Ok(booked)
+}
+
+#[component]
+pub fn BankImport() -> impl IntoView {
+    let book_action = ServerAction::<BookBankImport>::new();
+
+    let content = RwSignal::new(String::new());
+    let review_version = RwSignal::new(0_u32);
+
+    let review_resource = Resource::new(
+        move || (review_version.get(), book_action.version().get()),
+        move |_| {
+            let content = content.get_untracked();
+            async move {
+                match content.trim().is_empty() {
+                    true => Ok(BankImportPreview {
+                        lines: Vec::new(),
+                        skipped: Vec::new(),
+                    }),
+                    false => preview_bank_import(content).await,
+                }
+            }
+        },
+    );
+
+    let input_class = "text-black rounded p-1";
+    let button_class = "text-white hover:bg-pink-700 bg-emerald-700 rounded-full p-2";
+
+    view! {
+        <div class="flex flex-col items-center gap-3 pt-10 text-white">
+            <p class="text-[1.25em]">"Bank import"</p>
+            <textarea
+                class="text-black rounded p-1 w-full max-w-3xl h-40"
+                placeholder="Paste a CAMT.053 XML or CSV export of the bank account"
+                bind:value=content
+            />
+            <button class=button_class on:click=move |_| review_version.update(|value| *value += 1)>
+                "Review"
+            </button>
+            <Suspense fallback=move || view! { <p>"Reading statement..."</p> }>
+                {move || {
+                    review_resource
+                        .get()
+                        .map(|preview| match preview {
+                            Err(e) => error_view(Some(Err(e))),
+                            Ok(BankImportPreview { lines, skipped }) => {
+                                view! {
+                                    {(!skipped.is_empty())
+                                        .then(|| {
+                                            view! {
+                                                <p>"Skipped rows that are no payments:"</p>
+                                                <ul>
+                                                    {skipped
+                                                        .iter()
+                                                        .map(|row| {
+                                                            view! {
+                                                                <li>
+                                                                    {format!(
+                                                                        "Row {}: {} ({})",
+                                                                        row.row,
+                                                                        row.content,
+                                                                        row.reason,
+                                                                    )}
+                                                                </li>
+                                                            }
+                                                        })
+                                                        .collect_view()}
+                                                </ul>
+                                            }
+                                        })}
+                                    {(!lines.is_empty())
+                                        .then(|| {
+                                            view! {
+                                                <ActionForm action=book_action>
+                                                    <div class="flex flex-col items-center gap-2">
+                                                        <input type="hidden" name="content" value=content.get_untracked() />
+                                                        <table>
+                                                            <tr>
+                                                                <th class="px-3">"Book"</th>
+                                                                <th class="px-3">"Date"</th>
+                                                                <th class="px-3">"Amount"</th>
+                                                                <th class="px-3">"From"</th>
+                                                                <th class="px-3">"Reference"</th>
+                                                                <th class="px-3">"User"</th>
+                                                            </tr>
+                                                            {lines
+                                                                .into_iter()
+                                                                .enumerate()
+                                                                .map(|(index, line)| {
+                                                                    let is_matched = line.user.is_some();
+                                                                    view! {
+                                                                        <tr class:text-gray-400=line.is_booked>
+                                                                            <td class="px-3">
+                                                                                <input
+                                                                                    type="hidden"
+                                                                                    name=format!("lines[{index}][import_id]")
+                                                                                    value=line.line.import_id.clone()
+                                                                                />
+                                                                                {match line.is_booked {
+                                                                                    true => view! { "booked" }.into_any(),
+                                                                                    false => {
+                                                                                        view! {
+                                                                                            <input
+                                                                                                type="checkbox"
+                                                                                                name=format!("lines[{index}][accept]")
+                                                                                                checked=is_matched
+                                                                                            />
+                                                                                        }
+                                                                                            .into_any()
+                                                                                    }
+                                                                                }}
+                                                                            </td>
+                                                                            <td class="px-3">{line.line.date.clone()}</td>
+                                                                            <td class="px-3">{line.line.money.format_eur()}</td>
+                                                                            <td class="px-3">{line.line.name.clone()}</td>
+                                                                            <td class="px-3">{line.line.text.clone()}</td>
+                                                                            <td class="px-3">
+                                                                                <input
+                                                                                    class=input_class
+                                                                                    class:bg-red-200=!is_matched
+                                                                                    name=format!("lines[{index}][nickname]")
+                                                                                    placeholder="Nickname"
+                                                                                    value=line.user.map(|(_, nickname)| nickname)
+                                                                                    disabled=line.is_booked
+                                                                                />
+                                                                            </td>
+                                                                        </tr>
+                                                                    }
+                                                                })
+                                                                .collect_view()}
+                                                        </table>
+                                                        <input class=button_class type="submit" value="Book checked lines" />
+                                                    </div>
+                                                </ActionForm>
+                                            }
+                                        })}
+                                }
+                                    .into_any()
+                            }
+                        })
+                }}
+            </Suspense>
+            {move || match book_action.value().get() {
+                Some(Ok(booked)) => view! { <p>"Booked "{booked}" deposits"</p> }.into_any(),
+                Some(Err(e)) => error_view(Some(Err(e))),
+                None => ().into_any(),
+            }}
+        </div>
+    }
+}
@@ -0,0 +1,326 @@
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    models::{CashClosing, CashCount, Money, DENOMINATIONS},
+    routes::admin::recurring::error_view,
+};
+
+#[cfg(feature = "ssr")]
+use {
+    crate::models::CashClosingKind,
+    chrono::Utc,
+    tracing::{error, info},
+};
+
+/// How many counts and closings are shown.
+#[cfg(feature = "ssr")]
+const CASH_BOX_HISTORY: u64 = 30;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CashBoxOverview {
+    pub expected: Money,
+    pub counts: Vec<CashCount>,
+    pub closings: Vec<CashClosing>,
+}
+
+#[server]
+pub async fn get_cash_box() -> Result<CashBoxOverview, ServerFnError> {
+    use crate::backend::ServerState;
+    let state: ServerState = expect_context();
+    use axum::http::StatusCode;
+    use leptos_axum::ResponseOptions;
+
+    let response_opts: ResponseOptions = expect_context();
+
+    let db = state.db.lock().await;
+
+    let mut conn = match db.get_conn().await {
+        Ok(value) => value,
+        Err(e) => {
+            error!("Failed to get db handle: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(ServerFnError::new("Failed to get database!"));
+        }
+    };
+
+    let overview = async {
+        Ok::<_, crate::backend::db::DBError>(CashBoxOverview {
+            expected: CashCount::get_expected(&mut *conn, Utc::now()).await?,
+            counts: CashCount::get_latest(&mut *conn, CASH_BOX_HISTORY).await?,
+            closings: CashClosing::get_latest(&mut *conn, CASH_BOX_HISTORY).await?,
+        })
+    }
+    .await;
+
+    match overview {
+        Ok(value) => Ok(value),
+        Err(e) => {
+            error!("Failed to fetch cash box: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            Err(ServerFnError::new("Failed to fetch cash box!"))
+        }
+    }
+}
+
+/// Stores a count of the box, `quantities` holds the number of coins or notes
+/// of each of the [`DENOMINATIONS`], empty inputs count as none.
+#[server]
+pub async fn count_cash_box(
+    quantities: Option<Vec<String>>,
+    note: String,
+) -> Result<(), ServerFnError> {
+    use crate::backend::ServerState;
+    let state: ServerState = expect_context();
+    use axum::http::StatusCode;
+    use leptos_axum::ResponseOptions;
+
+    let response_opts: ResponseOptions = expect_context();
+
+    let mut denominations = Vec::with_capacity(DENOMINATIONS.len());
+
+    for (denomination, quantity) in DENOMINATIONS
+        .iter()
+        .zip(quantities.unwrap_or_default().iter())
+    {
+        let quantity = match quantity.trim() {
+            "" => 0,
+            value => match value.parse::<u32>() {
+                Ok(value) => value,
+                Err(_) => {
+                    response_opts.set_status(StatusCode::BAD_REQUEST);
+                    return Err(ServerFnError::new(format!(
+                        "Invalid number of {}: '{}'",
+                        Money::format_eur_value(*denomination),
+                        quantity
+                    )));
+                }
+            },
+        };
+
+        denominations.push((*denomination, quantity));
+    }
+
+    let note = match note.trim().is_empty() {
+        true => None,
+        false => Some(note),
+    };
+
+    let db = state.db.lock().await;
+
+    let mut db_trans = match db.get_conn_transaction().await {
+        Ok(value) => value,
+        Err(e) => {
+            error!("Failed to get db transaction: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(ServerFnError::new("Failed to get database!"));
+        }
+    };
+
+    let id = match CashCount::create(&mut *db_trans, &denominations, note).await {
+        Ok(value) => value,
+        Err(e) => {
+            error!("Failed to store cash count: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(ServerFnError::new("Failed to store cash count!"));
+        }
+    };
+
+    if let Err(e) = db_trans.commit().await {
+        error!("Failed to commit cash count: {e}");
+        response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+        return Err(ServerFnError::new("Failed to store cash count!"));
+    }
+
+    info!("Stored cash count {id}");
+
+    Ok(())
+}
+
+/// Closes the cash box now.
+#[server]
+pub async fn close_cash_box() -> Result<(), ServerFnError> {
+    use crate::backend::ServerState;
+    let state: ServerState = expect_context();
+    use axum::http::StatusCode;
+    use leptos_axum::ResponseOptions;
+
+    let response_opts: ResponseOptions = expect_context();
+
+    let db = state.db.lock().await;
+
+    let mut db_trans = match db.get_conn_transaction().await {
+        Ok(value) => value,
+        Err(e) => {
+            error!("Failed to get db transaction: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(ServerFnError::new("Failed to get database!"));
+        }
+    };
+
+    if let Err(e) = CashClosing::create(&mut *db_trans, CashClosingKind::AdHoc, Utc::now()).await {
+        error!("Failed to close cash box: {e}");
+        response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+        return Err(ServerFnError::new("Failed to close cash box!"));
+    }
+
+    if let Err(e) = db_trans.commit().await {
+        error!("Failed to commit cash closing: {e}");
+        response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+        return Err(ServerFnError::new("Failed to close cash box!"));
+    }
+
+    Ok(())
+}
+
+#[component]
+pub fn CashBox() -> impl IntoView {
+    let count_action = ServerAction::<CountCashBox>::new();
+    let close_action = ServerAction::<CloseCashBox>::new();
+
+    let cash_box_resource = Resource::new(
+        move || (count_action.version().get(), close_action.version().get()),
+        |_| get_cash_box(),
+    );
+
+    let input_class = "text-black rounded p-1";
+    let button_class = "text-white hover:bg-pink-700 bg-emerald-700 rounded-full p-2";
+
+    view! {
+        <div class="flex flex-col items-center gap-3 pt-10 text-white">
+            <p class="text-[1.25em]">"Cash box"</p>
+            <Suspense fallback=move || view! { <p>"Loading cash box..."</p> }>
+                {move || {
+                    cash_box_resource
+                        .get()
+                        .map(|overview| match overview {
+                            Err(e) => error_view(Some(Err(e))),
+                            Ok(overview) => {
+                                view! {
+                                    <p>"Expected cash: "{overview.expected.format_eur()}</p>
+
+                                    <p class="pt-5">"Counts"</p>
+                                    <table>
+                                        <tr>
+                                            <th class="px-3">"Date"</th>
+                                            <th class="px-3">"Expected"</th>
+                                            <th class="px-3">"Counted"</th>
+                                            <th class="px-3">"Difference"</th>
+                                            <th class="px-3">"Coins and notes"</th>
+                                            <th class="px-3">"Note"</th>
+                                        </tr>
+                                        {overview
+                                            .counts
+                                            .into_iter()
+                                            .map(|count| {
+                                                view! {
+                                                    <tr class="align-top">
+                                                        <td class="px-3">
+                                                            {count.counted_at.format("%d.%m.%Y %H:%M").to_string()}
+                                                        </td>
+                                                        <td class="px-3">{count.expected.format_eur()}</td>
+                                                        <td class="px-3">{count.counted.format_eur()}</td>
+                                                        <td class="px-3">{count.difference().format_eur_diff()}</td>
+                                                        <td class="px-3">
+                                                            {count
+                                                                .denominations
+                                                                .iter()
+                                                                .map(|(denomination, quantity)| {
+                                                                    format!(
+                                                                        "{}× {}",
+                                                                        quantity,
+                                                                        Money::format_eur_value(*denomination),
+                                                                    )
+                                                                })
+                                                                .collect::<Vec<_>>()
+                                                                .join(", ")}
+                                                        </td>
+                                                        <td class="px-3">{count.note}</td>
+                                                    </tr>
+                                                }
+                                            })
+                                            .collect_view()}
+                                    </table>
+
+                                    <p class="pt-5">"Closings"</p>
+                                    <table>
+                                        <tr>
+                                            <th class="px-3">"Closed at"</th>
+                                            <th class="px-3">"Kind"</th>
+                                            <th class="px-3">"Opening"</th>
+                                            <th class="px-3">"Deposits"</th>
+                                            <th class="px-3">"Withdrawals"</th>
+                                            <th class="px-3">"Differences"</th>
+                                            <th class="px-3">"Closing"</th>
+                                        </tr>
+                                        {overview
+                                            .closings
+                                            .into_iter()
+                                            .map(|closing| {
+                                                view! {
+                                                    <tr>
+                                                        <td class="px-3">
+                                                            {closing.closed_at.format("%d.%m.%Y %H:%M").to_string()}
+                                                        </td>
+                                                        <td class="px-3">{closing.kind.as_str()}</td>
+                                                        <td class="px-3">{closing.opening.format_eur()}</td>
+                                                        <td class="px-3">{closing.deposits.format_eur()}</td>
+                                                        <td class="px-3">{closing.withdrawals.format_eur()}</td>
+                                                        <td class="px-3">{closing.differences.format_eur_diff()}</td>
+                                                        <td class="px-3">{closing.closing.format_eur()}</td>
+                                                    </tr>
+                                                }
+                                            })
+                                            .collect_view()}
+                                    </table>
+                                }
+                                    .into_any()
+                            }
+                        })
+                }}
+            </Suspense>
+
+            <div class="flex justify-center items-center gap-2">
+                <ActionForm action=close_action>
+                    <input class=button_class type="submit" value="Close now" />
+                </ActionForm>
+                <a class=button_class href="/admin/cash_box/closings.csv" rel="external">
+                    "Export closings"
+                </a>
+            </div>
+            {move || error_view(close_action.value().get())}
+
+            <p class="pt-5">"Count the box"</p>
+            <ActionForm action=count_action>
+                <div class="flex flex-col items-center gap-2">
+                    <table>
+                        {DENOMINATIONS
+                            .iter()
+                            .enumerate()
+                            .map(|(index, denomination)| {
+                                view! {
+                                    <tr>
+                                        <td class="px-3 text-right">
+                                            {Money::format_eur_value(*denomination)}
+                                        </td>
+                                        <td class="px-3">
+                                            <input
+                                                class=input_class
+                                                size=4
+                                                name=format!("quantities[{index}]")
+                                                placeholder="0"
+                                            />
+                                        </td>
+                                    </tr>
+                                }
+                            })
+                            .collect_view()}
+                    </table>
+                    <input class=input_class name="note" placeholder="Note" />
+                    <input class=button_class type="submit" value="Store count" />
+                </div>
+            </ActionForm>
+            {move || error_view(count_action.value().get())}
+        </div>
+    }
+}
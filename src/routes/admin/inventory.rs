@@ -0,0 +1,358 @@
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    models::{Article, StockMovement},
+    routes::admin::recurring::error_view,
+};
+
+#[cfg(feature = "ssr")]
+use {
+    crate::models::Money,
+    chrono::{NaiveDate, Utc},
+    tracing::{error, info},
+};
+
+/// How many stock movements are shown.
+#[cfg(feature = "ssr")]
+const INVENTORY_HISTORY: u64 = 30;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct InventoryOverview {
+    /// active articles and inactive ones whose stock isn't zero
+    pub articles: Vec<Article>,
+    pub movements: Vec<StockMovement>,
+}
+
+/// The counted stock of an article, empty inputs leave the stock unchanged.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct InventoryCount {
+    pub article_id: i64,
+    pub counted: String,
+}
+
+#[server]
+pub async fn get_inventory() -> Result<InventoryOverview, ServerFnError> {
+    use crate::backend::ServerState;
+    let state: ServerState = expect_context();
+    use axum::http::StatusCode;
+    use leptos_axum::ResponseOptions;
+
+    let response_opts: ResponseOptions = expect_context();
+
+    let db = state.db.lock().await;
+
+    let articles = match Article::get_all(&db, None, true).await {
+        Ok(value) => value,
+        Err(e) => {
+            error!("Failed to fetch articles: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(ServerFnError::new("Failed to fetch articles!"));
+        }
+    };
+
+    let mut conn = match db.get_conn().await {
+        Ok(value) => value,
+        Err(e) => {
+            error!("Failed to get db handle: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(ServerFnError::new("Failed to get database!"));
+        }
+    };
+
+    let movements = match StockMovement::get_latest(&mut *conn, INVENTORY_HISTORY).await {
+        Ok(value) => value,
+        Err(e) => {
+            error!("Failed to fetch stock movements: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(ServerFnError::new("Failed to fetch stock movements!"));
+        }
+    };
+
+    Ok(InventoryOverview {
+        articles: articles
+            .into_iter()
+            .filter(|article| article.is_active || article.stock.is_some_and(|stock| stock != 0))
+            .collect(),
+        movements,
+    })
+}
+
+/// Books a delivery of `quantity` units. An empty date is today,
+/// `purchase_price` is per unit and optional.
+#[server]
+pub async fn restock_article(
+    article_id: i64,
+    quantity: String,
+    moved_on: String,
+    purchase_price: String,
+) -> Result<(), ServerFnError> {
+    use crate::backend::ServerState;
+    let state: ServerState = expect_context();
+    use axum::http::StatusCode;
+    use leptos_axum::ResponseOptions;
+
+    let response_opts: ResponseOptions = expect_context();
+
+    let quantity = match quantity.trim().parse::<i64>() {
+        Ok(value) if value > 0 => value,
+        _ => {
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(ServerFnError::new(format!(
+                "Invalid quantity: '{quantity}'"
+            )));
+        }
+    };
+
+    let moved_on = match moved_on.trim() {
+        "" => Utc::now().date_naive(),
+        value => match NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+            Ok(value) => value,
+            Err(_) => {
+                response_opts.set_status(StatusCode::BAD_REQUEST);
+                return Err(ServerFnError::new(format!("Invalid date: '{value}'")));
+            }
+        },
+    };
+
+    let purchase_price: Option<Money> = match purchase_price.trim() {
+        "" => None,
+        value => match value.to_string().try_into() {
+            Ok(value) => Some(value),
+            Err(e) => {
+                response_opts.set_status(StatusCode::BAD_REQUEST);
+                return Err(ServerFnError::new(format!(
+                    "Invalid purchase price '{value}': {e}"
+                )));
+            }
+        },
+    };
+
+    let db = state.db.lock().await;
+
+    let mut db_trans = match db.get_conn_transaction().await {
+        Ok(value) => value,
+        Err(e) => {
+            error!("Failed to get db transaction: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(ServerFnError::new("Failed to get database!"));
+        }
+    };
+
+    if let Err(e) = StockMovement::restock(
+        &mut *db_trans,
+        article_id,
+        quantity,
+        moved_on,
+        purchase_price,
+    )
+    .await
+    {
+        error!("Failed to restock article {article_id}: {e}");
+        response_opts.set_status(StatusCode::BAD_REQUEST);
+        return Err(ServerFnError::new(format!("Failed to restock: {e}")));
+    }
+
+    if let Err(e) = db_trans.commit().await {
+        error!("Failed to commit restock: {e}");
+        response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+        return Err(ServerFnError::new("Failed to restock!"));
+    }
+
+    info!("Restocked {quantity} units of article {article_id}");
+
+    Ok(())
+}
+
+/// Sets the stock of the counted articles and books the differences.
+#[server]
+pub async fn count_inventory(counts: Option<Vec<InventoryCount>>) -> Result<(), ServerFnError> {
+    use crate::backend::ServerState;
+    let state: ServerState = expect_context();
+    use axum::http::StatusCode;
+    use leptos_axum::ResponseOptions;
+
+    let response_opts: ResponseOptions = expect_context();
+
+    let mut counted = Vec::new();
+
+    for count in counts.unwrap_or_default() {
+        match count.counted.trim() {
+            "" => {}
+            value => match value.parse::<i64>() {
+                Ok(value) if value >= 0 => counted.push((count.article_id, value)),
+                _ => {
+                    response_opts.set_status(StatusCode::BAD_REQUEST);
+                    return Err(ServerFnError::new(format!(
+                        "Invalid count: '{}'",
+                        count.counted
+                    )));
+                }
+            },
+        }
+    }
+
+    let db = state.db.lock().await;
+
+    let mut db_trans = match db.get_conn_transaction().await {
+        Ok(value) => value,
+        Err(e) => {
+            error!("Failed to get db transaction: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(ServerFnError::new("Failed to get database!"));
+        }
+    };
+
+    for (article_id, value) in counted.iter() {
+        if let Err(e) = StockMovement::correct(&mut *db_trans, *article_id, *value).await {
+            error!("Failed to correct stock of article {article_id}: {e}");
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(ServerFnError::new(format!("Failed to store count: {e}")));
+        }
+    }
+
+    if let Err(e) = db_trans.commit().await {
+        error!("Failed to commit inventory count: {e}");
+        response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+        return Err(ServerFnError::new("Failed to store count!"));
+    }
+
+    info!("Counted the stock of {} articles", counted.len());
+
+    Ok(())
+}
+
+#[component]
+pub fn Inventory() -> impl IntoView {
+    let restock_action = ServerAction::<RestockArticle>::new();
+    let count_action = ServerAction::<CountInventory>::new();
+
+    let inventory_resource = Resource::new(
+        move || (restock_action.version().get(), count_action.version().get()),
+        |_| get_inventory(),
+    );
+
+    let input_class = "text-black rounded p-1";
+    let button_class = "text-white hover:bg-pink-700 bg-emerald-700 rounded-full p-2";
+
+    view! {
+        <div class="flex flex-col items-center gap-3 pt-10 text-white">
+            <p class="text-[1.25em]">"Inventory"</p>
+            <Suspense fallback=move || view! { <p>"Loading inventory..."</p> }>
+                {move || {
+                    inventory_resource
+                        .get()
+                        .map(|overview| match overview {
+                            Err(e) => error_view(Some(Err(e))),
+                            Ok(overview) => {
+                                let restock_articles = overview.articles.clone();
+                                view! {
+                                    <ActionForm action=count_action>
+                                        <div class="flex flex-col items-center gap-2">
+                                            <table>
+                                                <tr>
+                                                    <th class="px-3">"Article"</th>
+                                                    <th class="px-3">"Stock"</th>
+                                                    <th class="px-3">"Counted"</th>
+                                                </tr>
+                                                {overview
+                                                    .articles
+                                                    .into_iter()
+                                                    .enumerate()
+                                                    .map(|(index, article)| {
+                                                        let is_negative = article
+                                                            .stock
+                                                            .is_some_and(|stock| stock < 0);
+                                                        view! {
+                                                            <tr>
+                                                                <td class="px-3">{article.name}</td>
+                                                                <td class="px-3 text-right" class:text-red-400=is_negative>
+                                                                    {match article.stock {
+                                                                        Some(stock) => stock.to_string(),
+                                                                        None => "not tracked".to_string(),
+                                                                    }}
+                                                                </td>
+                                                                <td class="px-3">
+                                                                    <input
+                                                                        type="hidden"
+                                                                        name=format!("counts[{index}][article_id]")
+                                                                        value=article.id
+                                                                    />
+                                                                    <input
+                                                                        class=input_class
+                                                                        size=4
+                                                                        name=format!("counts[{index}][counted]")
+                                                                    />
+                                                                </td>
+                                                            </tr>
+                                                        }
+                                                    })
+                                                    .collect_view()}
+                                            </table>
+                                            <input class=button_class type="submit" value="Store count" />
+                                        </div>
+                                    </ActionForm>
+
+                                    <p class="pt-5">"Restock"</p>
+                                    <ActionForm action=restock_action>
+                                        <div class="flex flex-wrap justify-center items-center gap-2">
+                                            <select class=input_class name="article_id">
+                                                {restock_articles
+                                                    .into_iter()
+                                                    .map(|article| {
+                                                        view! { <option value=article.id>{article.name}</option> }
+                                                    })
+                                                    .collect_view()}
+                                            </select>
+                                            <input class=input_class size=4 name="quantity" placeholder="Quantity" />
+                                            <input class=input_class type="date" name="moved_on" />
+                                            <input
+                                                class=input_class
+                                                size=8
+                                                name="purchase_price"
+                                                placeholder="Price per unit"
+                                            />
+                                            <input class=button_class type="submit" value="Restock" />
+                                        </div>
+                                    </ActionForm>
+
+                                    <p class="pt-5">"Movements"</p>
+                                    <table>
+                                        <tr>
+                                            <th class="px-3">"Date"</th>
+                                            <th class="px-3">"Article"</th>
+                                            <th class="px-3">"Kind"</th>
+                                            <th class="px-3">"Quantity"</th>
+                                            <th class="px-3">"Price per unit"</th>
+                                        </tr>
+                                        {overview
+                                            .movements
+                                            .into_iter()
+                                            .map(|movement| {
+                                                view! {
+                                                    <tr>
+                                                        <td class="px-3">
+                                                            {movement.moved_on.format("%d.%m.%Y").to_string()}
+                                                        </td>
+                                                        <td class="px-3">{movement.article_name}</td>
+                                                        <td class="px-3">{movement.kind.as_str()}</td>
+                                                        <td class="px-3 text-right">{format!("{:+}", movement.quantity)}</td>
+                                                        <td class="px-3">
+                                                            {movement.purchase_price.map(|price| price.format_eur())}
+                                                        </td>
+                                                    </tr>
+                                                }
+                                            })
+                                            .collect_view()}
+                                    </table>
+                                }
+                                    .into_any()
+                            }
+                        })
+                }}
+            </Suspense>
+            {move || error_view(count_action.value().get())}
+            {move || error_view(restock_action.value().get())}
+        </div>
+    }
+}
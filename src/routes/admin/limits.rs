@@ -0,0 +1,195 @@
+use leptos::prelude::*;
+
+use crate::{models::User, routes::admin::recurring::error_view};
+
+#[cfg(feature = "ssr")]
+use {
+    crate::models::{Money, MoneyParseError},
+    tracing::{debug, error},
+};
+
+/// An empty limit means that the global limit from the settings applies.
+#[cfg(feature = "ssr")]
+fn parse_limit(value: String) -> Result<Option<Money>, MoneyParseError> {
+    match value.trim().is_empty() {
+        true => Ok(None),
+        false => Money::try_from(value.trim().to_string()).map(Some),
+    }
+}
+
+/// The users that override at least one of the global account limits.
+#[server]
+pub async fn get_user_limits() -> Result<Vec<User>, ServerFnError> {
+    use crate::backend::ServerState;
+    let state: ServerState = expect_context();
+    use axum::http::StatusCode;
+    use leptos_axum::ResponseOptions;
+
+    let response_opts: ResponseOptions = expect_context();
+
+    let db = state.db.lock().await;
+
+    match User::get_all(&db).await {
+        Ok(value) => Ok(value
+            .into_iter()
+            .filter(|user| user.lower_limit.is_some() || user.upper_limit.is_some())
+            .collect()),
+        Err(e) => {
+            error!("Failed to fetch users: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            Err(ServerFnError::new("Failed to fetch users!"))
+        }
+    }
+}
+
+#[server]
+pub async fn set_user_limits(
+    nickname: String,
+    lower_limit: String,
+    upper_limit: String,
+) -> Result<(), ServerFnError> {
+    use crate::backend::ServerState;
+    let state: ServerState = expect_context();
+    use axum::http::StatusCode;
+    use leptos_axum::ResponseOptions;
+
+    let response_opts: ResponseOptions = expect_context();
+
+    let lower_limit = match parse_limit(lower_limit) {
+        Ok(value) => value,
+        Err(e) => {
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(ServerFnError::new(format!(
+                "Failed to parse lower limit: {e}"
+            )));
+        }
+    };
+
+    let upper_limit = match parse_limit(upper_limit) {
+        Ok(value) => value,
+        Err(e) => {
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(ServerFnError::new(format!(
+                "Failed to parse upper limit: {e}"
+            )));
+        }
+    };
+
+    if let (Some(lower), Some(upper)) = (lower_limit, upper_limit) {
+        if lower > upper {
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(ServerFnError::new(
+                "The lower limit may not be above the upper limit!",
+            ));
+        }
+    }
+
+    let db = state.db.lock().await;
+
+    let mut conn = match db.get_conn().await {
+        Ok(value) => value,
+        Err(e) => {
+            error!("Failed to get db handle: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(ServerFnError::new("Failed to get database!"));
+        }
+    };
+
+    let mut user = match User::get_by_nick(&mut *conn, &nickname.trim().to_string()).await {
+        Ok(Some(value)) => value,
+        Ok(None) => {
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(ServerFnError::new(format!(
+                "User '{nickname}' does not exist!"
+            )));
+        }
+        Err(e) => {
+            error!("Failed to fetch user: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(ServerFnError::new("Failed to fetch user!"));
+        }
+    };
+
+    debug!("Setting account limits of user '{}'", user.id);
+
+    match user.set_limits(&mut *conn, lower_limit, upper_limit).await {
+        Ok(_) => Ok(()),
+        Err(e) => {
+            error!("Failed to set new account limits: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            Err(ServerFnError::new("Failed to set new account limits!"))
+        }
+    }
+}
+
+#[component]
+pub fn UserLimits() -> impl IntoView {
+    let set_action = ServerAction::<SetUserLimits>::new();
+
+    let users_resource = Resource::new(move || set_action.version().get(), |_| get_user_limits());
+
+    let input_class = "text-black rounded p-1";
+    let button_class = "text-white hover:bg-pink-700 bg-emerald-700 rounded-full p-2";
+
+    view! {
+        <div class="flex flex-col items-center gap-3 pt-10 text-white">
+            <p class="text-[1.25em]">"Account limits"</p>
+            <Suspense fallback=move || view! { <p>"Loading account limits..."</p> }>
+                {move || {
+                    users_resource
+                        .get()
+                        .map(|users| match users {
+                            Err(e) => error_view(Some(Err(e))),
+                            Ok(users) if users.is_empty() => {
+                                view! { <p>"All users have the default limits"</p> }.into_any()
+                            }
+                            Ok(users) => {
+                                view! {
+                                    <table>
+                                        <tr>
+                                            <th class="px-3">"User"</th>
+                                            <th class="px-3">"Lower limit"</th>
+                                            <th class="px-3">"Upper limit"</th>
+                                        </tr>
+                                        {users
+                                            .into_iter()
+                                            .map(|user| {
+                                                view! {
+                                                    <tr>
+                                                        <td class="px-3">{user.nickname}</td>
+                                                        <td class="px-3">
+                                                            {user
+                                                                .lower_limit
+                                                                .map(|limit| limit.format_eur())
+                                                                .unwrap_or("Default".to_string())}
+                                                        </td>
+                                                        <td class="px-3">
+                                                            {user
+                                                                .upper_limit
+                                                                .map(|limit| limit.format_eur())
+                                                                .unwrap_or("Default".to_string())}
+                                                        </td>
+                                                    </tr>
+                                                }
+                                            })
+                                            .collect_view()}
+                                    </table>
+                                }
+                                    .into_any()
+                            }
+                        })
+                }}
+            </Suspense>
+            <ActionForm action=set_action>
+                <div class="flex flex-row items-center gap-3">
+                    <input class=input_class name="nickname" placeholder="Nickname" />
+                    <input class=input_class name="lower_limit" placeholder="Lower limit" />
+                    <input class=input_class name="upper_limit" placeholder="Upper limit" />
+                    <input class=button_class type="submit" value="Set limits" />
+                </div>
+            </ActionForm>
+            <p class="text-gray-400">"Empty limits fall back to the defaults from the settings"</p>
+            {move || error_view(set_action.value().get())}
+        </div>
+    }
+}
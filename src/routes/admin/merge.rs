@@ -0,0 +1,261 @@
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    models::{Money, User, UserId},
+    routes::admin::recurring::error_view,
+};
+
+#[cfg(feature = "ssr")]
+use tracing::{debug, error};
+
+/// The two accounts of a merge as they are shown for the confirmation.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct MergePreview {
+    pub user: User,
+    pub other: User,
+}
+
+#[cfg(feature = "ssr")]
+fn check_merge(user: &User, other: &User) -> Result<(), &'static str> {
+    if other.id == user.id {
+        return Err("A user can not be merged with itself!");
+    }
+
+    if user.is_system_user || other.is_system_user {
+        return Err("System users can not be merged!");
+    }
+
+    Ok(())
+}
+
+#[server]
+pub async fn preview_merge(
+    nickname: String,
+    other_nickname: String,
+) -> Result<MergePreview, ServerFnError> {
+    use crate::backend::ServerState;
+    let state: ServerState = expect_context();
+    use axum::http::StatusCode;
+    use leptos_axum::ResponseOptions;
+
+    let response_opts: ResponseOptions = expect_context();
+
+    let db = state.db.lock().await;
+
+    let mut conn = match db.get_conn().await {
+        Ok(value) => value,
+        Err(e) => {
+            error!("Failed to get db handle: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(ServerFnError::new("Failed to get database!"));
+        }
+    };
+
+    let mut users = Vec::new();
+
+    for nickname in [nickname, other_nickname] {
+        match User::get_by_nick(&mut *conn, &nickname.trim().to_string()).await {
+            Ok(Some(value)) => users.push(value),
+            Ok(None) => {
+                response_opts.set_status(StatusCode::BAD_REQUEST);
+                return Err(ServerFnError::new(format!(
+                    "User '{nickname}' does not exist!"
+                )));
+            }
+            Err(e) => {
+                error!("Failed to fetch user: {e}");
+                response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+                return Err(ServerFnError::new("Failed to fetch user!"));
+            }
+        }
+    }
+
+    let other = users.pop().unwrap();
+    let user = users.pop().unwrap();
+
+    if let Err(e) = check_merge(&user, &other) {
+        response_opts.set_status(StatusCode::BAD_REQUEST);
+        return Err(ServerFnError::new(e));
+    }
+
+    Ok(MergePreview { user, other })
+}
+
+/// Merges `other_id` into `id`, the balances have to match the ones shown in
+/// the confirmation so nothing is merged that has not been seen.
+#[server]
+pub async fn merge_users(
+    id: UserId,
+    other_id: UserId,
+    money: i64,
+    other_money: i64,
+) -> Result<(), ServerFnError> {
+    use crate::backend::ServerState;
+    let state: ServerState = expect_context();
+    use axum::http::StatusCode;
+    use leptos_axum::ResponseOptions;
+
+    let response_opts: ResponseOptions = expect_context();
+
+    let db = state.db.lock().await;
+
+    let mut db_trans = match db.get_conn_transaction().await {
+        Ok(value) => value,
+        Err(e) => {
+            error!("Failed to get db transaction: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(ServerFnError::new("Failed to get database!"));
+        }
+    };
+
+    let mut users = Vec::new();
+
+    for id in [id, other_id] {
+        match User::get(&mut *db_trans, id).await {
+            Ok(Some(value)) => users.push(value),
+            Ok(None) => {
+                response_opts.set_status(StatusCode::BAD_REQUEST);
+                return Err(ServerFnError::new("No such user exists!"));
+            }
+            Err(e) => {
+                error!("Failed to fetch user '{id}': {e}");
+                response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+                return Err(ServerFnError::new("Failed to fetch user!"));
+            }
+        }
+    }
+
+    let other = users.pop().unwrap();
+    let mut user = users.pop().unwrap();
+
+    if let Err(e) = check_merge(&user, &other) {
+        response_opts.set_status(StatusCode::BAD_REQUEST);
+        return Err(ServerFnError::new(e));
+    }
+
+    if user.money.value != money || other.money.value != other_money {
+        response_opts.set_status(StatusCode::CONFLICT);
+        return Err(ServerFnError::new(
+            "The balances changed in the meantime, please review the merge again!",
+        ));
+    }
+
+    debug!("Merging user '{}' into user '{}'", other.id, user.id);
+
+    if let Err(e) = user.merge(&mut *db_trans, other).await {
+        error!("Failed to merge users: {e}");
+        response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+        return Err(ServerFnError::new("Failed to merge users!"));
+    }
+
+    match db_trans.commit().await {
+        Ok(_) => {}
+        Err(e) => {
+            error!("Failed to commit merge: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(ServerFnError::new("Failed to commit merge!"));
+        }
+    }
+
+    Ok(())
+}
+
+#[component]
+pub fn UserMerge() -> impl IntoView {
+    let merge_action = ServerAction::<MergeUsers>::new();
+
+    let nickname = RwSignal::new(String::new());
+    let other_nickname = RwSignal::new(String::new());
+    let review_version = RwSignal::new(0_u32);
+
+    let review_resource = Resource::new(
+        move || review_version.get(),
+        move |version| {
+            let nickname = nickname.get_untracked();
+            let other_nickname = other_nickname.get_untracked();
+            async move {
+                match version {
+                    0 => Ok(None),
+                    _ => preview_merge(nickname, other_nickname).await.map(Some),
+                }
+            }
+        },
+    );
+
+    // a finished merge invalidates the preview
+    Effect::new(move || {
+        if let Some(Ok(())) = merge_action.value().get() {
+            nickname.set(String::new());
+            other_nickname.set(String::new());
+            review_version.set(0);
+        }
+    });
+
+    let input_class = "text-black rounded p-1";
+    let button_class = "text-white hover:bg-pink-700 bg-emerald-700 rounded-full p-2";
+
+    view! {
+        <div class="flex flex-col items-center gap-3 pt-10 text-white">
+            <p class="text-[1.25em]">"Merge users"</p>
+            <div class="flex flex-row items-center gap-3">
+                <input class=input_class placeholder="Kept user" bind:value=nickname />
+                <input class=input_class placeholder="Merged user" bind:value=other_nickname />
+                <button
+                    class=button_class
+                    on:click=move |_| review_version.update(|value| *value += 1)
+                >
+                    "Review"
+                </button>
+            </div>
+            <Suspense fallback=move || view! { <p>"Loading users..."</p> }>
+                {move || {
+                    review_resource
+                        .get()
+                        .map(|preview| match preview {
+                            Err(e) => error_view(Some(Err(e))),
+                            Ok(None) => ().into_any(),
+                            Ok(Some(MergePreview { user, other })) => {
+                                let total = user.money.value + other.money.value;
+                                view! {
+                                    <ActionForm action=merge_action>
+                                        <div class="flex flex-col items-center gap-2">
+                                            <p>
+                                                "Merge '"{other.nickname.clone()}"' with a balance of "
+                                                {other.money.format_eur()}" into '"
+                                                {user.nickname.clone()}"' with a balance of "
+                                                {user.money.format_eur()}"?"
+                                            </p>
+                                            <p>
+                                                "'"{user.nickname.clone()}"' will have a balance of "
+                                                {Money::format_eur_value(total)}
+                                                " and '"{other.nickname.clone()}"' will be deleted."
+                                            </p>
+                                            <input type="hidden" name="id" value=user.id.0 />
+                                            <input type="hidden" name="other_id" value=other.id.0 />
+                                            <input type="hidden" name="money" value=user.money.value />
+                                            <input
+                                                type="hidden"
+                                                name="other_money"
+                                                value=other.money.value
+                                            />
+                                            <input
+                                                class="text-white hover:bg-pink-700 bg-red-400 rounded-full p-2"
+                                                type="submit"
+                                                value="Merge users"
+                                            />
+                                        </div>
+                                    </ActionForm>
+                                }
+                                    .into_any()
+                            }
+                        })
+                }}
+            </Suspense>
+            {move || match merge_action.value().get() {
+                Some(Ok(())) => view! { <p>"Merged the users"</p> }.into_any(),
+                result => error_view(result),
+            }}
+        </div>
+    }
+}
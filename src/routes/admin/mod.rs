@@ -0,0 +1,17 @@
+pub use bank_import::*;
+pub use cash_box::*;
+pub use inventory::*;
+pub use limits::*;
+pub use merge::*;
+pub use reconcile::*;
+pub use recurring::*;
+pub use show::*;
+
+mod bank_import;
+mod cash_box;
+mod inventory;
+mod limits;
+mod merge;
+mod reconcile;
+mod recurring;
+mod show;
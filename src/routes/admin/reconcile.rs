@@ -0,0 +1,191 @@
+use leptos::prelude::*;
+
+use crate::models::BalanceDrift;
+
+#[cfg(feature = "ssr")]
+use tracing::{error, info};
+
+#[server]
+pub async fn get_balance_drifts() -> Result<Vec<BalanceDrift>, ServerFnError> {
+    use crate::backend::ServerState;
+    let state: ServerState = expect_context();
+    use axum::http::StatusCode;
+    use leptos_axum::ResponseOptions;
+
+    let response_opts: ResponseOptions = expect_context();
+
+    let db = state.db.lock().await;
+
+    let mut conn = match db.get_conn().await {
+        Ok(value) => value,
+        Err(e) => {
+            error!("Failed to get db handle: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(ServerFnError::new("Failed to get database!"));
+        }
+    };
+
+    match BalanceDrift::get_all(&mut *conn).await {
+        Ok(value) => Ok(value),
+        Err(e) => {
+            error!("Failed to compute balances: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            Err(ServerFnError::new("Failed to compute balances!"))
+        }
+    }
+}
+
+/// Books corrections for all drifting users, system users are skipped.
+#[server]
+pub async fn fix_balance_drifts() -> Result<usize, ServerFnError> {
+    use crate::backend::ServerState;
+    let state: ServerState = expect_context();
+    use axum::http::StatusCode;
+    use leptos_axum::ResponseOptions;
+
+    let response_opts: ResponseOptions = expect_context();
+
+    let db = state.db.lock().await;
+
+    let mut db_trans = match db.get_conn_transaction().await {
+        Ok(value) => value,
+        Err(e) => {
+            error!("Failed to get db transaction: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(ServerFnError::new("Failed to get database!"));
+        }
+    };
+
+    let drifts = match BalanceDrift::get_all(&mut *db_trans).await {
+        Ok(value) => value,
+        Err(e) => {
+            error!("Failed to compute balances: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(ServerFnError::new("Failed to compute balances!"));
+        }
+    };
+
+    let mut fixed = 0;
+
+    for drift in drifts.iter().filter(|drift| !drift.is_system_user) {
+        match drift.fix(&mut *db_trans).await {
+            Ok(id) => {
+                info!(
+                    "Corrected balance of user '{}' with transaction {}",
+                    drift.user_id, id
+                );
+                fixed += 1;
+            }
+            Err(e) => {
+                error!("Failed to correct balance of user '{}': {e}", drift.user_id);
+                response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+                return Err(ServerFnError::new("Failed to correct balances!"));
+            }
+        }
+    }
+
+    match db_trans.commit().await {
+        Ok(_) => {}
+        Err(e) => {
+            error!("Failed to commit corrections: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(ServerFnError::new("Failed to commit corrections!"));
+        }
+    }
+
+    Ok(fixed)
+}
+
+#[component]
+pub fn Reconcile() -> impl IntoView {
+    let fix_action = ServerAction::<FixBalanceDrifts>::new();
+
+    let drifts_resource =
+        Resource::new(move || fix_action.version().get(), |_| get_balance_drifts());
+
+    view! {
+        <div class="flex flex-col items-center gap-3">
+            <p class="text-white text-[1.25em]">"Balance reconciliation"</p>
+            <Suspense fallback=move || {
+                view! { <p class="text-white text-center">"Computing balances..."</p> }
+            }>
+                {move || {
+                    drifts_resource
+                        .get()
+                        .map(|drifts| {
+                            match drifts {
+                                Err(e) => {
+                                    let msg = match e {
+                                        ServerFnError::ServerError(msg) => msg,
+                                        _ => e.to_string(),
+                                    };
+                                    view! {
+                                        <p class="p-3 bg-red-400 text-white text-center">{msg}</p>
+                                    }
+                                        .into_any()
+                                }
+                                Ok(drifts) if drifts.is_empty() => {
+                                    view! {
+                                        <p class="text-white text-center">
+                                            "All balances match the transactions"
+                                        </p>
+                                    }
+                                        .into_any()
+                                }
+                                Ok(drifts) => {
+                                    view! {
+                                        <table class="text-white">
+                                            <tr>
+                                                <th class="px-3">"User"</th>
+                                                <th class="px-3">"Stored"</th>
+                                                <th class="px-3">"Ledger"</th>
+                                                <th class="px-3">"Drift"</th>
+                                            </tr>
+                                            {drifts
+                                                .into_iter()
+                                                .map(|drift| {
+                                                    view! {
+                                                        <tr>
+                                                            <td class="px-3">
+                                                                {drift.nickname.clone()}
+                                                                {drift.is_system_user.then_some(" (system)")}
+                                                            </td>
+                                                            <td class="px-3">{drift.stored.format_eur()}</td>
+                                                            <td class="px-3">{drift.ledger.format_eur()}</td>
+                                                            <td class="px-3">{drift.drift().format_eur_diff()}</td>
+                                                        </tr>
+                                                    }
+                                                })
+                                                .collect_view()}
+                                        </table>
+                                        <ActionForm action=fix_action>
+                                            <input
+                                                class="text-white hover:bg-pink-700 bg-emerald-700 rounded-full p-2"
+                                                type="submit"
+                                                value="Book corrections"
+                                            />
+                                        </ActionForm>
+                                    }
+                                        .into_any()
+                                }
+                            }
+                        })
+                }}
+            </Suspense>
+            {move || match fix_action.value().get() {
+                Some(Ok(fixed)) => {
+                    view! { <p class="text-white text-center">"Corrected "{fixed}" balances"</p> }
+                        .into_any()
+                }
+                Some(Err(e)) => {
+                    let msg = match e {
+                        ServerFnError::ServerError(msg) => msg,
+                        _ => e.to_string(),
+                    };
+                    view! { <p class="p-3 bg-red-400 text-white text-center">{msg}</p> }.into_any()
+                }
+                None => ().into_any(),
+            }}
+        </div>
+    }
+}
@@ -0,0 +1,616 @@
+use leptos::prelude::*;
+
+use crate::models::{ChargeInterval, RecurringCharge, UserId};
+
+#[cfg(feature = "ssr")]
+use {
+    crate::backend::db::DBUSER_SNACKBAR_ID,
+    crate::models::{Money, RecurringChargeDB, User, UserDB},
+    chrono::{DateTime, Local, NaiveDate, Utc},
+    tracing::{error, warn},
+};
+
+#[cfg(feature = "ssr")]
+fn parse_start(value: &str) -> Option<DateTime<Utc>> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()?
+        .and_hms_opt(0, 0, 0)?
+        .and_local_timezone(Local)
+        .earliest()
+        .map(|start| start.with_timezone(&Utc))
+}
+
+#[server]
+pub async fn get_recurring_charges() -> Result<Vec<RecurringCharge>, ServerFnError> {
+    use crate::backend::ServerState;
+    let state: ServerState = expect_context();
+    use axum::http::StatusCode;
+    use leptos_axum::ResponseOptions;
+
+    let response_opts: ResponseOptions = expect_context();
+
+    let db = state.db.lock().await;
+
+    let mut conn = match db.get_conn().await {
+        Ok(value) => value,
+        Err(e) => {
+            error!("Failed to get db handle: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(ServerFnError::new("Failed to get database!"));
+        }
+    };
+
+    match RecurringCharge::get_all(&mut *conn).await {
+        Ok(value) => Ok(value),
+        Err(e) => {
+            error!("Failed to fetch recurring charges: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            Err(ServerFnError::new("Failed to fetch recurring charges!"))
+        }
+    }
+}
+
+/// The system accounts recurring charges can be booked to.
+#[server]
+pub async fn get_system_accounts() -> Result<Vec<(UserId, String)>, ServerFnError> {
+    use crate::backend::ServerState;
+    let state: ServerState = expect_context();
+    use axum::http::StatusCode;
+    use leptos_axum::ResponseOptions;
+
+    let response_opts: ResponseOptions = expect_context();
+
+    let db = state.db.lock().await;
+
+    let mut conn = match db.get_conn().await {
+        Ok(value) => value,
+        Err(e) => {
+            error!("Failed to get db handle: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(ServerFnError::new("Failed to get database!"));
+        }
+    };
+
+    match UserDB::get_all_system(&mut *conn).await {
+        Ok(value) => Ok(value
+            .into_iter()
+            .filter(|user| user.id != DBUSER_SNACKBAR_ID.0)
+            .map(|user| (UserId(user.id), user.nickname))
+            .collect()),
+        Err(e) => {
+            error!("Failed to fetch system accounts: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            Err(ServerFnError::new("Failed to fetch system accounts!"))
+        }
+    }
+}
+
+#[server]
+pub async fn create_system_account(nickname: String) -> Result<(), ServerFnError> {
+    use crate::backend::ServerState;
+    let state: ServerState = expect_context();
+    use axum::http::StatusCode;
+    use leptos_axum::ResponseOptions;
+
+    let response_opts: ResponseOptions = expect_context();
+
+    let nickname = nickname.trim().to_string();
+
+    if nickname.is_empty() {
+        response_opts.set_status(StatusCode::BAD_REQUEST);
+        return Err(ServerFnError::new("The name may not be empty!"));
+    }
+
+    let db = state.db.lock().await;
+
+    match User::create_system(&db, nickname).await {
+        Ok(_) => Ok(()),
+        Err(e) => {
+            warn!("Failed to create system account: {e}");
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            Err(ServerFnError::new(
+                "Failed to create system account, the name might already be taken!",
+            ))
+        }
+    }
+}
+
+#[server]
+pub async fn create_recurring_charge(
+    description: String,
+    amount: String,
+    receiver: i64,
+    interval: String,
+    start: String,
+    nicknames: String,
+) -> Result<(), ServerFnError> {
+    use crate::backend::ServerState;
+    let state: ServerState = expect_context();
+    use axum::http::StatusCode;
+    use leptos_axum::ResponseOptions;
+
+    let response_opts: ResponseOptions = expect_context();
+
+    let description = description.trim().to_string();
+
+    if description.is_empty() {
+        response_opts.set_status(StatusCode::BAD_REQUEST);
+        return Err(ServerFnError::new("The description may not be empty!"));
+    }
+
+    let money: Money = match amount.clone().try_into() {
+        Ok(value) => value,
+        Err(e) => {
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(ServerFnError::new(format!(
+                "Failed to convert '{amount}' to internal representation: {e}"
+            )));
+        }
+    };
+
+    if money.value <= 0 {
+        response_opts.set_status(StatusCode::BAD_REQUEST);
+        return Err(ServerFnError::new("The amount must be > 0!"));
+    }
+
+    let interval = match ChargeInterval::parse(&interval) {
+        Some(value) => value,
+        None => {
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(ServerFnError::new(format!(
+                "Invalid interval '{interval}'!"
+            )));
+        }
+    };
+
+    let start = match parse_start(&start) {
+        Some(value) => value,
+        None => {
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(ServerFnError::new(format!("Invalid start date '{start}'!")));
+        }
+    };
+
+    let db = state.db.lock().await;
+
+    let mut db_trans = match db.get_conn_transaction().await {
+        Ok(value) => value,
+        Err(e) => {
+            error!("Failed to get db transaction: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(ServerFnError::new("Failed to get database!"));
+        }
+    };
+
+    match UserDB::get(&mut *db_trans, receiver).await {
+        Ok(Some(user)) if user.is_system_user && user.id != DBUSER_SNACKBAR_ID.0 => {}
+        Ok(_) => {
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(ServerFnError::new("Invalid receiving account!"));
+        }
+        Err(e) => {
+            error!("Failed to fetch receiving account: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(ServerFnError::new("Failed to fetch receiving account!"));
+        }
+    }
+
+    let mut user_ids = Vec::new();
+
+    for nickname in nicknames
+        .split(',')
+        .map(str::trim)
+        .filter(|nickname| !nickname.is_empty())
+    {
+        match User::get_by_nick(&mut *db_trans, &nickname.to_string()).await {
+            Ok(Some(user)) => user_ids.push(user.id),
+            Ok(None) => {
+                response_opts.set_status(StatusCode::BAD_REQUEST);
+                return Err(ServerFnError::new(format!(
+                    "User '{nickname}' does not exist!"
+                )));
+            }
+            Err(e) => {
+                error!("Failed to fetch user: {e}");
+                response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+                return Err(ServerFnError::new("Failed to fetch user!"));
+            }
+        }
+    }
+
+    let id = match RecurringChargeDB::create(
+        &mut *db_trans,
+        description,
+        money,
+        UserId(receiver),
+        interval,
+        start,
+    )
+    .await
+    {
+        Ok(value) => value,
+        Err(e) => {
+            error!("Failed to create recurring charge: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(ServerFnError::new("Failed to create recurring charge!"));
+        }
+    };
+
+    for user_id in user_ids {
+        // the initial users are charged from the start on
+        if let Err(e) = RecurringChargeDB::add_user(&mut *db_trans, id, user_id, start).await {
+            warn!("Failed to add user to recurring charge: {e}");
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(ServerFnError::new("Users may only be listed once!"));
+        }
+    }
+
+    match db_trans.commit().await {
+        Ok(_) => {}
+        Err(e) => {
+            error!("Failed to commit recurring charge: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(ServerFnError::new("Failed to create recurring charge!"));
+        }
+    }
+
+    Ok(())
+}
+
+#[server]
+pub async fn set_recurring_charge_active(id: i64, is_active: bool) -> Result<(), ServerFnError> {
+    use crate::backend::ServerState;
+    let state: ServerState = expect_context();
+    use axum::http::StatusCode;
+    use leptos_axum::ResponseOptions;
+
+    let response_opts: ResponseOptions = expect_context();
+
+    let db = state.db.lock().await;
+
+    let mut conn = match db.get_conn().await {
+        Ok(value) => value,
+        Err(e) => {
+            error!("Failed to get db handle: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(ServerFnError::new("Failed to get database!"));
+        }
+    };
+
+    match RecurringChargeDB::set_active(&mut *conn, id, is_active).await {
+        Ok(_) => Ok(()),
+        Err(e) => {
+            error!("Failed to update recurring charge: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            Err(ServerFnError::new("Failed to update recurring charge!"))
+        }
+    }
+}
+
+/// The user is charged from the next period on.
+#[server]
+pub async fn add_recurring_charge_user(id: i64, nickname: String) -> Result<(), ServerFnError> {
+    use crate::backend::ServerState;
+    let state: ServerState = expect_context();
+    use axum::http::StatusCode;
+    use leptos_axum::ResponseOptions;
+
+    let response_opts: ResponseOptions = expect_context();
+
+    let db = state.db.lock().await;
+
+    let mut conn = match db.get_conn().await {
+        Ok(value) => value,
+        Err(e) => {
+            error!("Failed to get db handle: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(ServerFnError::new("Failed to get database!"));
+        }
+    };
+
+    let user = match User::get_by_nick(&mut *conn, &nickname.trim().to_string()).await {
+        Ok(Some(value)) => value,
+        Ok(None) => {
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(ServerFnError::new(format!(
+                "User '{nickname}' does not exist!"
+            )));
+        }
+        Err(e) => {
+            error!("Failed to fetch user: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(ServerFnError::new("Failed to fetch user!"));
+        }
+    };
+
+    match RecurringChargeDB::add_user(&mut *conn, id, user.id, Utc::now()).await {
+        Ok(_) => Ok(()),
+        Err(e) => {
+            warn!("Failed to add user to recurring charge: {e}");
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            Err(ServerFnError::new("The user is already charged!"))
+        }
+    }
+}
+
+#[server]
+pub async fn remove_recurring_charge_user(id: i64, user_id: UserId) -> Result<(), ServerFnError> {
+    use crate::backend::ServerState;
+    let state: ServerState = expect_context();
+    use axum::http::StatusCode;
+    use leptos_axum::ResponseOptions;
+
+    let response_opts: ResponseOptions = expect_context();
+
+    let db = state.db.lock().await;
+
+    let mut conn = match db.get_conn().await {
+        Ok(value) => value,
+        Err(e) => {
+            error!("Failed to get db handle: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(ServerFnError::new("Failed to get database!"));
+        }
+    };
+
+    match RecurringChargeDB::remove_user(&mut *conn, id, user_id).await {
+        Ok(_) => Ok(()),
+        Err(e) => {
+            error!("Failed to remove user from recurring charge: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            Err(ServerFnError::new("Failed to remove user!"))
+        }
+    }
+}
+
+/// Clears a failed booking, the charge is booked for the user on the next run.
+#[server]
+pub async fn retry_recurring_charge_user(id: i64, user_id: UserId) -> Result<(), ServerFnError> {
+    use crate::backend::ServerState;
+    let state: ServerState = expect_context();
+    use axum::http::StatusCode;
+    use leptos_axum::ResponseOptions;
+
+    let response_opts: ResponseOptions = expect_context();
+
+    let db = state.db.lock().await;
+
+    let mut conn = match db.get_conn().await {
+        Ok(value) => value,
+        Err(e) => {
+            error!("Failed to get db handle: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(ServerFnError::new("Failed to get database!"));
+        }
+    };
+
+    match RecurringChargeDB::clear_failure(&mut *conn, id, user_id).await {
+        Ok(_) => Ok(()),
+        Err(e) => {
+            error!("Failed to clear failure of recurring charge: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            Err(ServerFnError::new("Failed to retry the charge!"))
+        }
+    }
+}
+
+pub(super) fn error_view(result: Option<Result<(), ServerFnError>>) -> AnyView {
+    match result {
+        Some(Err(e)) => {
+            let msg = match e {
+                ServerFnError::ServerError(msg) => msg,
+                _ => e.to_string(),
+            };
+            view! { <p class="p-3 bg-red-400 text-white text-center">{msg}</p> }.into_any()
+        }
+        _ => ().into_any(),
+    }
+}
+
+#[component]
+pub fn RecurringCharges() -> impl IntoView {
+    let create_account_action = ServerAction::<CreateSystemAccount>::new();
+    let create_action = ServerAction::<CreateRecurringCharge>::new();
+    let active_action = ServerAction::<SetRecurringChargeActive>::new();
+    let add_user_action = ServerAction::<AddRecurringChargeUser>::new();
+    let remove_user_action = ServerAction::<RemoveRecurringChargeUser>::new();
+    let retry_action = ServerAction::<RetryRecurringChargeUser>::new();
+
+    let accounts_resource = Resource::new(
+        move || create_account_action.version().get(),
+        |_| get_system_accounts(),
+    );
+
+    let charges_resource = Resource::new(
+        move || {
+            (
+                create_action.version().get(),
+                active_action.version().get(),
+                add_user_action.version().get(),
+                remove_user_action.version().get(),
+                retry_action.version().get(),
+            )
+        },
+        |_| get_recurring_charges(),
+    );
+
+    let input_class = "text-black rounded p-1";
+    let button_class = "text-white hover:bg-pink-700 bg-emerald-700 rounded-full p-2";
+
+    view! {
+        <div class="flex flex-col items-center gap-3 pt-10 text-white">
+            <p class="text-[1.25em]">"Recurring charges"</p>
+            <Suspense fallback=move || view! { <p>"Loading recurring charges..."</p> }>
+                {move || {
+                    charges_resource
+                        .get()
+                        .map(|charges| match charges {
+                            Err(e) => error_view(Some(Err(e))),
+                            Ok(charges) => {
+                                view! {
+                                    <table>
+                                        <tr>
+                                            <th class="px-3">"Description"</th>
+                                            <th class="px-3">"Amount"</th>
+                                            <th class="px-3">"Interval"</th>
+                                            <th class="px-3">"Start"</th>
+                                            <th class="px-3">"To"</th>
+                                            <th class="px-3">"Users"</th>
+                                            <th class="px-3"></th>
+                                        </tr>
+                                        {charges
+                                            .into_iter()
+                                            .map(|charge| {
+                                                let id = charge.id;
+                                                view! {
+                                                    <tr class="align-top">
+                                                        <td class="px-3">{charge.description}</td>
+                                                        <td class="px-3">{charge.money.format_eur()}</td>
+                                                        <td class="px-3">{charge.interval.as_str()}</td>
+                                                        <td class="px-3">
+                                                            {charge.start.format("%d.%m.%Y").to_string()}
+                                                        </td>
+                                                        <td class="px-3">{charge.receiver_name}</td>
+                                                        <td class="px-3">
+                                                            {charge
+                                                                .users
+                                                                .into_iter()
+                                                                .map(|user| {
+                                                                    view! {
+                                                                        <ActionForm action=remove_user_action>
+                                                                            <input type="hidden" name="id" value=id />
+                                                                            <input
+                                                                                type="hidden"
+                                                                                name="user_id"
+                                                                                value=user.user_id.0
+                                                                            />
+                                                                            {user.nickname}
+                                                                            " "
+                                                                            <input type="submit" value="✕" />
+                                                                        </ActionForm>
+                                                                        {user
+                                                                            .failure
+                                                                            .map(|failure| {
+                                                                                let failed_at = user
+                                                                                    .failed_at
+                                                                                    .map(|failed_at| {
+                                                                                        failed_at
+                                                                                            .with_timezone(&chrono::Local)
+                                                                                            .format("%d.%m.%Y %H:%M")
+                                                                                            .to_string()
+                                                                                    })
+                                                                                    .unwrap_or_default();
+                                                                                view! {
+                                                                                    <ActionForm action=retry_action>
+                                                                                        <input type="hidden" name="id" value=id />
+                                                                                        <input
+                                                                                            type="hidden"
+                                                                                            name="user_id"
+                                                                                            value=user.user_id.0
+                                                                                        />
+                                                                                        <span class="text-red-400">
+                                                                                            "Not charged since "{failed_at}": "
+                                                                                            {failure}
+                                                                                        </span>
+                                                                                        " "
+                                                                                        <input type="submit" value="Retry" />
+                                                                                    </ActionForm>
+                                                                                }
+                                                                            })}
+                                                                    }
+                                                                })
+                                                                .collect_view()}
+                                                            <ActionForm action=add_user_action>
+                                                                <input type="hidden" name="id" value=id />
+                                                                <input
+                                                                    class=input_class
+                                                                    size=8
+                                                                    name="nickname"
+                                                                    placeholder="Nickname"
+                                                                />
+                                                                <input type="submit" value="Add" />
+                                                            </ActionForm>
+                                                        </td>
+                                                        <td class="px-3">
+                                                            <ActionForm action=active_action>
+                                                                <input type="hidden" name="id" value=id />
+                                                                <input
+                                                                    type="hidden"
+                                                                    name="is_active"
+                                                                    value=(!charge.is_active).to_string()
+                                                                />
+                                                                <input
+                                                                    class=button_class
+                                                                    type="submit"
+                                                                    value=if charge.is_active {
+                                                                        "Stop"
+                                                                    } else {
+                                                                        "Resume"
+                                                                    }
+                                                                />
+                                                            </ActionForm>
+                                                        </td>
+                                                    </tr>
+                                                }
+                                            })
+                                            .collect_view()}
+                                    </table>
+                                }
+                                    .into_any()
+                            }
+                        })
+                }}
+            </Suspense>
+            {move || error_view(active_action.value().get())}
+            {move || error_view(add_user_action.value().get())}
+            {move || error_view(remove_user_action.value().get())}
+            {move || error_view(retry_action.value().get())}
+
+            <p class="pt-5">"New recurring charge"</p>
+            <ActionForm action=create_action>
+                <div class="flex flex-wrap justify-center items-center gap-2">
+                    <input class=input_class name="description" placeholder="Description" />
+                    <input class=input_class size=6 name="amount" placeholder="Amount €" />
+                    <select class=input_class name="interval">
+                        {ChargeInterval::ALL
+                            .into_iter()
+                            .map(|interval| {
+                                view! {
+                                    <option value=interval.as_str()>{interval.as_str()}</option>
+                                }
+                            })
+                            .collect_view()}
+                    </select>
+                    <input class=input_class type="date" name="start" />
+                    <Suspense>
+                        <select class=input_class name="receiver">
+                            {move || {
+                                accounts_resource
+                                    .get()
+                                    .and_then(Result::ok)
+                                    .unwrap_or_default()
+                                    .into_iter()
+                                    .map(|(id, nickname)| {
+                                        view! { <option value=id.0>{nickname}</option> }
+                                    })
+                                    .collect_view()
+                            }}
+                        </select>
+                    </Suspense>
+                    <input class=input_class name="nicknames" placeholder="Users, comma separated" />
+                    <input class=button_class type="submit" value="Create" />
+                </div>
+            </ActionForm>
+            {move || error_view(create_action.value().get())}
+
+            <p class="pt-5">"New system account"</p>
+            <ActionForm action=create_account_action>
+                <div class="flex justify-center items-center gap-2">
+                    <input class=input_class name="nickname" placeholder="Name" />
+                    <input class=button_class type="submit" value="Create" />
+                </div>
+            </ActionForm>
+            {move || error_view(create_account_action.value().get())}
+        </div>
+    }
+}
@@ -0,0 +1,21 @@
+use leptos::prelude::*;
+
+use crate::routes::admin::{
+    BankImport, CashBox, Inventory, Reconcile, RecurringCharges, UserLimits, UserMerge,
+};
+
+#[component]
+pub fn Show() -> impl IntoView {
+    view! {
+        <div class="p-5">
+            <p class="text-white text-center text-[1.5em] pb-5">"Administration"</p>
+            <Reconcile />
+            <RecurringCharges />
+            <CashBox />
+            <Inventory />
+            <BankImport />
+            <UserLimits />
+            <UserMerge />
+        </div>
+    }
+}
@@ -5,11 +5,22 @@ use leptos::{
 };
 #[cfg(feature = "ssr")]
 use {
-    crate::models::Money,
-    tracing::{debug, error},
+    crate::backend::{
+        remove_unused_sound_files, sound_file_name, uploaded_sounds_directory, MAX_SOUND_SIZE,
+    },
+    crate::models::{ArticleDB, Money},
+    chrono::Utc,
+    tracing::{debug, error, info},
 };
 
-use crate::models::{Article, Barcode, BarcodeDiff};
+use leptos::{
+    ev::SubmitEvent,
+    wasm_bindgen::JsCast,
+    web_sys::{FormData, HtmlFormElement},
+};
+use server_fn::codec::{MultipartData, MultipartFormData};
+
+use crate::models::{Article, ArticleSound, Barcode, BarcodeDiff, SoundDiff, TagDiff};
 
 #[server]
 pub async fn get_article(article_id: i64) -> Result<Article, ServerFnError> {
@@ -52,6 +63,8 @@ pub async fn update_article(
     name: String,
     cost: String,
     barcodes: Option<Vec<BarcodeDiff>>,
+    tags: Option<Vec<TagDiff>>,
+    sounds: Option<Vec<SoundDiff>>,
 ) -> Result<(), ServerFnError> {
     use crate::backend::ServerState;
     let state: ServerState = expect_context();
@@ -74,7 +87,7 @@ pub async fn update_article(
         }
     };
 
-    let db = &*state.db.lock().await;
+    let db = state.db.lock().await;
 
     let mut db_transaction = match db.get_conn_transaction().await {
         Ok(value) => value,
@@ -131,6 +144,22 @@ pub async fn update_article(
         }
     }
 
+    if let Some(tags) = tags {
+        if let Err(e) = article.set_tags(&mut *db_transaction, tags).await {
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            error!("Failed to set tags: {}", e);
+            return Err(ServerFnError::new(format!("Failed to set tags: {}", e)));
+        }
+    }
+
+    if let Some(sounds) = sounds {
+        if let Err(e) = article.set_sounds(&mut *db_transaction, sounds).await {
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            error!("Failed to set sounds: {}", e);
+            return Err(ServerFnError::new(format!("Failed to set sounds: {}", e)));
+        }
+    }
+
     match db_transaction.commit().await {
         Ok(_) => {}
         Err(e) => {
@@ -140,6 +169,314 @@ pub async fn update_article(
         }
     }
 
+    drop(db);
+
+    if let Err(e) = remove_unused_sound_files(&state).await {
+        error!("Failed to remove unused sounds: {}", e);
+    }
+
+    redirect("/articles");
+
+    Ok(())
+}
+
+/// All uploaded sounds, to choose from when assigning them to an article.
+#[server]
+pub async fn get_all_sounds() -> Result<Vec<ArticleSound>, ServerFnError> {
+    use crate::backend::ServerState;
+    let state: ServerState = expect_context();
+    use axum::http::StatusCode;
+    use leptos_axum::ResponseOptions;
+
+    let response_opts: ResponseOptions = expect_context();
+
+    let db = state.db.lock().await;
+
+    let mut conn = match db.get_conn().await {
+        Ok(value) => value,
+        Err(e) => {
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            error!("Failed to get database handle: {}", e);
+            return Err(ServerFnError::new("Failed to get database handle!"));
+        }
+    };
+
+    match ArticleSound::get_all(&mut *conn).await {
+        Ok(value) => Ok(value),
+        Err(e) => {
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            error!("Failed to get sounds: {}", e);
+            Err(ServerFnError::new("Failed to get sounds!"))
+        }
+    }
+}
+
+/// Saves an uploaded sound into the data directory and assigns it to the
+/// article.
+///
+/// Expects the multipart fields `article_id`, `name` and `file`.
+#[server(input = MultipartFormData)]
+pub async fn upload_article_sound(data: MultipartData) -> Result<ArticleSound, ServerFnError> {
+    use crate::backend::ServerState;
+    let state: ServerState = expect_context();
+    use axum::http::StatusCode;
+    use leptos_axum::ResponseOptions;
+
+    let response_opts: ResponseOptions = expect_context();
+
+    let Some(mut multipart) = data.into_inner() else {
+        response_opts.set_status(StatusCode::BAD_REQUEST);
+        return Err(ServerFnError::new("Failed to read upload!"));
+    };
+
+    let mut article_id = None;
+    let mut name = String::new();
+    let mut file = None;
+
+    loop {
+        let mut field = match multipart.next_field().await {
+            Ok(Some(value)) => value,
+            Ok(None) => break,
+            Err(e) => {
+                response_opts.set_status(StatusCode::BAD_REQUEST);
+                return Err(ServerFnError::new(format!("Failed to read upload: {e}")));
+            }
+        };
+
+        match field.name() {
+            Some("article_id") => match field.text().await.map(|text| text.parse::<i64>()) {
+                Ok(Ok(value)) => article_id = Some(value),
+                _ => {
+                    response_opts.set_status(StatusCode::BAD_REQUEST);
+                    return Err(ServerFnError::new("Invalid article id!"));
+                }
+            },
+            Some("name") => match field.text().await {
+                Ok(value) => name = value.trim().to_string(),
+                Err(e) => {
+                    response_opts.set_status(StatusCode::BAD_REQUEST);
+                    return Err(ServerFnError::new(format!("Failed to read upload: {e}")));
+                }
+            },
+            Some("file") => {
+                let file_name = field.file_name().unwrap_or_default().to_string();
+                let mut content = Vec::new();
+
+                loop {
+                    match field.chunk().await {
+                        Ok(Some(chunk)) if content.len() + chunk.len() > MAX_SOUND_SIZE => {
+                            response_opts.set_status(StatusCode::PAYLOAD_TOO_LARGE);
+                            return Err(ServerFnError::new(format!(
+                                "Sounds may have at most {} MiB!",
+                                MAX_SOUND_SIZE / 1024 / 1024
+                            )));
+                        }
+                        Ok(Some(chunk)) => content.extend_from_slice(&chunk),
+                        Ok(None) => break,
+                        Err(e) => {
+                            response_opts.set_status(StatusCode::BAD_REQUEST);
+                            return Err(ServerFnError::new(format!("Failed to read upload: {e}")));
+                        }
+                    }
+                }
+
+                file = Some((file_name, content));
+            }
+            _ => {}
+        }
+    }
+
+    let Some(article_id) = article_id else {
+        response_opts.set_status(StatusCode::BAD_REQUEST);
+        return Err(ServerFnError::new("Missing article id!"));
+    };
+
+    let Some((file_name, content)) = file.filter(|(_, content)| !content.is_empty()) else {
+        response_opts.set_status(StatusCode::BAD_REQUEST);
+        return Err(ServerFnError::new("Choose a sound file to upload!"));
+    };
+
+    let safe_name = match sound_file_name(&file_name, &content) {
+        Ok(value) => value,
+        Err(e) => {
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(ServerFnError::new(e));
+        }
+    };
+
+    if name.is_empty() {
+        name = file_name;
+    }
+
+    // held until the sound is stored, so the file isn't taken for unused
+    let db = state.db.lock().await;
+
+    let mut db_transaction = match db.get_conn_transaction().await {
+        Ok(value) => value,
+        Err(e) => {
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            error!("Failed to get transaction for database: {}", e);
+            return Err(ServerFnError::new("Failed to get transaction handle!"));
+        }
+    };
+
+    match ArticleDB::get_single(&mut *db_transaction, article_id).await {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(ServerFnError::new(format!(
+                "Unknown article id '{article_id}'"
+            )));
+        }
+        Err(e) => {
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            error!("Failed to look up article: {}", e);
+            return Err(ServerFnError::new("Failed to look up article!"));
+        }
+    }
+
+    let directory = uploaded_sounds_directory(&state.data_dir);
+
+    if let Err(e) = tokio::fs::create_dir_all(&directory).await {
+        response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+        error!("Failed to create '{}': {}", directory.display(), e);
+        return Err(ServerFnError::new("Failed to store sound!"));
+    }
+
+    let path = directory.join(format!("{}_{safe_name}", Utc::now().timestamp_millis()));
+
+    if let Err(e) = tokio::fs::write(&path, &content).await {
+        response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+        error!("Failed to write '{}': {}", path.display(), e);
+        return Err(ServerFnError::new("Failed to store sound!"));
+    }
+
+    let sound =
+        match ArticleSound::create(&mut *db_transaction, name, path.display().to_string()).await {
+            Ok(value) => value,
+            Err(e) => {
+                _ = tokio::fs::remove_file(&path).await;
+                response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+                error!("Failed to store sound: {}", e);
+                return Err(ServerFnError::new("Failed to store sound!"));
+            }
+        };
+
+    let stored = match ArticleDB::add_sound(&mut *db_transaction, article_id, sound.id).await {
+        Ok(_) => db_transaction.commit().await.map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+
+    if let Err(e) = stored {
+        _ = tokio::fs::remove_file(&path).await;
+        response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+        error!("Failed to store sound: {}", e);
+        return Err(ServerFnError::new("Failed to store sound!"));
+    }
+
+    info!("Uploaded sound '{}'", path.display());
+
+    Ok(sound)
+}
+
+/// Inactive articles can't be bought or scanned anymore but stay in the
+/// history of the users.
+#[server]
+pub async fn set_article_active(id: i64, is_active: bool) -> Result<(), ServerFnError> {
+    use crate::backend::ServerState;
+    let state: ServerState = expect_context();
+    use axum::http::StatusCode;
+    use leptos_axum::redirect;
+    use leptos_axum::ResponseOptions;
+
+    let response_opts: ResponseOptions = expect_context();
+
+    let mut article = get_article(id).await?;
+
+    let db = state.db.lock().await;
+
+    let mut conn = match db.get_conn().await {
+        Ok(value) => value,
+        Err(e) => {
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            error!("Failed to get database handle: {}", e);
+            return Err(ServerFnError::new("Failed to get database handle!"));
+        }
+    };
+
+    if let Err(e) = article.set_active(&mut *conn, is_active).await {
+        response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+        error!("Failed to update article state: {}", e);
+        return Err(ServerFnError::new("Failed to update article state!"));
+    }
+
+    redirect("/articles");
+
+    Ok(())
+}
+
+/// Only articles that were never bought or stocked can be deleted, all
+/// others have to be deactivated instead.
+#[server]
+pub async fn delete_article(id: i64) -> Result<(), ServerFnError> {
+    use crate::backend::ServerState;
+    let state: ServerState = expect_context();
+    use axum::http::StatusCode;
+    use leptos_axum::redirect;
+    use leptos_axum::ResponseOptions;
+
+    let response_opts: ResponseOptions = expect_context();
+
+    let article = get_article(id).await?;
+
+    let db = state.db.lock().await;
+
+    let mut db_transaction = match db.get_conn_transaction().await {
+        Ok(value) => value,
+        Err(e) => {
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            error!("Failed to get transaction for database: {}", e);
+            return Err(ServerFnError::new("Failed to get transaction handle!"));
+        }
+    };
+
+    match ArticleDB::is_used(&mut *db_transaction, article.id).await {
+        Ok(false) => {}
+        Ok(true) => {
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(ServerFnError::new(
+                "The article was already bought or stocked, deactivate it instead!",
+            ));
+        }
+        Err(e) => {
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            error!("Failed to look up purchases and stock movements: {}", e);
+            return Err(ServerFnError::new(
+                "Failed to look up purchases and stock movements!",
+            ));
+        }
+    }
+
+    if let Err(e) = ArticleDB::delete(&mut *db_transaction, article.id).await {
+        response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+        error!("Failed to delete article: {}", e);
+        return Err(ServerFnError::new("Failed to delete article!"));
+    }
+
+    if let Err(e) = db_transaction.commit().await {
+        response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+        error!("Failed to commit transaction: {}", e);
+        return Err(ServerFnError::new("Failed to commit transaction"));
+    }
+
+    drop(db);
+
+    if let Err(e) = remove_unused_sound_files(&state).await {
+        error!("Failed to remove unused sounds: {}", e);
+    }
+
+    info!("Deleted article '{}'", article.name);
+
     redirect("/articles");
 
     Ok(())
@@ -207,8 +544,45 @@ fn SingleArticleView(article: Article) -> impl IntoView {
     let barcodes_signal = RwSignal::new(article.barcodes.clone());
     let barcodes_diff_signal = RwSignal::new(Vec::<BarcodeDiff>::new());
 
+    let new_tag_node = NodeRef::<html::Input>::new();
+
+    let tags_signal = RwSignal::new(
+        article
+            .tags
+            .iter()
+            .map(|tag| tag.name.clone())
+            .collect::<Vec<_>>(),
+    );
+    let tags_diff_signal = RwSignal::new(Vec::<TagDiff>::new());
+
+    let new_sound_node = NodeRef::<html::Select>::new();
+
+    let sounds_signal = RwSignal::new(article.sounds.clone());
+    let sounds_diff_signal = RwSignal::new(Vec::<SoundDiff>::new());
+
     let error_signal = RwSignal::new(String::new());
 
+    let upload_action =
+        Action::new_local(|data: &FormData| upload_article_sound(data.clone().into()));
+
+    let all_sounds_resource = Resource::new(
+        move || upload_action.version().get(),
+        move |_| get_all_sounds(),
+    );
+
+    // the upload assigns the sound to the article already
+    Effect::new(move || match upload_action.value().get() {
+        Some(Ok(sound)) => sounds_signal.write().push(sound),
+        Some(Err(e)) => error_signal.set(match e {
+            ServerFnError::ServerError(msg) => msg,
+            _ => e.to_string(),
+        }),
+        None => {}
+    });
+
+    let set_active_action = ServerAction::<SetArticleActive>::new();
+    let delete_action = ServerAction::<DeleteArticle>::new();
+
     let class_css = "flex flex-col gap-5";
     let input_css = "ml-5 text-black rounded-[5px] text-center";
 
@@ -226,6 +600,9 @@ fn SingleArticleView(article: Article) -> impl IntoView {
                 cost: _,
                 sounds: _,
                 barcodes: _,
+                tags: _,
+                is_active: _,
+                stock: _,
             } = article;
 
             let name = name_node
@@ -238,7 +615,11 @@ fn SingleArticleView(article: Article) -> impl IntoView {
                 .value();
 
             let barcodes = barcodes_diff_signal.get_untracked();
-            if let Err(e) = update_article(id, name, cost, Some(barcodes)).await {
+            let tags = tags_diff_signal.get_untracked();
+            let sounds = sounds_diff_signal.get_untracked();
+            if let Err(e) =
+                update_article(id, name, cost, Some(barcodes), Some(tags), Some(sounds)).await
+            {
                 let msg = match e {
                     ServerFnError::ServerError(msg) => msg,
                     _ => e.to_string(),
@@ -396,6 +777,164 @@ fn SingleArticleView(article: Article) -> impl IntoView {
                     </button>
                 </div>
             </div>
+            <div class="flex flex-col items-center gap-3">
+                <p class="text-white">"Tags"</p>
+                <div class="flex flex-wrap justify-center gap-2">
+                    {move || {
+                        tags_signal
+                            .get()
+                            .into_iter()
+                            .map(|tag| {
+                                let name = tag.clone();
+                                view! {
+                                    <span class="bg-gray-700 text-white rounded-full px-3 py-1">
+                                        {tag}
+                                        <button
+                                            class="pl-2 text-red-400"
+                                            on:click=move |_| {
+                                                tags_signal.update(|vec| vec.retain(|elem| *elem != name));
+                                                tags_diff_signal
+                                                    .write()
+                                                    .push(TagDiff::Removed(name.clone()));
+                                            }
+                                        >
+                                            "×"
+                                        </button>
+                                    </span>
+                                }
+                            })
+                            .collect_view()
+                    }}
+                </div>
+                <div class="flex justify-center gap-5">
+                    <input
+                        class="text-black rounded-[5px] text-center"
+                        placeholder="e.g. Drinks"
+                        node_ref=new_tag_node
+                    />
+                    <div class="w-[10vw]">
+                        <button
+                            type="button"
+                            class="w-full bg-indigo-700 hover:bg-pink-700 text-white font-bold py-2 px-4 mb-6 rounded"
+                            on:click=move |_| {
+                                let node = new_tag_node
+                                    .get()
+                                    .expect("new_tag_input should be mounted!");
+                                let new_tag = node.value().trim().to_string();
+                                node.set_value("");
+                                if new_tag.is_empty() || tags_signal.read().contains(&new_tag) {
+                                    return;
+                                }
+                                tags_signal.write().push(new_tag.clone());
+                                tags_diff_signal.write().push(TagDiff::Added(new_tag));
+                            }
+                        >
+                            "Add Tag"
+                        </button>
+                    </div>
+                </div>
+            </div>
+            <div class="flex flex-col items-center gap-3">
+                <p class="text-white">"Sounds"</p>
+                <div class="flex flex-wrap justify-center gap-2">
+                    {move || {
+                        sounds_signal
+                            .get()
+                            .into_iter()
+                            .map(|sound| {
+                                let id = sound.id;
+                                view! {
+                                    <span class="bg-gray-700 text-white rounded-full px-3 py-1">
+                                        {sound.name}
+                                        <button
+                                            class="pl-2 text-red-400"
+                                            on:click=move |_| {
+                                                sounds_signal.update(|vec| vec.retain(|elem| elem.id != id));
+                                                sounds_diff_signal.write().push(SoundDiff::Removed(id));
+                                            }
+                                        >
+                                            "×"
+                                        </button>
+                                    </span>
+                                }
+                            })
+                            .collect_view()
+                    }}
+                </div>
+                <div class="flex justify-center gap-5">
+                    <select class="text-black rounded-[5px] text-center" node_ref=new_sound_node>
+                        <Suspense fallback=|| ()>
+                            {move || {
+                                all_sounds_resource
+                                    .get()
+                                    .map(|sounds| {
+                                        sounds
+                                            .unwrap_or_default()
+                                            .into_iter()
+                                            .map(|sound| {
+                                                view! { <option value=sound.id>{sound.name}</option> }
+                                            })
+                                            .collect_view()
+                                    })
+                            }}
+                        </Suspense>
+                    </select>
+                    <div class="w-[10vw]">
+                        <button
+                            type="button"
+                            class="w-full bg-indigo-700 hover:bg-pink-700 text-white font-bold py-2 px-4 mb-6 rounded"
+                            on:click=move |_| {
+                                let node = new_sound_node
+                                    .get()
+                                    .expect("new_sound_select should be mounted!");
+                                let Ok(id) = node.value().parse::<i64>() else {
+                                    return;
+                                };
+                                if sounds_signal.read().iter().any(|sound| sound.id == id) {
+                                    return;
+                                }
+                                let Some(sound) = all_sounds_resource
+                                    .get_untracked()
+                                    .and_then(|sounds| sounds.ok())
+                                    .and_then(|sounds| sounds.into_iter().find(|sound| sound.id == id)) else {
+                                    return;
+                                };
+                                sounds_signal.write().push(sound);
+                                sounds_diff_signal.write().push(SoundDiff::Added(id));
+                            }
+                        >
+                            "Add Sound"
+                        </button>
+                    </div>
+                </div>
+                <form
+                    class="flex justify-center gap-5 text-white"
+                    on:submit=move |ev: SubmitEvent| {
+                        ev.prevent_default();
+                        let form = ev
+                            .target()
+                            .expect("upload form should be mounted!")
+                            .unchecked_into::<HtmlFormElement>();
+                        let Ok(data) = FormData::new_with_form(&form) else {
+                            return;
+                        };
+                        upload_action.dispatch_local(data);
+                    }
+                >
+                    <input type="hidden" name="article_id" value=article.id />
+                    <input
+                        class="text-black rounded-[5px] text-center"
+                        name="name"
+                        placeholder="Name"
+                    />
+                    <input type="file" name="file" accept="audio/*" />
+                    <input
+                        class="bg-indigo-700 hover:bg-pink-700 text-white font-bold py-2 px-4 rounded"
+                        type="submit"
+                        value="Upload sound"
+                    />
+                </form>
+            </div>
 
             // Last element (Submit button)
             <div class="w-[30vw]">
@@ -406,6 +945,51 @@ fn SingleArticleView(article: Article) -> impl IntoView {
                     on:click=on_click
                 />
             </div>
+            <div class="flex justify-center gap-5">
+                <ActionForm action=set_active_action>
+                    <input type="hidden" name="id" value=article.id />
+                    <input type="hidden" name="is_active" value=(!article.is_active).to_string() />
+                    <input
+                        class="text-white hover:bg-pink-700 bg-emerald-700 rounded-full p-2"
+                        type="submit"
+                        value=match article.is_active {
+                            true => "Deactivate article",
+                            false => "Activate article",
+                        }
+                    />
+                </ActionForm>
+                <ActionForm action=delete_action>
+                    <input type="hidden" name="id" value=article.id />
+                    <input
+                        class="text-white hover:bg-pink-700 bg-red-400 rounded-full p-2"
+                        type="submit"
+                        value="Delete article"
+                    />
+                </ActionForm>
+            </div>
+            {move || {
+                let result = match delete_action.value().get() {
+                    Some(Err(e)) => Some(e),
+                    _ => {
+                        match set_active_action.value().get() {
+                            Some(Err(e)) => Some(e),
+                            _ => None,
+                        }
+                    }
+                };
+                result
+                    .map(|e| {
+                        let msg = match e {
+                            ServerFnError::ServerError(msg) => msg,
+                            _ => e.to_string(),
+                        };
+                        view! {
+                            <div class="bg-red-400 p-5">
+                                <p class="text-white text-center">{msg}</p>
+                            </div>
+                        }
+                    })
+            }}
         </div>
     }
     .into_any();
@@ -2,7 +2,7 @@ use leptos::prelude::*;
 #[cfg(feature = "ssr")]
 use tracing::error;
 
-use crate::models::Article;
+use crate::{models::Article, routes::components::tag_filter::TagFilter};
 
 #[server]
 pub async fn get_article_by_barcode(barcode: String) -> Result<Option<Article>, ServerFnError> {
@@ -29,7 +29,10 @@ pub async fn get_article_by_barcode(barcode: String) -> Result<Option<Article>,
 }
 
 #[server]
-pub async fn get_all_articles(limit: Option<i64>) -> Result<Vec<Article>, ServerFnError> {
+pub async fn get_all_articles(
+    limit: Option<i64>,
+    include_inactive: bool,
+) -> Result<Vec<Article>, ServerFnError> {
     use crate::backend::ServerState;
     let state: ServerState = expect_context();
     use axum::http::StatusCode;
@@ -37,7 +40,7 @@ pub async fn get_all_articles(limit: Option<i64>) -> Result<Vec<Article>, Server
 
     let response_opts: ResponseOptions = expect_context();
 
-    let articles = Article::get_all(&*state.db.lock().await, limit).await;
+    let articles = Article::get_all(&*state.db.lock().await, limit, include_inactive).await;
     articles.map_err(|e| {
         let err = e.to_string();
         error!("Could not fetch articles {}", err);
@@ -69,7 +72,8 @@ pub fn View() -> impl IntoView {
 
 #[component]
 fn ShowArticles() -> impl IntoView {
-    let all_articles = OnceResource::new(get_all_articles(None));
+    let all_articles = OnceResource::new(get_all_articles(None, true));
+    let selected_tag = RwSignal::new(None::<String>);
     view! {
         <Suspense fallback=move || {
             view! { <h1>"Loading articles..."</h1> }
@@ -90,25 +94,48 @@ fn ShowArticles() -> impl IntoView {
                                     .into_any()
                             }
                             Ok(mut articles) => {
+                                articles.sort_by(|a, b| a.name.cmp(&b.name));
+                                let tags = Article::tag_names(&articles);
                                 view! {
+                                    <TagFilter tags selected=selected_tag />
                                     <table class="w-full text-white p-2">
                                         <thead>
                                             <tr class="bg-black">
                                                 <th>"Name"</th>
                                                 <th>"Preis"</th>
+                                                <th>"Tags"</th>
                                                 <th></th>
                                             </tr>
                                         </thead>
                                         <tbody>
-                                            {
-                                                articles.sort_by(|a, b| a.name.cmp(&b.name));
+                                            {move || {
+                                                let selected = selected_tag.get();
                                                 articles
-                                                    .into_iter()
+                                                    .iter()
+                                                    .filter(|article| match &selected {
+                                                        Some(tag) => article.has_tag(tag),
+                                                        None => true,
+                                                    })
+                                                    .cloned()
                                                     .map(|article| {
                                                         view! {
-                                                            <tr class="even:bg-gray-700 odd:bg-gray-500">
-                                                                <td class="p-2 text-center">{article.name}</td>
+                                                            <tr
+                                                                class="even:bg-gray-700 odd:bg-gray-500"
+                                                                class:text-gray-400=!article.is_active
+                                                            >
+                                                                <td class="p-2 text-center">
+                                                                    {article.name}
+                                                                    {(!article.is_active).then_some(" (inactive)")}
+                                                                </td>
                                                                 <td class="p-2 text-center">{article.cost.format_eur()}</td>
+                                                                <td class="p-2 text-center">
+                                                                    {article
+                                                                        .tags
+                                                                        .iter()
+                                                                        .map(|tag| tag.name.clone())
+                                                                        .collect::<Vec<_>>()
+                                                                        .join(", ")}
+                                                                </td>
                                                                 <td class="bg-green-700 p-2">
                                                                     <a href=format!("/articles/{}", article.id)>
                                                                         <p class="text-center">"Edit"</p>
@@ -118,7 +145,7 @@ fn ShowArticles() -> impl IntoView {
                                                         }
                                                     })
                                                     .collect_view()
-                                            }
+                                            }}
                                         </tbody>
                                     </table>
                                 }
@@ -1,2 +1,3 @@
 pub mod error_message;
 pub mod icons;
+pub mod tag_filter;
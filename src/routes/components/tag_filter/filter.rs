@@ -0,0 +1,50 @@
+use leptos::prelude::*;
+
+/// Chips to narrow a list of articles down to a single tag, `selected` is
+/// `None` while all articles are shown.
+#[component]
+pub fn TagFilter(tags: Vec<String>, selected: RwSignal<Option<String>>) -> impl IntoView {
+    if tags.is_empty() {
+        return ().into_any();
+    }
+
+    let chip_class = "text-white rounded-full px-3 py-1";
+
+    view! {
+        <div class="flex flex-wrap justify-center gap-2 p-2">
+            <button
+                class=chip_class
+                class=("bg-emerald-700", move || selected.read().is_none())
+                class=("bg-gray-700", move || selected.read().is_some())
+                on:click=move |_| selected.set(None)
+            >
+                "All"
+            </button>
+            {tags
+                .into_iter()
+                .map(|tag| {
+                    let is_selected = {
+                        let tag = tag.clone();
+                        move || selected.read().as_ref() == Some(&tag)
+                    };
+                    let on_click = {
+                        let tag = tag.clone();
+                        move |_| selected.set(Some(tag.clone()))
+                    };
+
+                    view! {
+                        <button
+                            class=chip_class
+                            class=("bg-emerald-700", is_selected.clone())
+                            class=("bg-gray-700", move || !is_selected())
+                            on:click=on_click
+                        >
+                            {tag}
+                        </button>
+                    }
+                })
+                .collect_view()}
+        </div>
+    }
+    .into_any()
+}
@@ -68,6 +68,9 @@ pub fn View() -> impl IntoView {
                         </button>
                     </div>
                 </a>
+                <a href="/user/archived" class="block pt-5 text-gray-400 text-center">
+                    "Archived"
+                </a>
             </div>
             {InvisibleScanInput()}
             <div class="col-span-9 pr-7">{ShowUsers()}</div>
@@ -1,6 +1,8 @@
+pub mod admin;
 pub mod articles;
 pub mod home;
 pub mod navbar;
+pub mod settle_up;
 pub mod split_cost;
 pub mod user;
 
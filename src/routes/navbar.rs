@@ -9,6 +9,8 @@ pub fn View() -> impl IntoView {
                 <a href="/">"Strichliste"</a>
                 <a href="/articles">"Article list"</a>
                 <a href="/split_cost">"Split cost"</a>
+                <a href="/settle_up">"Settle up"</a>
+                <a href="/admin">"Admin"</a>
             </div>
         </nav>
     }
@@ -0,0 +1,238 @@
+use leptos::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{models::Settlement, routes::user::CreateTransactionError};
+
+#[cfg(feature = "ssr")]
+use {
+    crate::backend::db::{DBError, DatabaseType},
+    crate::models::{compute_settlements, Group, Money, Transaction, TransactionType, User},
+    crate::routes::user::is_unlocked,
+    leptos_axum::redirect,
+    sqlx::Executor,
+    tracing::error,
+};
+
+#[derive(Error, Debug, Clone, Deserialize, Serialize)]
+pub enum SettleUpError {
+    #[error("Server function error: {0}")]
+    ServerFn(ServerFnErrorErr),
+
+    #[error("Database error: {0}")]
+    DatabaseError(String),
+
+    #[error("User with nickname '{0}' does not exist!")]
+    UserDoesNotExistError(String),
+
+    #[error("Failed to create transaction: {0}")]
+    CreateTransactionError(String),
+
+    #[error("Select at least two users to settle up")]
+    NotEnoughUsersError,
+
+    #[error("'{0}' can't settle up")]
+    InvalidUserError(String),
+
+    #[error("Please enter the PIN of {0} first!")]
+    PinRequiredError(String),
+
+    #[error("The transfers changed in the meantime, please check them again")]
+    SettlementsChangedError,
+}
+
+impl FromServerFnError for SettleUpError {
+    type Encoder = server_fn::codec::JsonEncoding;
+
+    fn from_server_fn_error(value: ServerFnErrorErr) -> Self {
+        Self::ServerFn(value)
+    }
+}
+
+impl From<CreateTransactionError> for SettleUpError {
+    fn from(value: CreateTransactionError) -> Self {
+        Self::CreateTransactionError(value.to_string())
+    }
+}
+
+#[cfg(feature = "ssr")]
+impl From<DBError> for SettleUpError {
+    fn from(value: DBError) -> Self {
+        Self::DatabaseError(value.to_string())
+    }
+}
+
+/// The transfers that settle the balances of the users with the given
+/// nicknames between each other, also returns the users. Negative balances are
+/// paid to positive ones until one side is used up.
+#[cfg(feature = "ssr")]
+async fn get_settlements_for<T>(
+    conn: &mut T,
+    nicknames: &[String],
+) -> Result<(Vec<User>, Vec<Settlement>), SettleUpError>
+where
+    for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+{
+    let mut users = Vec::<User>::new();
+
+    for nickname in nicknames.iter() {
+        match User::get_by_nick(&mut *conn, nickname).await? {
+            Some(user) if users.iter().any(|elem| elem.id == user.id) => {}
+            Some(user) if user.is_system_user || user.is_archived => {
+                return Err(SettleUpError::InvalidUserError(user.nickname))
+            }
+            Some(user) => users.push(user),
+            None => return Err(SettleUpError::UserDoesNotExistError(nickname.clone())),
+        }
+    }
+
+    if users.len() < 2 {
+        return Err(SettleUpError::NotEnoughUsersError);
+    }
+
+    let balances = users
+        .iter()
+        .map(|user| (user.id, user.money.value))
+        .collect::<Vec<_>>();
+
+    let nickname = |user_id| {
+        users
+            .iter()
+            .find(|user| user.id == user_id)
+            .map(|user| user.nickname.clone())
+            .unwrap_or_default()
+    };
+
+    let settlements = compute_settlements(&balances)
+        .into_iter()
+        .map(|(from, to, amount)| Settlement {
+            from,
+            from_name: nickname(from),
+            to,
+            to_name: nickname(to),
+            money: Money { value: amount },
+        })
+        .collect();
+
+    Ok((users, settlements))
+}
+
+#[server]
+pub async fn get_settlements(
+    users_input: Option<Vec<String>>,
+) -> Result<Vec<Settlement>, SettleUpError> {
+    use crate::backend::ServerState;
+    let state: ServerState = expect_context();
+    use axum::http::StatusCode;
+    use leptos_axum::ResponseOptions;
+
+    let response_opts: ResponseOptions = expect_context();
+
+    let db = state.db.lock().await;
+    let mut conn = match db.get_conn().await {
+        Ok(val) => val,
+        Err(e) => {
+            error!("Failed to get database handle: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(SettleUpError::DatabaseError(
+                "Failed to get database handle!".to_string(),
+            ));
+        }
+    };
+
+    match get_settlements_for(&mut *conn, &users_input.unwrap_or_default()).await {
+        Ok((_, settlements)) => Ok(settlements),
+        Err(e) => {
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            Err(e)
+        }
+    }
+}
+
+/// Books the settlements of the given users after the debtors paid the
+/// creditors outside of the list. They are computed again here and only
+/// booked if they still match the `confirmed` ones the users were shown. Every
+/// creditor has to be unlocked, as their balance decreases.
+#[server]
+pub async fn settle_up(
+    users_input: Option<Vec<String>>,
+    confirmed: Option<Vec<Settlement>>,
+) -> Result<(), SettleUpError> {
+    use crate::backend::ServerState;
+    let state: ServerState = expect_context();
+    use axum::http::StatusCode;
+    use leptos_axum::ResponseOptions;
+
+    let response_opts: ResponseOptions = expect_context();
+
+    let db = state.db.lock().await;
+    let mut trans = match db.get_conn_transaction().await {
+        Ok(val) => val,
+        Err(e) => {
+            error!("Failed to get database handle: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(SettleUpError::DatabaseError(
+                "Failed to get database handle!".to_string(),
+            ));
+        }
+    };
+
+    let (users, settlements) =
+        match get_settlements_for(&mut *trans, &users_input.unwrap_or_default()).await {
+            Ok(value) => value,
+            Err(e) => {
+                response_opts.set_status(StatusCode::BAD_REQUEST);
+                return Err(e);
+            }
+        };
+
+    if settlements != confirmed.unwrap_or_default() {
+        response_opts.set_status(StatusCode::CONFLICT);
+        return Err(SettleUpError::SettlementsChangedError);
+    }
+
+    for creditor in users.iter().filter(|user| {
+        settlements
+            .iter()
+            .any(|settlement| settlement.to == user.id)
+    }) {
+        if !is_unlocked(creditor).await {
+            response_opts.set_status(StatusCode::UNAUTHORIZED);
+            return Err(SettleUpError::PinRequiredError(creditor.nickname.clone()));
+        }
+    }
+
+    for settlement in settlements.into_iter() {
+        // the creditor hands the part of their balance to the debtor that the
+        // debtor paid them in cash
+        let debtor_group = Group::get_user_group_id(&mut *trans, settlement.from).await?;
+        let creditor_group = Group::get_user_group_id(&mut *trans, settlement.to).await?;
+
+        if let Err(e) = Transaction::create(
+            &mut *trans,
+            creditor_group,
+            debtor_group,
+            TransactionType::Sent(debtor_group),
+            Some("Settle up".to_string()),
+            settlement.money,
+            &state.settings,
+        )
+        .await
+        {
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(e.into());
+        }
+    }
+
+    if let Err(e) = trans.commit().await {
+        error!("Failed to commit transaction: {e}");
+        response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+        return Err(SettleUpError::DatabaseError(
+            "Failed to commit transaction".to_string(),
+        ));
+    }
+
+    redirect("/");
+
+    Ok(())
+}
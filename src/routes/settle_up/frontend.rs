@@ -0,0 +1,143 @@
+use leptos::{prelude::*, task::spawn_local};
+
+use crate::models::Settlement;
+use crate::routes::{
+    components::error_message::ErrorMessage,
+    settle_up::{get_settlements, settle_up},
+    split_cost::components::multi_user_selection::SelectMultiUser,
+};
+
+#[component]
+pub fn Show() -> impl IntoView {
+    let users = RwSignal::new(Vec::<String>::new());
+
+    let error_signal = RwSignal::new(String::new());
+    // the transfers shown for confirmation before they are booked
+    let confirming = RwSignal::new(None::<Vec<Settlement>>);
+
+    Effect::new(move || {
+        users.track();
+        confirming.set(None);
+    });
+
+    let settlements_resource = Resource::new(
+        move || users.get(),
+        |users| async move {
+            match users.len() {
+                0 | 1 => Ok(Vec::new()),
+                _ => get_settlements(Some(users)).await,
+            }
+        },
+    );
+
+    let on_click = move |_| match settlements_resource.get_untracked() {
+        Some(Ok(settlements)) if !settlements.is_empty() => confirming.set(Some(settlements)),
+        _ => error_signal.set("Nothing to settle".to_string()),
+    };
+
+    let on_confirm = move |_| {
+        let confirmed = confirming.get_untracked();
+        confirming.set(None);
+
+        spawn_local(async move {
+            if let Err(e) = settle_up(Some(users.get_untracked()), confirmed).await {
+                error_signal.update(|value| *value = e.to_string());
+                settlements_resource.refetch();
+            }
+        })
+    };
+
+    view! {
+        {move || {
+            let msg = error_signal.get();
+            match msg.len() {
+                0 => ().into_any(),
+                _ => view! { <ErrorMessage error=msg /> }.into_any(),
+            }
+        }}
+        <div class="flex flex-col items-center text-[1.25em]">
+            <div class="grid grid-cols-2 py-2 w-fit h-fit justify-center gap-2">
+                <SelectMultiUser
+                    title=String::from("Who wants to settle up?")
+                    users_input=users
+                    single_user_extra_class="w-full h-full".to_string()
+                />
+                <div class="flex flex-col items-center gap-3 p-2 bg-indigo-100 rounded text-indigo-700">
+                    <p>"Transfers"</p>
+                    <Suspense fallback=move || view! { <p>"Loading..."</p> }>
+                        {move || {
+                            settlements_resource
+                                .get()
+                                .map(|settlements| match settlements {
+                                    Err(e) => view! { <p>{e.to_string()}</p> }.into_any(),
+                                    Ok(settlements) if settlements.is_empty() => {
+                                        view! { <p>"Nothing to settle"</p> }.into_any()
+                                    }
+                                    Ok(settlements) => {
+                                        view! {
+                                            <table>
+                                                {settlements
+                                                    .into_iter()
+                                                    .map(|settlement| {
+                                                        view! {
+                                                            <tr>
+                                                                <td class="px-2">{settlement.from_name}</td>
+                                                                <td class="px-2">"pays"</td>
+                                                                <td class="px-2">{settlement.to_name}</td>
+                                                                <td class="px-2">{settlement.money.format_eur()}</td>
+                                                            </tr>
+                                                        }
+                                                    })
+                                                    .collect_view()}
+                                            </table>
+                                        }
+                                            .into_any()
+                                    }
+                                })
+                        }}
+                    </Suspense>
+                </div>
+                <div class="flex items-center justify-center bg-indigo-100 rounded p-2 col-span-2">
+                    {move || match confirming.get() {
+                        None => {
+                            view! {
+                                <button
+                                    class="w-full bg-indigo-700 hover:bg-pink-700 text-white p-3 rounded"
+                                    on:click=on_click
+                                >
+                                    "Settle up"
+                                </button>
+                            }
+                                .into_any()
+                        }
+                        Some(settlements) => {
+                            view! {
+                                <div class="flex flex-col items-center gap-2 w-full text-indigo-700">
+                                    <p>
+                                        "Book these "{settlements.len()}
+                                        " transfers? Everyone has to have paid in cash first."
+                                    </p>
+                                    <div class="flex gap-2 w-full">
+                                        <button
+                                            class="w-full bg-indigo-700 hover:bg-pink-700 text-white p-3 rounded"
+                                            on:click=on_confirm
+                                        >
+                                            "Confirm"
+                                        </button>
+                                        <button
+                                            class="w-full bg-gray-500 hover:bg-pink-700 text-white p-3 rounded"
+                                            on:click=move |_| confirming.set(None)
+                                        >
+                                            "Cancel"
+                                        </button>
+                                    </div>
+                                </div>
+                            }
+                                .into_any()
+                        }
+                    }}
+                </div>
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,5 @@
+pub use frontend::*;
+mod frontend;
+
+pub use backend::*;
+mod backend;
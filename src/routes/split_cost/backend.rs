@@ -2,13 +2,16 @@ use leptos::prelude::*;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use crate::routes::user::CreateTransactionError;
+use crate::{models::SplitMode, routes::user::CreateTransactionError};
 
 #[cfg(feature = "ssr")]
 use {
-    crate::backend::db::DBError,
-    crate::models::{Group, GroupId, Money, Transaction, User, UserId},
+    crate::backend::db::{DBError, DatabaseType},
+    crate::models::{split_by_weights, Group, GroupId, Money, Transaction, User, UserId},
+    crate::routes::user::is_unlocked,
     leptos_axum::redirect,
+    sqlx::Executor,
+    std::collections::HashMap,
     tracing::error,
 };
 
@@ -34,6 +37,15 @@ pub enum SplitCostError {
 
     #[error("{0} may not be empty")]
     MayNotBeEmptyError(String),
+
+    #[error("Invalid shares: {0}")]
+    InvalidSharesError(String),
+
+    #[error("{0} may list '{1}' only once")]
+    DuplicateUserError(String, String),
+
+    #[error("Please enter the PIN of {0} first!")]
+    PinRequiredError(String),
 }
 
 impl FromServerFnError for SplitCostError {
@@ -57,12 +69,151 @@ impl From<DBError> for SplitCostError {
     }
 }
 
+/// The amount every participant has to pay, `inputs` holds a weight or an
+/// amount per participant depending on the mode.
+#[cfg(feature = "ssr")]
+fn compute_shares(
+    total: Money,
+    mode: SplitMode,
+    participants: usize,
+    inputs: &[String],
+) -> Result<Vec<i64>, SplitCostError> {
+    type Error = SplitCostError;
+
+    if mode != SplitMode::Equal && inputs.len() != participants {
+        return Err(Error::InvalidSharesError(
+            "every participant needs a share".to_string(),
+        ));
+    }
+
+    match mode {
+        SplitMode::Equal => split_by_weights(total.value, &vec![1; participants])
+            .ok_or_else(|| Error::InvalidSharesError("nobody to split with".to_string())),
+
+        SplitMode::Weights => {
+            let weights = inputs
+                .iter()
+                .map(|input| {
+                    input
+                        .trim()
+                        .parse::<u64>()
+                        .map_err(|_| Error::InvalidSharesError(format!("invalid weight '{input}'")))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            split_by_weights(total.value, &weights)
+                .ok_or_else(|| Error::InvalidSharesError("all weights are zero".to_string()))
+        }
+
+        SplitMode::Amounts => {
+            let amounts = inputs
+                .iter()
+                .map(|input| match Money::try_from(input.trim().to_string()) {
+                    Ok(value) if value.value >= 0 => Ok(value.value),
+                    _ => Err(Error::InvalidSharesError(format!(
+                        "invalid amount '{input}'"
+                    ))),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            if amounts.iter().sum::<i64>() != total.value {
+                return Err(Error::InvalidSharesError(format!(
+                    "the amounts don't add up to {}",
+                    total.format_eur()
+                )));
+            }
+
+            Ok(amounts)
+        }
+    }
+}
+
+/// What every payer paid, `inputs` may be empty if there is a single payer who
+/// paid everything.
+#[cfg(feature = "ssr")]
+fn compute_payments(
+    total: Money,
+    payers: usize,
+    inputs: &[String],
+) -> Result<Vec<i64>, SplitCostError> {
+    type Error = SplitCostError;
+
+    if payers == 1 && inputs.iter().all(|input| input.trim().is_empty()) {
+        return Ok(vec![total.value]);
+    }
+
+    if inputs.len() != payers {
+        return Err(Error::InvalidSharesError(
+            "every payer needs an amount".to_string(),
+        ));
+    }
+
+    let amounts = inputs
+        .iter()
+        .map(|input| match Money::try_from(input.trim().to_string()) {
+            Ok(value) if value.value >= 0 => Ok(value.value),
+            _ => Err(Error::InvalidSharesError(format!(
+                "invalid amount '{input}'"
+            ))),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if amounts.iter().sum::<i64>() != total.value {
+        return Err(Error::InvalidSharesError(format!(
+            "the payments don't add up to {}",
+            total.format_eur()
+        )));
+    }
+
+    Ok(amounts)
+}
+
+#[cfg(feature = "ssr")]
+async fn get_users<T>(conn: &mut T, nicknames: &[String]) -> Result<Vec<User>, SplitCostError>
+where
+    for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+{
+    let mut users = Vec::new();
+
+    for nickname in nicknames.iter() {
+        match User::get_by_nick(&mut *conn, nickname).await? {
+            Some(val) => users.push(val),
+            None => return Err(SplitCostError::UserDoesNotExistError(nickname.clone())),
+        }
+    }
+
+    Ok(users)
+}
+
+#[cfg(feature = "ssr")]
+async fn get_group_id<T>(conn: &mut T, user_ids: &[UserId]) -> Result<GroupId, SplitCostError>
+where
+    for<'a> &'a mut T: Executor<'a, Database = DatabaseType>,
+{
+    Ok(match user_ids {
+        [user_id] => Group::get_user_group_id(&mut *conn, *user_id).await?,
+        _ => Group::get_group_id_for_multiple_users(&mut *conn, user_ids).await?,
+    })
+}
+
+/// Splits `money` paid by the payers between the consumers, a payer doesn't
+/// have to be a consumer. `payer_amounts_input` holds what every payer paid,
+/// `shares_input` the weights or amounts of the consumers, it is ignored for
+/// equal splits.
+///
+/// Everyone who paid more than their share receives the difference from the
+/// ones who paid less, booked as a single transaction. The payers and everyone
+/// who pays the difference have to be unlocked.
+#[allow(clippy::too_many_arguments)]
 #[server]
 pub async fn split_cost(
-    primary_user: String,
-    secondary_users_input: Option<Vec<String>>,
+    payers_input: Option<Vec<String>>,
+    #[server(default)] payer_amounts_input: Option<Vec<String>>,
+    consumers_input: Option<Vec<String>>,
     money: String,
     description: String,
+    #[server(default)] split_mode: SplitMode,
+    #[server(default)] shares_input: Option<Vec<String>>,
 ) -> Result<(), SplitCostError> {
     use crate::backend::ServerState;
     let state: ServerState = expect_context();
@@ -71,7 +222,7 @@ pub async fn split_cost(
 
     let response_opts: ResponseOptions = expect_context();
 
-    let mut money: Money = match money.try_into() {
+    let money: Money = match money.try_into() {
         Ok(value) => value,
         Err(e) => {
             response_opts.set_status(StatusCode::BAD_REQUEST);
@@ -79,26 +230,34 @@ pub async fn split_cost(
         }
     };
 
-    if primary_user.is_empty() {
+    let payers_input = payers_input.unwrap_or_default();
+
+    if payers_input.is_empty() {
+        response_opts.set_status(StatusCode::BAD_REQUEST);
+        return Err(SplitCostError::MayNotBeEmptyError("Payers".to_string()));
+    }
+
+    let consumers_input = consumers_input.unwrap_or_default();
+
+    if consumers_input.is_empty() {
         response_opts.set_status(StatusCode::BAD_REQUEST);
-        return Err(SplitCostError::MayNotBeEmptyError("User".to_string()));
+        return Err(SplitCostError::MayNotBeEmptyError("Consumers".to_string()));
     }
 
-    let secondary_users_input = match secondary_users_input {
-        Some(val) => val,
-        None => {
+    // the amounts are matched to the nicknames by position
+    for (name, nicknames) in [("Payers", &payers_input), ("Consumers", &consumers_input)] {
+        if let Some(nickname) = nicknames
+            .iter()
+            .enumerate()
+            .find(|(index, nickname)| nicknames[..*index].contains(nickname))
+            .map(|(_, nickname)| nickname)
+        {
             response_opts.set_status(StatusCode::BAD_REQUEST);
-            return Err(SplitCostError::MayNotBeEmptyError(
-                "Other users".to_string(),
+            return Err(SplitCostError::DuplicateUserError(
+                name.to_string(),
+                nickname.clone(),
             ));
         }
-    };
-
-    if secondary_users_input.is_empty() {
-        response_opts.set_status(StatusCode::BAD_REQUEST);
-        return Err(SplitCostError::MayNotBeEmptyError(
-            "Other users".to_string(),
-        ));
     }
 
     let description = match description.is_empty() {
@@ -113,6 +272,29 @@ pub async fn split_cost(
         ));
     }
 
+    let shares = compute_payments(
+        money,
+        payers_input.len(),
+        &payer_amounts_input.unwrap_or_default(),
+    )
+    .and_then(|paid| {
+        compute_shares(
+            money,
+            split_mode,
+            consumers_input.len(),
+            &shares_input.unwrap_or_default(),
+        )
+        .map(|owed| (paid, owed))
+    });
+
+    let (paid, owed) = match shares {
+        Ok(value) => value,
+        Err(e) => {
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(e);
+        }
+    };
+
     let db = state.db.lock().await;
     let mut trans = match db.get_conn_transaction().await {
         Ok(val) => val,
@@ -125,49 +307,94 @@ pub async fn split_cost(
         }
     };
 
-    let primary_user = match User::get_by_nick(&mut *trans, &primary_user).await? {
-        Some(val) => val,
-        None => {
+    let (payers, consumers) = match (
+        get_users(&mut *trans, &payers_input).await,
+        get_users(&mut *trans, &consumers_input).await,
+    ) {
+        (Ok(payers), Ok(consumers)) => (payers, consumers),
+        (Err(e), _) | (_, Err(e)) => {
             response_opts.set_status(StatusCode::BAD_REQUEST);
-            return Err(SplitCostError::UserDoesNotExistError(primary_user));
+            return Err(e);
         }
     };
 
-    let mut secondary_users: Vec<UserId> = Vec::new();
+    if let Some(user) = payers
+        .iter()
+        .chain(consumers.iter())
+        .find(|user| user.is_system_user || user.is_archived)
+    {
+        response_opts.set_status(StatusCode::BAD_REQUEST);
+        return Err(SplitCostError::InvalidSharesError(format!(
+            "'{}' can't take part in a split",
+            user.nickname
+        )));
+    }
 
-    for user_input in secondary_users_input.into_iter() {
-        let user = match User::get_by_nick(&mut *trans, &user_input).await? {
-            Some(val) => val.id,
-            None => {
-                response_opts.set_status(StatusCode::BAD_REQUEST);
-                return Err(SplitCostError::UserDoesNotExistError(user_input));
-            }
-        };
+    for payer in payers.iter() {
+        if !is_unlocked(payer).await {
+            response_opts.set_status(StatusCode::UNAUTHORIZED);
+            return Err(SplitCostError::PinRequiredError(payer.nickname.clone()));
+        }
+    }
 
-        secondary_users.push(user);
+    let mut balance = HashMap::<UserId, i64>::new();
+
+    for (user, amount) in payers.iter().zip(paid.iter()) {
+        *balance.entry(user.id).or_default() += amount;
     }
 
-    let primary_group = Group::get_user_group_id(&mut *trans, primary_user.id).await?;
+    for (user, amount) in consumers.iter().zip(owed.iter()) {
+        *balance.entry(user.id).or_default() -= amount;
+    }
 
-    let secondary_group =
-        Group::get_group_id_for_multiple_users(&mut *trans, &secondary_users).await?;
+    balance.retain(|_, delta| *delta != 0);
 
-    let total_amount_of_users = (secondary_users.len() + 1) as i64;
+    let mut debtors = balance
+        .iter()
+        .filter(|(_, delta)| **delta < 0)
+        .map(|(user_id, _)| *user_id)
+        .collect::<Vec<_>>();
+    let mut creditors = balance
+        .iter()
+        .filter(|(_, delta)| **delta > 0)
+        .map(|(user_id, _)| *user_id)
+        .collect::<Vec<_>>();
 
-    let single_share = money.value / total_amount_of_users;
+    if debtors.is_empty() {
+        response_opts.set_status(StatusCode::BAD_REQUEST);
+        return Err(SplitCostError::InvalidSharesError(
+            "nobody owes anything".to_string(),
+        ));
+    }
+
+    // the debtors are charged, so they have to agree with their PIN
+    for debtor in consumers.iter().filter(|user| debtors.contains(&user.id)) {
+        if !is_unlocked(debtor).await {
+            response_opts.set_status(StatusCode::UNAUTHORIZED);
+            return Err(SplitCostError::PinRequiredError(debtor.nickname.clone()));
+        }
+    }
 
-    // the primary user already payed his share
-    money.value -= single_share;
+    debtors.sort_by_key(|user_id| user_id.0);
+    creditors.sort_by_key(|user_id| user_id.0);
+
+    let transferred = Money {
+        value: balance.values().filter(|delta| **delta > 0).sum(),
+    };
+
+    let debtor_group = get_group_id(&mut *trans, &debtors).await?;
+    let creditor_group = get_group_id(&mut *trans, &creditors).await?;
 
     let settings = &state.settings;
 
-    Transaction::create(
+    Transaction::create_with_shares(
         &mut *trans,
-        secondary_group,
-        primary_group,
+        debtor_group,
+        creditor_group,
         crate::models::TransactionType::Sent(GroupId(0)),
         description,
-        money,
+        transferred,
+        &balance,
         settings,
     )
     .await?;
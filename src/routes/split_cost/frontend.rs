@@ -1,32 +1,104 @@
+use std::collections::HashMap;
+
 use leptos::{prelude::*, task::spawn_local};
 
+use crate::models::SplitMode;
 use crate::routes::{
     components::error_message::ErrorMessage,
-    split_cost::{
-        components::{
-            multi_user_selection::SelectMultiUser, single_user_selection::SelectSingleUser,
-        },
-        split_cost,
-    },
+    split_cost::{components::multi_user_selection::SelectMultiUser, split_cost},
 };
 
+/// An input per nickname, the values are stored by nickname.
+#[component]
+fn ValueInputs(
+    nicknames: Signal<Vec<String>>,
+    values: RwSignal<HashMap<String, String>>,
+    placeholder: Signal<&'static str>,
+) -> impl IntoView {
+    view! {
+        <table class="text-indigo-700">
+            {move || {
+                nicknames
+                    .get()
+                    .into_iter()
+                    .map(|nickname| {
+                        let key = nickname.clone();
+                        let value = values
+                            .get_untracked()
+                            .get(&nickname)
+                            .cloned()
+                            .unwrap_or_default();
+
+                        view! {
+                            <tr>
+                                <td class="px-2">{nickname}</td>
+                                <td class="px-2">
+                                    <input
+                                        class="rounded-[5px] text-center"
+                                        type="text"
+                                        placeholder=placeholder
+                                        value=value
+                                        on:input=move |ev| {
+                                            values
+                                                .update(|values| {
+                                                    _ = values.insert(key.clone(), event_target_value(&ev));
+                                                })
+                                        }
+                                    />
+                                </td>
+                            </tr>
+                        }
+                    })
+                    .collect_view()
+            }}
+        </table>
+    }
+}
+
 #[component]
 pub fn Show() -> impl IntoView {
-    let primary_user = RwSignal::new(String::new());
-    let secondary_users = RwSignal::new(Vec::<String>::new());
+    let payers = RwSignal::new(Vec::<String>::new());
+    // amount paid per nickname
+    let payer_amounts = RwSignal::new(HashMap::<String, String>::new());
+    let consumers = RwSignal::new(Vec::<String>::new());
     let money_input = RwSignal::new(String::new());
     let description_input = RwSignal::new(String::new());
+    let split_mode = RwSignal::new(SplitMode::Equal);
+    // weight or amount per nickname
+    let shares_input = RwSignal::new(HashMap::<String, String>::new());
 
     let error_signal = RwSignal::new(String::new());
 
     // don't see how I can pass a Vec<String> to a server function with ActionForms
     let on_click = move |_| {
+        let amounts = payer_amounts.get_untracked();
+        let amounts = payers
+            .get_untracked()
+            .iter()
+            .map(|nickname| amounts.get(nickname).cloned().unwrap_or_default())
+            .collect::<Vec<_>>();
+
+        let shares = shares_input.get_untracked();
+        let shares = consumers
+            .get_untracked()
+            .iter()
+            .map(|nickname| match shares.get(nickname) {
+                Some(value) if !value.trim().is_empty() => value.clone(),
+                // an empty weight counts as a single portion
+                _ if split_mode.get_untracked() == SplitMode::Weights => "1".to_string(),
+                _ => String::new(),
+            })
+            .collect::<Vec<_>>();
+
         spawn_local(async move {
             if let Err(e) = split_cost(
-                primary_user.get_untracked(),
-                Some(secondary_users.get_untracked()),
+                Some(payers.get_untracked()),
+                Some(amounts),
+                Some(consumers.get_untracked()),
                 money_input.get_untracked(),
                 description_input.get_untracked(),
+                split_mode.get_untracked(),
+                Some(shares),
             )
             .await
             {
@@ -44,14 +116,27 @@ pub fn Show() -> impl IntoView {
         }}
         <div class="flex flex-col items-center text-[1.25em]">
             <div class="grid grid-cols-2 py-2 w-fit h-fit justify-center gap-2">
-                <SelectSingleUser
-                    title=String::from("Who are you?")
-                    input=primary_user
-                    extra_class="w-full h-full".to_string()
-                />
                 <SelectMultiUser
-                    title=String::from("Who do you want to split the cost with?")
-                    users_input=secondary_users
+                    title=String::from("Who paid?")
+                    users_input=payers
+                    single_user_extra_class="w-full h-full".to_string()
+                >
+                    {move || {
+                        (payers.get().len() > 1)
+                            .then(|| {
+                                view! {
+                                    <ValueInputs
+                                        nicknames=payers.into()
+                                        values=payer_amounts
+                                        placeholder=Signal::stored("0,00")
+                                    />
+                                }
+                            })
+                    }}
+                </SelectMultiUser>
+                <SelectMultiUser
+                    title=String::from("Who consumed?")
+                    users_input=consumers
                     single_user_extra_class="w-full h-full".to_string()
                 />
                 <div class="flex justify-center items-center p-2 bg-indigo-100 rounded">
@@ -74,7 +159,40 @@ pub fn Show() -> impl IntoView {
                         />
                     </div>
                 </div>
-                <div class="flex items-center justify-center bg-indigo-100 rounded p-2">
+                <div class="flex flex-col items-center gap-3 p-2 bg-indigo-100 rounded">
+                    <select
+                        class="text-indigo-700 rounded-[5px] p-1"
+                        on:change=move |ev| {
+                            split_mode
+                                .set(
+                                    SplitMode::parse(&event_target_value(&ev))
+                                        .unwrap_or_default(),
+                                )
+                        }
+                        prop:value=move || split_mode.get().as_str()
+                    >
+                        <option value="equal">"Split equally"</option>
+                        <option value="weights">"Split by weights (e.g. portions)"</option>
+                        <option value="amounts">"Split by amounts"</option>
+                    </select>
+                    {move || {
+                        let placeholder = match split_mode.get() {
+                            SplitMode::Equal => return ().into_any(),
+                            SplitMode::Weights => "1",
+                            SplitMode::Amounts => "0,00",
+                        };
+
+                        view! {
+                            <ValueInputs
+                                nicknames=consumers.into()
+                                values=shares_input
+                                placeholder=Signal::stored(placeholder)
+                            />
+                        }
+                            .into_any()
+                    }}
+                </div>
+                <div class="flex items-center justify-center bg-indigo-100 rounded p-2 col-span-2">
                     <button
                         class="w-full bg-indigo-700 hover:bg-pink-700 text-white p-3 rounded"
                         on:click=on_click
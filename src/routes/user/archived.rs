@@ -0,0 +1,90 @@
+use leptos::prelude::*;
+
+use crate::{models::User, routes::home::UserPreview};
+
+#[cfg(feature = "ssr")]
+use tracing::error;
+
+#[server]
+pub async fn get_archived_users() -> Result<Vec<User>, ServerFnError> {
+    use crate::backend::ServerState;
+    let state: ServerState = expect_context();
+    use axum::http::StatusCode;
+    use leptos_axum::ResponseOptions;
+
+    let response_opts: ResponseOptions = expect_context();
+
+    let users = match User::get_all_archived(&*state.db.lock().await).await {
+        Ok(users) => users,
+        Err(err) => {
+            error!("Could not fetch archived users: {}", err);
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(ServerFnError::new("Failed to fetch archived users"));
+        }
+    };
+
+    Ok(users)
+}
+
+#[component]
+pub fn Archived() -> impl IntoView {
+    let users_resource = OnceResource::new(get_archived_users());
+
+    view! {
+        <div class="p-5">
+            <p class="text-white text-center text-[1.5em] pb-5">"Archived users"</p>
+            <Suspense fallback=move || {
+                view! { <p class="text-white text-center">"Loading users..."</p> }
+            }>
+                {move || {
+                    users_resource
+                        .get()
+                        .map(|users| {
+                            match users {
+                                Err(err) => {
+                                    let msg = match err {
+                                        ServerFnError::ServerError(msg) => msg,
+                                        _ => err.to_string(),
+                                    };
+                                    view! {
+                                        <p class="bg-red-400 text-white text-center">
+                                            "Failed to fetch users: "{msg}
+                                        </p>
+                                    }
+                                        .into_any()
+                                }
+                                Ok(users) if users.is_empty() => {
+                                    view! {
+                                        <p class="text-white text-center">"There are no archived users"</p>
+                                    }
+                                        .into_any()
+                                }
+                                Ok(users) => {
+                                    view! {
+                                        <div
+                                            class="grid gap-5"
+                                            style="grid-template-columns: repeat(auto-fill, minmax(8rem, 1fr));"
+                                        >
+                                            {users
+                                                .into_iter()
+                                                .map(|user| {
+                                                    let id = user.id;
+
+                                                    view! {
+                                                        <a href=format!("/user/{}", id)>
+                                                            <UserPreview user />
+                                                        </a>
+                                                    }
+                                                })
+                                                .collect_view()}
+                                        </div>
+                                    }
+                                        .into_any()
+                                }
+                            }
+                        })
+                }}
+            </Suspense>
+        </div>
+    }
+}
@@ -0,0 +1,353 @@
+use std::rc::Rc;
+
+use leptos::{prelude::*, task::spawn_local};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    models::{play_sound, Article, AudioPlayback, Money, Transaction, UserId},
+    routes::user::{CreateTransactionError, MoneyArgs},
+};
+
+use super::{buy_article::buy_article, icons::ArticleBasketIcon};
+
+#[cfg(feature = "ssr")]
+use {
+    crate::{
+        backend::db::DBGROUP_SNACKBAR_ID,
+        models::{Group, TransactionType},
+        routes::articles::get_article,
+        routes::user::{get_user, is_unlocked},
+    },
+    tracing::error,
+};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BasketItem {
+    pub article_id: i64,
+    pub quantity: u32,
+}
+
+/// Buys all articles of the basket in one database transaction, the limits of
+/// the user are checked against the total of the basket.
+#[server]
+pub async fn checkout_basket(
+    user_id: UserId,
+    items: Vec<BasketItem>,
+) -> Result<Vec<Transaction>, CreateTransactionError> {
+    type Error = CreateTransactionError;
+    use crate::backend::ServerState;
+    use axum::http::StatusCode;
+    use leptos_axum::ResponseOptions;
+
+    let state: ServerState = expect_context();
+
+    let response_opts: ResponseOptions = expect_context();
+
+    if items.is_empty() || items.iter().any(|item| item.quantity == 0) {
+        response_opts.set_status(StatusCode::BAD_REQUEST);
+        return Err(Error::new("The basket is empty!"));
+    }
+
+    let user = match get_user(user_id).await? {
+        Some(user) => user,
+        None => {
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(Error::new("No such user exists!"));
+        }
+    };
+
+    if !is_unlocked(&user).await {
+        response_opts.set_status(StatusCode::UNAUTHORIZED);
+        return Err(Error::PinRequiredError);
+    }
+
+    let mut entries = Vec::new();
+
+    for item in items.iter() {
+        let article = get_article(item.article_id).await?;
+
+        if !article.is_active {
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(Error::new(&format!(
+                "The article '{}' is not sold anymore!",
+                article.name
+            )));
+        }
+
+        entries.push((
+            TransactionType::Bought(article.id),
+            Some(article.name),
+            Money {
+                value: article.cost.value * item.quantity as i64,
+            },
+            item.quantity,
+        ));
+    }
+
+    let db = state.db.lock().await;
+    let mut db_trans = match db.get_conn_transaction().await {
+        Ok(value) => value,
+        Err(e) => {
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            error!("Failed to create db_transaction: {}", e);
+            return Err(Error::new("Failed to create db connection"));
+        }
+    };
+
+    let user_group = match Group::get_user_group_id(&mut *db_trans, user_id).await {
+        Ok(value) => value,
+        Err(e) => {
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            error!("Failed to get user single group: {}", e);
+            return Err(Error::new("Failed to find single user group for user"));
+        }
+    };
+
+    let transaction_ids = Transaction::create_batch(
+        &mut *db_trans,
+        user_group,
+        DBGROUP_SNACKBAR_ID,
+        &entries,
+        &state.settings,
+    )
+    .await?;
+
+    let mut transactions = Vec::with_capacity(transaction_ids.len());
+
+    for transaction_id in transaction_ids.into_iter() {
+        match Transaction::get(&mut *db_trans, transaction_id, user_id).await {
+            Ok(Some(value)) => transactions.push(value),
+            _ => {
+                response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+                error!("Failed to read back db_transaction");
+                return Err(Error::new("Failed to read back db_transaction"));
+            }
+        }
+    }
+
+    match Transaction::set_undo_deadlines(
+        &mut *db_trans,
+        user_id,
+        &state.settings.undo,
+        &mut transactions,
+    )
+    .await
+    {
+        Ok(_) => {}
+        Err(e) => {
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            error!("Failed to check undo policy: {}", e);
+            return Err(Error::new("Failed to check undo policy!"));
+        }
+    }
+
+    match db_trans.commit().await {
+        Ok(_) => {}
+        Err(e) => {
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            error!("Failed to commit transaction: {e}");
+            return Err(Error::new("Failed to commit the db transaction!"));
+        }
+    }
+
+    Ok(transactions)
+}
+
+/// The articles collected before checking out. While the basket is enabled,
+/// clicked and scanned articles are added to it instead of being bought.
+#[derive(Debug, Clone, Copy)]
+pub struct Basket {
+    pub enabled: RwSignal<bool>,
+    pub entries: RwSignal<Vec<(Article, u32)>>,
+}
+
+impl Basket {
+    pub fn new() -> Self {
+        Self {
+            enabled: RwSignal::new(false),
+            entries: RwSignal::new(Vec::new()),
+        }
+    }
+
+    pub fn add(&self, article: Article) {
+        self.entries.update(|entries| {
+            match entries.iter_mut().find(|(elem, _)| elem.id == article.id) {
+                Some((_, quantity)) => *quantity += 1,
+                None => entries.push((article, 1)),
+            }
+        });
+    }
+
+    pub fn remove(&self, article_id: i64) {
+        self.entries.update(|entries| {
+            if let Some((_, quantity)) = entries.iter_mut().find(|(elem, _)| elem.id == article_id)
+            {
+                *quantity -= 1;
+            }
+            entries.retain(|(_, quantity)| *quantity > 0);
+        });
+    }
+
+    pub fn total(&self) -> Money {
+        self.entries
+            .get()
+            .iter()
+            .map(|(article, quantity)| article.cost.value * *quantity as i64)
+            .sum::<i64>()
+            .into()
+    }
+
+    /// Adds the article to the basket if it is enabled, buys it otherwise.
+    pub fn add_or_buy(&self, article: Article, args: &MoneyArgs) {
+        if self.enabled.get_untracked() {
+            self.add(article);
+            return;
+        }
+
+        buy_article(
+            args.user_id,
+            article.id,
+            args.money,
+            args.error,
+            args.transactions,
+            args.audio_ref,
+        );
+    }
+}
+
+impl Default for Basket {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn checkout(basket: Basket, args: Rc<MoneyArgs>) {
+    let entries = basket.entries.get_untracked();
+
+    let items = entries
+        .iter()
+        .map(|(article, quantity)| BasketItem {
+            article_id: article.id,
+            quantity: *quantity,
+        })
+        .collect::<Vec<_>>();
+
+    spawn_local(async move {
+        match checkout_basket(args.user_id, items).await {
+            Ok(transactions) => {
+                let cost = transactions
+                    .iter()
+                    .map(|transaction| transaction.money.value)
+                    .sum::<i64>();
+
+                args.money.update(|money| money.value -= cost);
+                args.transactions.update(|trns| {
+                    for transaction in transactions.into_iter() {
+                        trns.insert(0, transaction);
+                    }
+                });
+                args.error.set(String::new());
+                basket.entries.set(Vec::new());
+                basket.enabled.set(false);
+
+                if let Some((article, _)) = entries.last() {
+                    play_sound(args.clone(), AudioPlayback::Bought(article.id));
+                }
+            }
+
+            Err(e) => {
+                args.error.set(format!("Failed to check out: {e}"));
+                play_sound(args.clone(), AudioPlayback::Failed);
+            }
+        }
+    });
+}
+
+#[component]
+pub fn ShowBasket(args: Rc<MoneyArgs>, basket: Basket) -> impl IntoView {
+    let args = (*args).clone();
+    view! {
+        <div class="flex flex-col items-center gap-2 p-2 text-white">
+            <button
+                class="flex items-center gap-2 rounded p-2"
+                class=("bg-emerald-600", move || basket.enabled.get())
+                class=("bg-gray-700", move || !basket.enabled.get())
+                on:click=move |_| basket.enabled.update(|enabled| *enabled = !*enabled)
+            >
+                <ArticleBasketIcon class="inline bg-white rounded p-1" />
+                {move || match basket.enabled.get() {
+                    true => "Basket (adding articles)",
+                    false => "Basket",
+                }}
+            </button>
+            {move || {
+                let entries = basket.entries.get();
+                if entries.is_empty() {
+                    return ().into_any();
+                }
+                let args = args.clone();
+
+                view! {
+                    <table class="text-[1.25em]">
+                        {entries
+                            .into_iter()
+                            .map(|(article, quantity)| {
+                                let id = article.id;
+                                let line_total: Money = (article.cost.value * quantity as i64)
+                                    .into();
+                                let add_article = article.clone();
+
+                                view! {
+                                    <tr>
+                                        <td class="px-3">{article.name.clone()}</td>
+                                        <td class="px-1">
+                                            <button
+                                                class="bg-red-400 rounded-full px-3"
+                                                on:click=move |_| basket.remove(id)
+                                            >
+                                                "-"
+                                            </button>
+                                        </td>
+                                        <td class="px-1 text-center">{quantity}</td>
+                                        <td class="px-1">
+                                            <button
+                                                class="bg-emerald-600 rounded-full px-3"
+                                                on:click=move |_| basket.add(add_article.clone())
+                                            >
+                                                "+"
+                                            </button>
+                                        </td>
+                                        <td class="px-3 text-right">{line_total.format_eur()}</td>
+                                    </tr>
+                                }
+                            })
+                            .collect_view()}
+                        <tr>
+                            <td class="px-3 pt-2" colspan="4">
+                                "Total"
+                            </td>
+                            <td class="px-3 pt-2 text-right">
+                                {move || basket.total().format_eur()}
+                            </td>
+                        </tr>
+                    </table>
+                    <div class="flex gap-3">
+                        <button
+                            class="bg-gray-700 rounded p-2"
+                            on:click=move |_| basket.entries.set(Vec::new())
+                        >
+                            "Clear"
+                        </button>
+                        <button
+                            class="bg-emerald-600 rounded p-2"
+                            on:click=move |_| checkout(basket, Rc::new(args.clone()))
+                        >
+                            "Checkout"
+                        </button>
+                    </div>
+                }
+                    .into_any()
+            }}
+        </div>
+    }
+}
@@ -3,13 +3,20 @@ use std::rc::Rc;
 use leptos::{ev, html, leptos_dom::logging::console_log, prelude::*, task::spawn_local};
 
 use crate::{
-    models::{play_sound, Article, Money, Transaction, UserId},
-    routes::{articles::get_all_articles, user::MoneyArgs},
+    models::{new_idempotency_key, play_sound, Article, Money, Transaction, UserId},
+    routes::{articles::get_all_articles, components::tag_filter::TagFilter, user::MoneyArgs},
 };
 
+use super::basket::Basket;
+
 #[cfg(feature = "ssr")]
 use {
-    crate::{backend::db::DBGROUP_SNACKBAR_ID, models::Group, routes::articles::get_article},
+    crate::{
+        backend::db::DBGROUP_SNACKBAR_ID,
+        models::{BookOnceError, Group, IdempotencyKey},
+        routes::articles::get_article,
+        routes::user::{get_user, is_unlocked},
+    },
     tracing::error,
 };
 
@@ -39,6 +46,7 @@ pub async fn get_articles_per_user(user_id: UserId) -> Result<Vec<Article>, Serv
 pub async fn buy_article_by_id(
     user_id: UserId,
     article_id: i64,
+    idempotency_key: Option<String>,
 ) -> Result<Transaction, ServerFnError> {
     use crate::backend::ServerState;
     let state: ServerState = expect_context();
@@ -49,6 +57,24 @@ pub async fn buy_article_by_id(
 
     let article = get_article(article_id).await?;
 
+    if !article.is_active {
+        response_opts.set_status(StatusCode::BAD_REQUEST);
+        return Err(ServerFnError::new("The article is not sold anymore!"));
+    }
+
+    let user = match get_user(user_id).await? {
+        Some(user) => user,
+        None => {
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(ServerFnError::new("No such user exists!"));
+        }
+    };
+
+    if !is_unlocked(&user).await {
+        response_opts.set_status(StatusCode::UNAUTHORIZED);
+        return Err(ServerFnError::new("Please enter your PIN first!"));
+    }
+
     let db = state.db.lock().await;
     let mut db_trans = match db.get_conn_transaction().await {
         Ok(value) => value,
@@ -70,16 +96,41 @@ pub async fn buy_article_by_id(
         }
     };
 
-    let transaction_id = Transaction::create(
+    let transaction_id = match IdempotencyKey::book_once(
         &mut *db_trans,
-        user_group,
-        DBGROUP_SNACKBAR_ID,
-        crate::models::TransactionType::Bought(article_id),
-        Some(article.name.clone()),
-        article.cost,
-        &state.settings,
+        user_id,
+        idempotency_key.as_deref(),
+        &article_id,
+        state.settings.idempotency.window,
+        async |conn| {
+            Transaction::create(
+                conn,
+                user_group,
+                DBGROUP_SNACKBAR_ID,
+                crate::models::TransactionType::Bought(article_id),
+                Some(article.name.clone()),
+                article.cost,
+                &state.settings,
+            )
+            .await
+        },
     )
-    .await?;
+    .await
+    {
+        Ok(value) => value,
+        Err(BookOnceError::KeyReused) => {
+            response_opts.set_status(StatusCode::CONFLICT);
+            return Err(ServerFnError::new(
+                "The idempotency key was already used for another request!",
+            ));
+        }
+        Err(BookOnceError::Database(e)) => {
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            error!("Failed to look up or store idempotency key: {}", e);
+            return Err(ServerFnError::new("Failed to check idempotency key!"));
+        }
+        Err(BookOnceError::Booking(e)) => return Err(e.into()),
+    };
 
     let transaction = match Transaction::get(&mut *db_trans, transaction_id, user_id).await {
         Ok(Some(o)) => o,
@@ -90,6 +141,24 @@ pub async fn buy_article_by_id(
         }
     };
 
+    let mut transaction = transaction;
+
+    match Transaction::set_undo_deadlines(
+        &mut *db_trans,
+        user_id,
+        &state.settings.undo,
+        std::slice::from_mut(&mut transaction),
+    )
+    .await
+    {
+        Ok(_) => {}
+        Err(e) => {
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            error!("Failed to check undo policy: {}", e);
+            return Err(ServerFnError::new("Failed to check undo policy!"));
+        }
+    }
+
     match db_trans.commit().await {
         Ok(_) => {}
         Err(e) => {
@@ -119,7 +188,7 @@ pub fn buy_article(
         audio_ref,
     };
     spawn_local(async move {
-        match buy_article_by_id(user_id, article_id).await {
+        match buy_article_by_id(user_id, article_id, Some(new_idempotency_key())).await {
             Ok(transaction) => {
                 money.update(|money| money.value -= transaction.money.value);
                 transactions.update(|trns| trns.insert(0, transaction));
@@ -138,90 +207,85 @@ pub fn buy_article(
 }
 
 #[component]
-pub fn BuyArticle(args: Rc<MoneyArgs>) -> impl IntoView {
+pub fn BuyArticle(args: Rc<MoneyArgs>, basket: Basket) -> impl IntoView {
     let m_clone = args.clone();
-    let MoneyArgs {
-        user_id,
-        money,
-        error,
-        transactions,
-        audio_ref,
-    } = *args;
+    let user_id = args.user_id;
+    let money_args = (*args).clone();
     let personal_articles = OnceResource::new(get_articles_per_user(user_id));
+    let selected_tag = RwSignal::new(None::<String>);
     view! {
         <div>
             <Suspense fallback=move || {
                 view! { <p class="text-center text-white">"Loading Articles"</p> }
             }>
-                <div class="grid grid-cols-3 text-white text-center gap-2 text-[1.25em] p-2 pt-4">
-                    {move || {
-                        personal_articles
-                            .get()
-                            .map(|article| {
-                                let article = match article {
-                                    Ok(value) => {
-                                        value
-                                            .into_iter()
-                                            .take(Article::DEFAULT_ARTICLE_AMOUNT)
-                                            .collect::<Vec<Article>>()
-                                    }
-                                    Err(e) => {
-                                        let msg = match e {
-                                            ServerFnError::ServerError(msg) => msg,
-                                            _ => e.to_string(),
-                                        };
-                                        return view! {
-                                            <p class="bg-red-400 text-white text-center">
-                                                {format!("Failed to fetch articles: {}", msg)}
-                                            </p>
-                                        }
-                                            .into_any();
+                {move || {
+                    personal_articles
+                        .get()
+                        .map(|articles| {
+                            let articles = match articles {
+                                Ok(value) => value,
+                                Err(e) => {
+                                    let msg = match e {
+                                        ServerFnError::ServerError(msg) => msg,
+                                        _ => e.to_string(),
+                                    };
+                                    return view! {
+                                        <p class="bg-red-400 text-white text-center">
+                                            {format!("Failed to fetch articles: {}", msg)}
+                                        </p>
                                     }
-                                };
-                                article
-                                    .into_iter()
-                                    .map(|article| {
-                                        let Article { id, name, cost, sounds: _, barcodes: _ } = article;
-
-                                        view! {
-                                            <button
-                                                class="bg-gray-700 rounded p-2"
-                                                on:click=move |_| {
-                                                    buy_article(
-                                                        user_id,
-                                                        id,
-                                                        money,
-                                                        error,
-                                                        transactions,
-                                                        audio_ref,
-                                                    );
+                                        .into_any();
+                                }
+                            };
+                            let tags = Article::tag_names(&articles);
+                            let money_args = money_args.clone();
+
+                            view! {
+                                <TagFilter tags selected=selected_tag />
+                                <div class="grid grid-cols-3 text-white text-center gap-2 text-[1.25em] p-2 pt-4">
+                                    {move || {
+                                        let selected = selected_tag.get();
+                                        articles
+                                            .iter()
+                                            .filter(|article| match &selected {
+                                                Some(tag) => article.has_tag(tag),
+                                                None => true,
+                                            })
+                                            .take(Article::DEFAULT_ARTICLE_AMOUNT)
+                                            .cloned()
+                                            .map(|article| {
+                                                let name = article.name.clone();
+                                                let cost = article.cost;
+                                                let money_args = money_args.clone();
+
+                                                view! {
+                                                    <button
+                                                        class="bg-gray-700 rounded p-2"
+                                                        on:click=move |_| {
+                                                            basket.add_or_buy(article.clone(), &money_args);
+                                                        }
+                                                    >
+                                                        <div>{name}" | "{cost.format_eur()}</div>
+                                                    </button>
                                                 }
-                                            >
-                                                <div>{name}" | "{cost.format_eur()}</div>
-                                            </button>
-                                        }
-                                    })
-                                    .collect_view()
-                                    .into_any()
-                            })
-                    }}
-                </div>
+                                            })
+                                            .collect_view()
+                                    }}
+                                </div>
+                            }
+                                .into_any()
+                        })
+                }}
             </Suspense>
-            <ArticleSearch money_args=m_clone.clone() />
+            <ArticleSearch money_args=m_clone.clone() basket />
         </div>
     }
 }
 
 #[component]
-pub fn ArticleSearch(money_args: Rc<MoneyArgs>) -> impl IntoView {
-    let MoneyArgs {
-        user_id,
-        money,
-        error,
-        transactions,
-        audio_ref,
-    } = *money_args;
-    let articles_resource = OnceResource::new(get_all_articles(None));
+pub fn ArticleSearch(money_args: Rc<MoneyArgs>, basket: Basket) -> impl IntoView {
+    let money_args = (*money_args).clone();
+    let articles_resource = OnceResource::new(get_all_articles(None, false));
 
     let dropdown_div = NodeRef::<html::Div>::new();
     let search_term = RwSignal::new(String::new());
@@ -309,20 +373,17 @@ pub fn ArticleSearch(money_args: Rc<MoneyArgs>) -> impl IntoView {
                         .get()
                         .into_iter()
                         .map(|elem| {
+                            let money_args = money_args.clone();
+                            let name = elem.name.clone();
+                            let cost = elem.cost;
+
                             view! {
                                 <button on:click=move |_| {
-                                    buy_article(
-                                        user_id,
-                                        elem.id,
-                                        money,
-                                        error,
-                                        transactions,
-                                        audio_ref,
-                                    );
+                                    basket.add_or_buy(elem.clone(), &money_args);
                                     search_term.set(String::new());
                                 }>
                                     <div class="p-2 m-2 rounded text-white bg-gray-700">
-                                        <p>{elem.name.clone()}" | "{elem.cost.format_eur()}</p>
+                                        <p>{name}" | "{cost.format_eur()}</p>
                                     </div>
                                 </button>
                             }
@@ -1,4 +1,7 @@
+pub mod basket;
 pub mod buy_article;
 pub mod icons;
+pub mod money_requests;
 pub mod scan_input;
+pub mod top_up_qr;
 pub mod transaction_view;
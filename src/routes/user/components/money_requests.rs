@@ -0,0 +1,324 @@
+use std::rc::Rc;
+
+use leptos::{prelude::*, task::spawn_local};
+
+use crate::{
+    models::{MoneyRequest, MoneyRequestStatus, Transaction, UserId},
+    routes::user::{CreateTransactionError, MoneyArgs},
+};
+
+#[cfg(feature = "ssr")]
+use {
+    crate::models::MoneyRequestDB,
+    crate::routes::user::{get_user, is_unlocked},
+    tracing::error,
+};
+
+/// How many of the latest answered or expired requests are shown on the user
+/// page, open requests are always shown.
+pub const MONEY_REQUESTS_SHOWN: u64 = 10;
+
+#[server]
+pub async fn get_money_requests(user_id: UserId) -> Result<Vec<MoneyRequest>, ServerFnError> {
+    use crate::backend::ServerState;
+    let state: ServerState = expect_context();
+    use axum::http::StatusCode;
+    use leptos_axum::ResponseOptions;
+
+    let response_opts: ResponseOptions = expect_context();
+
+    let db = state.db.lock().await;
+
+    let mut conn = match db.get_conn().await {
+        Ok(value) => value,
+        Err(e) => {
+            error!("Failed to get db handle: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(ServerFnError::new("Failed to get database!"));
+        }
+    };
+
+    match MoneyRequest::get_for_user(&mut *conn, user_id, MONEY_REQUESTS_SHOWN).await {
+        Ok(value) => Ok(value),
+        Err(e) => {
+            error!("Failed to fetch money requests: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            Err(ServerFnError::new("Failed to fetch money requests!"))
+        }
+    }
+}
+
+/// Pays a request the user received.
+#[server]
+pub async fn accept_money_request(
+    user_id: UserId,
+    request_id: i64,
+) -> Result<Transaction, CreateTransactionError> {
+    type Error = CreateTransactionError;
+    use crate::backend::ServerState;
+    use axum::http::StatusCode;
+    use leptos_axum::ResponseOptions;
+
+    let state: ServerState = expect_context();
+
+    let response_opts: ResponseOptions = expect_context();
+
+    let user = match get_user(user_id).await? {
+        Some(user) => user,
+        None => {
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(Error::new("No such user exists!"));
+        }
+    };
+
+    if !is_unlocked(&user).await {
+        response_opts.set_status(StatusCode::UNAUTHORIZED);
+        return Err(Error::PinRequiredError);
+    }
+
+    let db = state.db.lock().await;
+    let mut db_trans = match db.get_conn_transaction().await {
+        Ok(value) => value,
+        Err(e) => {
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            error!("Failed to get database handle: {}", e);
+            return Err(Error::new("Failed to get database handle!"));
+        }
+    };
+
+    let request = match MoneyRequest::get(&mut *db_trans, request_id).await? {
+        Some(value) if value.debtor == user_id => value,
+        _ => {
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(Error::new("Invalid money request!"));
+        }
+    };
+
+    let transaction_id = match request.accept(&mut *db_trans, &state.settings).await {
+        Ok(value) => value,
+        Err(e) => {
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(e);
+        }
+    };
+
+    let mut transaction = match Transaction::get(&mut *db_trans, transaction_id, user_id).await {
+        Ok(Some(value)) => value,
+        _ => {
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            error!("Failed to read back db_transaction");
+            return Err(Error::new("Failed to find transaction!"));
+        }
+    };
+
+    if let Err(e) = Transaction::set_undo_deadlines(
+        &mut *db_trans,
+        user_id,
+        &state.settings.undo,
+        std::slice::from_mut(&mut transaction),
+    )
+    .await
+    {
+        response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+        error!("Failed to check undo policy: {}", e);
+        return Err(Error::new("Failed to check undo policy!"));
+    }
+
+    if let Err(e) = db_trans.commit().await {
+        response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+        error!("Failed to commit transaction: {}", e);
+        return Err(Error::new("Failed to commit transaction!"));
+    }
+
+    Ok(transaction)
+}
+
+#[server]
+pub async fn decline_money_request(user_id: UserId, request_id: i64) -> Result<(), ServerFnError> {
+    use crate::backend::ServerState;
+    let state: ServerState = expect_context();
+    use axum::http::StatusCode;
+    use leptos_axum::ResponseOptions;
+
+    let response_opts: ResponseOptions = expect_context();
+
+    let user = match get_user(user_id).await? {
+        Some(user) => user,
+        None => {
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(ServerFnError::new("No such user exists!"));
+        }
+    };
+
+    if !is_unlocked(&user).await {
+        response_opts.set_status(StatusCode::UNAUTHORIZED);
+        return Err(ServerFnError::new("Please enter your PIN first!"));
+    }
+
+    let db = state.db.lock().await;
+
+    let mut conn = match db.get_conn().await {
+        Ok(value) => value,
+        Err(e) => {
+            error!("Failed to get db handle: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(ServerFnError::new("Failed to get database!"));
+        }
+    };
+
+    let request = match MoneyRequest::get(&mut *conn, request_id).await {
+        Ok(Some(value)) if value.debtor == user_id => value,
+        Ok(_) => {
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(ServerFnError::new("Invalid money request!"));
+        }
+        Err(e) => {
+            error!("Failed to fetch money request: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(ServerFnError::new("Failed to fetch money request!"));
+        }
+    };
+
+    // expired requests are still stored as pending
+    if request.status != MoneyRequestStatus::Pending {
+        response_opts.set_status(StatusCode::BAD_REQUEST);
+        return Err(ServerFnError::new(format!(
+            "The request is {}!",
+            request.status.as_str()
+        )));
+    }
+
+    match MoneyRequestDB::resolve(&mut *conn, request_id, MoneyRequestStatus::Declined, None).await
+    {
+        Ok(true) => Ok(()),
+        Ok(false) => {
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            Err(ServerFnError::new("The request was already answered!"))
+        }
+        Err(e) => {
+            error!("Failed to decline money request: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            Err(ServerFnError::new("Failed to decline money request!"))
+        }
+    }
+}
+
+fn accept(request_id: i64, args: Rc<MoneyArgs>, version: RwSignal<u32>) {
+    spawn_local(async move {
+        match accept_money_request(args.user_id, request_id).await {
+            Ok(transaction) => {
+                args.money
+                    .update(|money| money.value -= transaction.money.value);
+                args.transactions
+                    .update(|transactions| transactions.insert(0, transaction));
+                args.error.set(String::new());
+            }
+            Err(e) => args.error.set(format!("Failed to pay the request: {e}")),
+        }
+        version.update(|value| *value += 1);
+    });
+}
+
+fn decline(request_id: i64, args: Rc<MoneyArgs>, version: RwSignal<u32>) {
+    spawn_local(async move {
+        match decline_money_request(args.user_id, request_id).await {
+            Ok(_) => args.error.set(String::new()),
+            Err(e) => args
+                .error
+                .set(format!("Failed to decline the request: {e}")),
+        }
+        version.update(|value| *value += 1);
+    });
+}
+
+#[component]
+pub fn MoneyRequests(args: Rc<MoneyArgs>) -> impl IntoView {
+    let user_id = args.user_id;
+    let args = (*args).clone();
+    let version = RwSignal::new(0_u32);
+
+    let requests_resource =
+        Resource::new(move || version.get(), move |_| get_money_requests(user_id));
+
+    view! {
+        <Suspense>
+            {move || {
+                let requests = match requests_resource.get() {
+                    Some(Ok(value)) if !value.is_empty() => value,
+                    _ => return ().into_any(),
+                };
+                let args = args.clone();
+
+                view! {
+                    <div class="flex flex-col items-center p-2 text-white">
+                        <p class="text-[1.25em]">"Money requests"</p>
+                        <table>
+                            {requests
+                                .into_iter()
+                                .map(|request| {
+                                    let is_incoming = request.debtor == user_id;
+                                    let is_pending = request.status == MoneyRequestStatus::Pending;
+                                    let (accept_args, decline_args) = (
+                                        Rc::new(args.clone()),
+                                        Rc::new(args.clone()),
+                                    );
+                                    let id = request.id;
+
+                                    view! {
+                                        <tr>
+                                            <td class="px-2">
+                                                {match is_incoming {
+                                                    true => format!("{} requests", request.requester_name),
+                                                    false => format!("You requested from {}", request.debtor_name),
+                                                }}
+                                            </td>
+                                            <td class="px-2">{request.money.format_eur()}</td>
+                                            <td class="px-2">{request.description.clone()}</td>
+                                            <td class="px-2">
+                                                {match (is_incoming, is_pending) {
+                                                    (true, true) => {
+                                                        view! {
+                                                            <button
+                                                                class="bg-emerald-600 rounded p-1 mr-1"
+                                                                on:click=move |_| accept(id, accept_args.clone(), version)
+                                                            >
+                                                                "Pay"
+                                                            </button>
+                                                            <button
+                                                                class="bg-red-400 rounded p-1"
+                                                                on:click=move |_| decline(id, decline_args.clone(), version)
+                                                            >
+                                                                "Decline"
+                                                            </button>
+                                                        }
+                                                            .into_any()
+                                                    }
+                                                    _ => {
+                                                        view! {
+                                                            <p>
+                                                                {request.status.as_str()}
+                                                                {is_pending
+                                                                    .then(|| {
+                                                                        format!(
+                                                                            " until {}",
+                                                                            request.expires_at.format("%d.%m.%Y"),
+                                                                        )
+                                                                    })}
+                                                            </p>
+                                                        }
+                                                            .into_any()
+                                                    }
+                                                }}
+                                            </td>
+                                        </tr>
+                                    }
+                                })
+                                .collect_view()}
+                        </table>
+                    </div>
+                }
+                    .into_any()
+            }}
+        </Suspense>
+    }
+}
@@ -5,12 +5,13 @@ use leptos::{ev, leptos_dom::logging::console_log, prelude::*, task::spawn_local
 
 use crate::routes::{articles::get_article_by_barcode, user::MoneyArgs};
 
-use super::buy_article::buy_article;
+use super::basket::Basket;
 
 pub fn invisible_scan_input(
     is_focused_signal: RwSignal<bool>,
     error_signal: RwSignal<String>,
     money_args: Rc<MoneyArgs>,
+    basket: Basket,
 ) -> impl IntoView {
     let input_signal = RwSignal::new(String::new());
     let last_input = RwSignal::new(Utc::now());
@@ -55,14 +56,7 @@ pub fn invisible_scan_input(
 
                     Some(value) => {
                         console_log(&format!("Need to buy article: {}", value.name));
-                        buy_article(
-                            money_args_clone.user_id,
-                            value.id,
-                            money_args_clone.money,
-                            money_args_clone.error,
-                            money_args_clone.transactions,
-                            money_args_clone.audio_ref,
-                        );
+                        basket.add_or_buy(value, &money_args_clone);
                     }
                 }
             });
@@ -0,0 +1,88 @@
+use leptos::prelude::*;
+
+use crate::models::UserId;
+
+#[cfg(feature = "ssr")]
+use {
+    crate::models::{epc_payload, epc_qr_svg, payment_reference, Money},
+    tracing::error,
+};
+
+/// The QR code as SVG, or nothing while no club account is configured.
+#[server]
+pub async fn get_top_up_qr_code(
+    user_id: UserId,
+    amount: String,
+) -> Result<Option<String>, ServerFnError> {
+    use crate::backend::ServerState;
+    let state: ServerState = expect_context();
+    use axum::http::StatusCode;
+    use leptos_axum::ResponseOptions;
+
+    let response_opts: ResponseOptions = expect_context();
+
+    let bank = &state.settings.bank;
+
+    if bank.iban.trim().is_empty() {
+        return Ok(None);
+    }
+
+    let money = match amount.trim() {
+        "" => None,
+        amount => match Money::try_from(amount.to_string()) {
+            Ok(value) if value.value > 0 && value.value <= 99_999_999_999 => Some(value),
+            _ => {
+                response_opts.set_status(StatusCode::BAD_REQUEST);
+                return Err(ServerFnError::new(format!("Invalid amount '{amount}'")));
+            }
+        },
+    };
+
+    let payload = epc_payload(bank, money, &payment_reference(user_id));
+
+    match epc_qr_svg(&payload) {
+        Ok(value) => Ok(Some(value)),
+        Err(e) => {
+            error!("{e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            Err(ServerFnError::new("Failed to create QR code!"))
+        }
+    }
+}
+
+#[component]
+pub fn TopUpQrCode(user_id: UserId) -> impl IntoView {
+    let amount = RwSignal::new(String::from("20.00"));
+
+    let qr_resource = Resource::new(
+        move || amount.get(),
+        move |amount| get_top_up_qr_code(user_id, amount),
+    );
+
+    view! {
+        <Suspense>
+            {move || {
+                let svg = match qr_resource.get() {
+                    Some(Ok(Some(value))) => value,
+                    Some(Err(e)) => {
+                        return view! { <p class="text-red-400 text-center">{e.to_string()}</p> }
+                            .into_any();
+                    }
+                    _ => return ().into_any(),
+                };
+
+                view! {
+                    <div class="flex flex-col items-center gap-2 pt-3 text-white">
+                        <p>"Top up by bank transfer"</p>
+                        <div class="bg-white p-2 rounded" inner_html=svg />
+                    </div>
+                }
+                    .into_any()
+            }}
+        </Suspense>
+        <div class="flex justify-center items-center gap-2 pt-2 text-white">
+            <label>"Amount"</label>
+            <input class="text-black rounded p-1 w-24" bind:value=amount placeholder="Euros" />
+        </div>
+    }
+}
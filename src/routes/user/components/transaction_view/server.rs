@@ -1,14 +1,14 @@
 use leptos::prelude::*;
 
-use crate::models::{Page, PageRequestParams, Transaction, UserId};
+use crate::models::{Page, PageRequestParams, Transaction, TransactionFilter, UserId};
 
 #[cfg(feature = "ssr")]
 use {
     crate::{
-        models::{Group, GroupId, Money, TransactionDB, User},
-        routes::user::get_user,
+        models::{Group, GroupId, TransactionDB},
+        routes::user::{get_user, is_unlocked},
     },
-    tracing::{debug, error, trace, warn},
+    tracing::{debug, error, warn},
 };
 
 #[server]
@@ -50,6 +50,7 @@ pub async fn get_group_members(gid: i64) -> Result<Vec<String>, ServerFnError> {
 pub async fn get_user_transactions(
     user_id: UserId,
     page_request_params: PageRequestParams,
+    #[server(default)] filter: TransactionFilter,
 ) -> Result<Page<Transaction>, ServerFnError> {
     use crate::backend::ServerState;
     let state: ServerState = expect_context();
@@ -57,10 +58,13 @@ pub async fn get_user_transactions(
     use leptos_axum::ResponseOptions;
     let response_opts: ResponseOptions = expect_context();
 
-    let transactions = match Transaction::get_user_transactions(
-        &*state.db.lock().await,
+    let db = state.db.lock().await;
+
+    let mut transactions = match Transaction::get_user_transactions(
+        &db,
         user_id,
         page_request_params,
+        &filter,
     )
     .await
     {
@@ -72,11 +76,40 @@ pub async fn get_user_transactions(
         }
     };
 
+    let mut conn = match db.get_conn().await {
+        Ok(val) => val,
+        Err(e) => {
+            error!("Failed to get db handle: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(ServerFnError::new("Failed to get database!"));
+        }
+    };
+
+    match Transaction::set_undo_deadlines(
+        &mut *conn,
+        user_id,
+        &state.settings.undo,
+        &mut transactions.items,
+    )
+    .await
+    {
+        Ok(_) => {}
+        Err(e) => {
+            error!("Failed to check undo policy: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(ServerFnError::new("Failed to fetch transactions!"));
+        }
+    }
+
     Ok(transactions)
 }
 
 #[server]
-pub async fn undo_transaction(user_id: UserId, transaction_id: i64) -> Result<(), ServerFnError> {
+pub async fn undo_transaction(
+    user_id: UserId,
+    transaction_id: i64,
+    undo_reason: String,
+) -> Result<Transaction, ServerFnError> {
     use crate::backend::ServerState;
     let state: ServerState = expect_context();
     use axum::http::StatusCode;
@@ -100,6 +133,11 @@ pub async fn undo_transaction(user_id: UserId, transaction_id: i64) -> Result<()
         }
     };
 
+    if !is_unlocked(&user).await {
+        response_opts.set_status(StatusCode::UNAUTHORIZED);
+        return Err(ServerFnError::new("Please enter your PIN first!"));
+    }
+
     let db = state.db.lock().await;
 
     let mut db_trns = match db.get_conn_transaction().await {
@@ -131,199 +169,81 @@ pub async fn undo_transaction(user_id: UserId, transaction_id: i64) -> Result<()
         Some(value) => value,
     };
 
-    if transaction_db.is_undone {
-        warn!("Attempting to undo a transaction that is already undone!");
-        response_opts.set_status(StatusCode::BAD_REQUEST);
-        return Err(ServerFnError::new("The transaction is already undone!"));
-    }
-
-    let (receiver_group, sender_group) = (
-        match Group::get(&mut *db_trns, GroupId(transaction_db.receiver)).await {
-            Ok(val) => val,
-            Err(e) => {
-                warn!(
-                    "Failed to find receiver group with id '{}': {}",
-                    transaction_db.receiver, e
-                );
-                response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
-                return Err(ServerFnError::new("Failed to find group!"));
-            }
-        },
-        match Group::get(&mut *db_trns, GroupId(transaction_db.sender)).await {
-            Ok(val) => val,
-            Err(e) => {
-                warn!(
-                    "Failed to find sender group with id '{}': {}",
-                    transaction_db.sender, e
-                );
-                response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
-                return Err(ServerFnError::new("Failed to find group!"));
-            }
-        },
-    );
-
-    let money_shares_sender = transaction_db.money / sender_group.members.len() as u64;
-
-    let mut full_money = transaction_db.money;
-    trace!("full_money: {}", full_money);
-
-    let mut users_sender = Vec::<User>::new();
-    let mut users_receiver = Vec::<User>::new();
-
-    for user_db in sender_group.members.iter() {
-        let user = match User::get(&mut *db_trns, UserId(user_db.id)).await {
-            Ok(val) => val,
-            Err(e) => {
-                error!("Failed to lookup user: '{}'", e);
-                response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
-                return Err(ServerFnError::new("Failed to find user"));
-            }
-        };
-
-        let user = match user {
-            Some(val) => val,
-            None => {
-                error!("Failed to find user with id '{}'", user_db.id);
-                response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
-                return Err(ServerFnError::new("Failed to find user"));
-            }
-        };
-
-        users_sender.push(user);
-    }
-
-    for user_db in receiver_group.members.iter() {
-        let user = match User::get(&mut *db_trns, UserId(user_db.id)).await {
-            Ok(val) => val,
-            Err(e) => {
-                error!("Failed to lookup user: '{}'", e);
-                response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
-                return Err(ServerFnError::new("Failed to find user"));
-            }
-        };
-
-        let user = match user {
-            Some(val) => val,
-            None => {
-                error!("Failed to find user with id '{}'", user_db.id);
-                response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
-                return Err(ServerFnError::new("Failed to find user"));
-            }
-        };
-
-        users_receiver.push(user);
-    }
+    // also checks that the transaction belongs to the user
+    let transaction = match Transaction::get(&mut *db_trns, transaction_id, user.id).await {
+        Ok(value) => value,
+        Err(e) => {
+            warn!("Failed to get transaction for user '{}': {}", user.id, e);
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(ServerFnError::new("Invalid transaction!"));
+        }
+    };
 
-    for user_sender in users_sender.iter_mut() {
-        match user_sender
-            .add_money(
-                &mut *db_trns,
-                Money {
-                    value: money_shares_sender as i64,
-                },
-            )
-            .await
-        {
-            Ok(val) => val,
-            Err(e) => {
-                error!(
-                    "Failed to add money to user '{}': {}",
-                    user_sender.nickname, e
-                );
-                response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
-                return Err(ServerFnError::new("Failed to subtract money"));
-            }
+    let transaction = match transaction {
+        Some(value) => value,
+        None => {
+            warn!("A transaction with id '{}' does not exist!", transaction_id);
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(ServerFnError::new("Invalid transaction!"));
         }
+    };
 
-        full_money -= money_shares_sender;
-        trace!("full_money: {}", full_money);
-    }
+    let undo_settings = &state.settings.undo;
 
-    while full_money > 0 {
-        for user_sender in users_sender.iter_mut() {
-            match user_sender
-                .add_money(&mut *db_trns, Money { value: 1 })
+    let recent_ids = match undo_settings.last_n {
+        0 => Vec::new(),
+        last_n => {
+            match TransactionDB::get_latest_user_transaction_ids(&mut *db_trns, user.id, last_n)
                 .await
             {
-                Ok(val) => val,
+                Ok(value) => value,
                 Err(e) => {
-                    error!(
-                        "Failed to add money to user (fine-grained) '{}': {}",
-                        user.nickname, e
-                    );
+                    error!("Failed to fetch latest transactions: {}", e);
                     response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
-                    return Err(ServerFnError::new("Failed to subtract money"));
+                    return Err(ServerFnError::new("Failed to fetch transactions!"));
                 }
-            };
-
-            trace!("full_money: {}", full_money);
-            if full_money == 0 {
-                break;
-            }
-
-            full_money -= 1;
-        }
-    }
-
-    let restore_money_share = transaction_db.money / users_receiver.len() as u64;
-
-    for user_receiver in users_receiver.iter_mut() {
-        match user_receiver
-            .add_money(
-                &mut *db_trns,
-                Money {
-                    value: -(restore_money_share as i64),
-                },
-            )
-            .await
-        {
-            Ok(val) => val,
-            Err(e) => {
-                error!(
-                    "Failed to remove money from user '{}': {}",
-                    user_receiver.nickname, e
-                );
-                response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
-                return Err(ServerFnError::new("Failed to subtract money"));
             }
         }
+    };
 
-        full_money += restore_money_share;
+    if let Err(msg) = transaction.check_undoable(undo_settings, &recent_ids) {
+        warn!("Refusing to undo transaction '{}': {}", transaction_id, msg);
+        response_opts.set_status(StatusCode::BAD_REQUEST);
+        return Err(ServerFnError::new(msg));
     }
 
-    while full_money < transaction_db.money {
-        for user_receiver in users_receiver.iter_mut() {
-            match user_receiver
-                .add_money(&mut *db_trns, Money { value: -1 })
-                .await
-            {
-                Ok(val) => val,
-                Err(e) => {
-                    error!(
-                        "Failed to remove money from user (fine-grained) '{}': {}",
-                        user.nickname, e
-                    );
-                    response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
-                    return Err(ServerFnError::new("Failed to subtract money"));
-                }
-            };
-
-            if full_money == transaction_db.money {
-                break;
-            }
+    let undo_reason = match undo_reason.trim().len() {
+        0 => None,
+        _ => Some(undo_reason.trim().to_string()),
+    };
 
-            full_money += 1;
+    let reversal_id = match Transaction::undo(
+        &mut *db_trns,
+        &transaction_db,
+        user.id,
+        undo_reason,
+        &state.settings,
+    )
+    .await
+    {
+        Ok(value) => value,
+        Err(e) => {
+            warn!("Failed to undo transaction '{}': {}", transaction_id, e);
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(ServerFnError::new(format!(
+                "Failed to undo transaction: {e}"
+            )));
         }
-    }
+    };
 
-    match TransactionDB::set_undone(&mut *db_trns, transaction_db.id, true).await {
-        Ok(_) => {}
-        Err(e) => {
-            error!("Failed to set transaction to undone: {}", e);
+    let reversal = match Transaction::get(&mut *db_trns, reversal_id, user.id).await {
+        Ok(Some(value)) => value,
+        _ => {
+            error!("Failed to read back reversal '{}'", reversal_id);
             response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
-            return Err(ServerFnError::new("Failed to update transaction!"));
+            return Err(ServerFnError::new("Failed to read back the undo!"));
         }
-    }
+    };
 
     match db_trns.commit().await {
         Ok(_) => {}
@@ -334,5 +254,5 @@ pub async fn undo_transaction(user_id: UserId, transaction_id: i64) -> Result<()
         }
     }
 
-    Ok(())
+    Ok(reversal)
 }
@@ -1,12 +1,15 @@
 use std::rc::Rc;
 
-use chrono::{DateTime, Local, Utc};
+use chrono::{Local, Utc};
 use leptos::{leptos_dom::logging::console_log, prelude::*};
 use leptos_router::hooks::use_params_map;
 use leptos_use::{use_infinite_scroll_with_options, UseInfiniteScrollOptions};
 
 use crate::{
-    models::{Money, PageRequestParams, PageResponseParams, Transaction, TransactionType, UserId},
+    models::{
+        Money, PageRequestParams, PageResponseParams, Transaction, TransactionFilter,
+        TransactionType, UserId,
+    },
     routes::user::components::{
         icons::{ArticleBasketIcon, LeftArrowIcon, RightArrowIcon},
         transaction_view::{get_group_members, server::get_user_transactions, UndoTransaction},
@@ -35,8 +38,11 @@ pub fn ShowTransactions(arguments: Rc<MoneyArgs>) -> impl IntoView {
 
     let previous_transactions_presonse_params: RwSignal<Option<PageResponseParams>> =
         RwSignal::new(None);
-    let transaction_data =
-        OnceResource::new(get_user_transactions(user_id, PageRequestParams::new(100)));
+    let transaction_data = OnceResource::new(get_user_transactions(
+        user_id,
+        PageRequestParams::new(100),
+        TransactionFilter::default(),
+    ));
 
     let transaction_signal = arguments.transactions;
     let error_signal = arguments.error;
@@ -85,7 +91,12 @@ pub fn ShowTransactions(arguments: Rc<MoneyArgs>) -> impl IntoView {
                             let next_params = previous_transactions_presonse_params
                                 .with_untracked(|p| PageResponseParams::next_params(*p, 100));
                             if let Some(params) = next_params {
-                                let mut data = get_user_transactions(user_id, params).await;
+                                let mut data = get_user_transactions(
+                                    user_id,
+                                    params,
+                                    TransactionFilter::default(),
+                                )
+                                .await;
                                 match data {
                                     Ok(mut data) => {
                                         transaction_signal.update(|d| d.append(&mut data.items));
@@ -111,7 +122,13 @@ pub fn ShowTransactions(arguments: Rc<MoneyArgs>) -> impl IntoView {
                             )
                             let:child
                         >
-                            {format_transaction(&child, user_id, error_signal, money_signal)}
+                            {format_transaction(
+                                &child,
+                                user_id,
+                                error_signal,
+                                money_signal,
+                                transaction_signal,
+                            )}
                         </For>
 
                     </div>
@@ -129,9 +146,9 @@ pub fn format_transaction(
     user_id: UserId,
     error_write: RwSignal<String>,
     money_signal: RwSignal<Money>,
+    transactions: RwSignal<Vec<Transaction>>,
 ) -> impl IntoView {
-    let now: DateTime<Utc> = Utc::now();
-    let diff = now - transaction.timestamp;
+    let transaction_undoable_until = transaction.undoable_until;
 
     let undo_action = ServerAction::<UndoTransaction>::new();
     let transaction_id = transaction.id;
@@ -149,13 +166,32 @@ pub fn format_transaction(
     let money = match transaction.t_type {
         TransactionType::Deposit
         | TransactionType::Received(_)
-        | TransactionType::SentAndReceived(_) => transaction.money.value,
+        | TransactionType::SentAndReceived(_)
+        | TransactionType::Correction(true) => transaction.money.value,
 
-        TransactionType::Withdraw | TransactionType::Bought(_) | TransactionType::Sent(_) => {
-            -transaction.money.value
-        }
+        TransactionType::Withdraw
+        | TransactionType::Bought(_)
+        | TransactionType::Sent(_)
+        | TransactionType::Correction(false) => -transaction.money.value,
     };
 
+    if let Some(original_id) = transaction.reverses_id {
+        let reason = transaction
+            .undo_reason
+            .as_ref()
+            .map(|reason| format!(": {reason}"));
+
+        // a reversal has the same type as the original, but the opposite effect
+        return view! {
+            <div class="grid grid-cols-3 items-center border-t-4 border-gray-300 p-2 text-white">
+                <p class="text-gray-300">{Money::format_eur_diff_value(-money)}</p>
+                <p class="text-white">"Undo of #"{original_id}{reason}</p>
+                <p class="text-white">{date_string}</p>
+            </div>
+        }
+        .into_any();
+    }
+
     view! {
         <div
             class="grid grid-cols-3 items-center border-t-4 border-gray-300 p-2 text-white"
@@ -177,6 +213,18 @@ pub fn format_transaction(
                     }
                         .into_any()
                 }
+                TransactionType::Correction(_) => {
+                    let class = match money < 0 {
+                        true => "text-red-400",
+                        false => "text-green-500",
+                    };
+
+                    view! {
+                        <p class=class>{Money::format_eur_diff_value(money)}</p>
+                        <p class="text-white">"Balance correction"</p>
+                    }
+                        .into_any()
+                }
                 TransactionType::Bought(_) => {
 
                     view! {
@@ -184,12 +232,23 @@ pub fn format_transaction(
                         <p class="text-white">
                             <ArticleBasketIcon class="inline" />
                             " "
+                            {(transaction.quantity > 1)
+                                .then(|| format!("{}× ", transaction.quantity))}
                             {transaction.description.clone().unwrap_or("".to_string())}
                         </p>
                     }
                         .into_any()
                 }
-                TransactionType::SentAndReceived(_received_group) => todo!(),
+                TransactionType::SentAndReceived(_received_group) => {
+                    view! {
+                        <p class="text-gray-300">{transaction.money.format_eur()}</p>
+                        <p class="text-white">
+                            "Sent to self"
+                            {transaction.description.clone().map(|val| format!(": {val}"))}
+                        </p>
+                    }
+                        .into_any()
+                }
                 TransactionType::Received(group) | TransactionType::Sent(group) => {
                     let transaction = transaction.clone();
                     let group_members_resource = OnceResource::new(get_group_members(group.0));
@@ -269,23 +328,38 @@ pub fn format_transaction(
                 }
             }}
             {move || match undo_signal.get() {
-                true => view! { <p class="text-white">{date_string.clone()}</p> }.into_any(),
+                true => {
+                    view! {
+                        <p class="text-white">
+                            {date_string.clone()}" (#"{transaction_id}" undone)"
+                        </p>
+                    }
+                        .into_any()
+                }
                 false => {
-                    if diff.num_minutes() > 2 {
-
-                        // grace period for undoing transactions
-                        // if transaction is already undone, only show the date regardless of grace period
-                        view! { <p class="text-white">{date_string.clone()}</p> }
-                            .into_any()
-                    } else {
-                        view! {
-                            <ActionForm action=undo_action>
-                                <input type="hidden" name="user_id" value=user_id.0 />
-                                <input type="hidden" name="transaction_id" value=transaction_id />
-                                <input type="submit" class="text-white" value="Undo" />
-                            </ActionForm>
+                    match transaction_undoable_until {
+                        Some(until) if Utc::now() <= until => {
+                            view! {
+                                <ActionForm action=undo_action>
+                                    <input type="hidden" name="user_id" value=user_id.0 />
+                                    <input
+                                        type="hidden"
+                                        name="transaction_id"
+                                        value=transaction_id
+                                    />
+                                    <input
+                                        type="text"
+                                        class="text-black rounded w-[8rem]"
+                                        name="undo_reason"
+                                        placeholder="Reason"
+                                    />
+                                    <input type="submit" class="text-white" value="Undo" />
+                                </ActionForm>
+                            }
+                                .into_any()
                         }
-                            .into_any()
+                        // not eligible according to the undo policy
+                        _ => view! { <p class="text-white">{date_string.clone()}</p> }.into_any(),
                     }
                 }
             }}
@@ -293,9 +367,10 @@ pub fn format_transaction(
                 None => {}
                 Some(response) => {
                     match response {
-                        Ok(_) => {
+                        Ok(reversal) => {
                             undo_signal.set(true);
                             money_signal.update(|value| value.value -= money);
+                            transactions.update(|transactions| transactions.insert(0, reversal));
                             console_log("Set signal to true");
                             error_write.set(String::new());
                         }
@@ -311,4 +386,5 @@ pub fn format_transaction(
             }}
         </div>
     }
+    .into_any()
 }
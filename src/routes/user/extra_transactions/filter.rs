@@ -0,0 +1,140 @@
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use leptos::prelude::*;
+
+use crate::{
+    models::{Money, TransactionFilter, TransactionKind},
+    routes::articles::get_all_articles,
+};
+
+/// Start of the given local day, `"YYYY-MM-DD"` as delivered by date inputs.
+fn parse_day(value: &str, days_later: u64) -> Result<Option<DateTime<Utc>>, String> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.checked_add_days(chrono::Days::new(days_later)))
+        .ok_or_else(|| format!("Invalid date: {value}"))?;
+
+    date.and_hms_opt(0, 0, 0)
+        .and_then(|date| date.and_local_timezone(Local).earliest())
+        .map(|date| Some(date.with_timezone(&Utc)))
+        .ok_or_else(|| format!("Invalid date: {value}"))
+}
+
+fn parse_amount(value: String) -> Result<Option<Money>, String> {
+    match value.trim().is_empty() {
+        true => Ok(None),
+        false => Money::try_from(value.trim().to_string())
+            .map(Some)
+            .map_err(|e| e.to_string()),
+    }
+}
+
+fn parse_kind(value: &str) -> Option<TransactionKind> {
+    match value {
+        "deposit" => Some(TransactionKind::Deposit),
+        "withdraw" => Some(TransactionKind::Withdraw),
+        "bought" => Some(TransactionKind::Bought),
+        "sent" => Some(TransactionKind::Sent),
+        "received" => Some(TransactionKind::Received),
+        "correction" => Some(TransactionKind::Correction),
+        _ => None,
+    }
+}
+
+#[component]
+pub fn FilterForm(
+    filter: RwSignal<TransactionFilter>,
+    page_count: RwSignal<usize>,
+) -> impl IntoView {
+    let articles_resource = OnceResource::new(get_all_articles(None, true));
+
+    let kind = RwSignal::new(String::new());
+    let from = RwSignal::new(String::new());
+    let to = RwSignal::new(String::new());
+    let article_id = RwSignal::new(String::new());
+    let min_amount = RwSignal::new(String::new());
+    let max_amount = RwSignal::new(String::new());
+    let text = RwSignal::new(String::new());
+
+    let error = RwSignal::new(String::new());
+
+    let apply = move |_| {
+        let new_filter = (|| {
+            Ok::<_, String>(TransactionFilter {
+                kind: parse_kind(&kind.get_untracked()),
+                from: parse_day(&from.get_untracked(), 0)?,
+                // include the whole last day
+                to: parse_day(&to.get_untracked(), 1)?,
+                article_id: article_id.get_untracked().parse::<i64>().ok(),
+                min_amount: parse_amount(min_amount.get_untracked())?,
+                max_amount: parse_amount(max_amount.get_untracked())?,
+                text: Some(text.get_untracked()).filter(|text| !text.trim().is_empty()),
+            })
+        })();
+
+        match new_filter {
+            Ok(new_filter) => {
+                error.set(String::new());
+                page_count.set(0);
+                filter.set(new_filter);
+            }
+            Err(e) => error.set(e),
+        }
+    };
+
+    let input_class = "text-black rounded p-1";
+
+    view! {
+        <div class="flex flex-wrap justify-center items-center gap-2 p-2 text-white">
+            <select
+                class=input_class
+                on:change=move |ev| kind.set(event_target_value(&ev))
+                prop:value=move || kind.get()
+            >
+                <option value="">"All types"</option>
+                <option value="deposit">"Deposit"</option>
+                <option value="withdraw">"Withdraw"</option>
+                <option value="bought">"Bought"</option>
+                <option value="sent">"Sent"</option>
+                <option value="received">"Received"</option>
+                <option value="correction">"Balance correction"</option>
+            </select>
+            <label>"From " <input class=input_class type="date" bind:value=from /></label>
+            <label>"To " <input class=input_class type="date" bind:value=to /></label>
+            <Suspense>
+                <select
+                    class=input_class
+                    on:change=move |ev| article_id.set(event_target_value(&ev))
+                    prop:value=move || article_id.get()
+                >
+                    <option value="">"All articles"</option>
+                    {move || {
+                        articles_resource
+                            .get()
+                            .and_then(Result::ok)
+                            .unwrap_or_default()
+                            .into_iter()
+                            .map(|article| {
+                                view! { <option value=article.id>{article.name}</option> }
+                            })
+                            .collect_view()
+                    }}
+                </select>
+            </Suspense>
+            <input class=input_class size=6 placeholder="Min €" bind:value=min_amount />
+            <input class=input_class size=6 placeholder="Max €" bind:value=max_amount />
+            <input class=input_class placeholder="Description" bind:value=text />
+            <button class="bg-gray-400 rounded p-2" on:click=apply>
+                "Filter"
+            </button>
+        </div>
+        {move || {
+            let msg = error.get();
+            (!msg.is_empty())
+                .then(|| view! { <p class="p-3 bg-red-400 text-white text-center">{msg}</p> })
+        }}
+    }
+}
@@ -1,3 +1,5 @@
+pub use filter::*;
 pub use show::*;
 
+mod filter;
 mod show;
@@ -2,8 +2,11 @@ use leptos::{html, prelude::*};
 use leptos_router::hooks::use_params_map;
 
 use crate::{
-    models::{Money, PageRequestParams, Transaction, UserId},
-    routes::user::components::transaction_view::{format_transaction, get_user_transactions},
+    models::{Money, PageRequestParams, Transaction, TransactionFilter, UserId},
+    routes::user::{
+        components::transaction_view::{format_transaction, get_user_transactions},
+        extra_transactions::FilterForm,
+    },
 };
 
 #[component]
@@ -21,15 +24,24 @@ pub fn Show() -> impl IntoView {
     let transactions_per_page = 15;
 
     let page_count = RwSignal::new(0);
+    let filter = RwSignal::new(TransactionFilter::default());
     let trans_resource = Resource::new(
-        move || (page_count.get(), user_id, transactions_per_page),
-        |(page_count, user_id, transactions_per_page)| {
+        move || {
+            (
+                page_count.get(),
+                user_id,
+                transactions_per_page,
+                filter.get(),
+            )
+        },
+        |(page_count, user_id, transactions_per_page, filter)| {
             get_user_transactions(
                 user_id,
                 PageRequestParams {
                     offset: page_count * transactions_per_page,
                     limit: transactions_per_page,
                 },
+                filter,
             )
         },
     );
@@ -54,6 +66,7 @@ pub fn Show() -> impl IntoView {
                     </a>
                 </button>
             </div>
+            <FilterForm filter=filter page_count=page_count />
             <ShowNavigationButtons
                 page_count=page_count
                 transaction_signal=transaction_signal
@@ -88,6 +101,7 @@ pub fn Show() -> impl IntoView {
                                                 user_id,
                                                 error,
                                                 money_signal,
+                                                transaction_signal,
                                             )
                                         })
                                         .collect_view()}
@@ -1,10 +1,15 @@
+pub use archived::*;
 pub use create::*;
+pub use pin::*;
 pub use show::*;
 
+mod archived;
 mod create;
+mod pin;
 pub mod settings;
 mod show;
 
 pub mod components;
 pub mod extra_transactions;
+pub mod request_money;
 pub mod send_money;
@@ -0,0 +1,275 @@
+use leptos::prelude::*;
+
+use crate::models::UserId;
+
+#[cfg(feature = "ssr")]
+use {
+    crate::backend::{ServerState, SESSION_COOKIE},
+    crate::models::User,
+    crate::routes::user::get_user,
+    tracing::{debug, error, warn},
+};
+
+#[cfg(feature = "ssr")]
+fn get_session_token() -> Option<String> {
+    use axum::http::{header::COOKIE, request::Parts};
+
+    let parts: Parts = use_context()?;
+
+    parts
+        .headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|header| header.to_str().ok())
+        .flat_map(|header| header.split(';'))
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .find(|(name, _)| *name == SESSION_COOKIE)
+        .map(|(_, value)| value.to_string())
+}
+
+#[cfg(feature = "ssr")]
+fn set_session_cookie(token: &str, max_age: u64) {
+    use axum::http::{header::SET_COOKIE, HeaderValue};
+    use leptos_axum::ResponseOptions;
+
+    let response_opts: ResponseOptions = expect_context();
+
+    let cookie = format!(
+        "{}={}; Path=/; Max-Age={}; HttpOnly; SameSite=Strict",
+        SESSION_COOKIE, token, max_age
+    );
+
+    match HeaderValue::from_str(&cookie) {
+        Ok(value) => response_opts.append_header(SET_COOKIE, value),
+        Err(e) => error!("Failed to create session cookie: {}", e),
+    }
+}
+
+/// Users without a PIN are always unlocked.
+#[cfg(feature = "ssr")]
+pub async fn is_unlocked(user: &User) -> bool {
+    if !user.has_pin {
+        return true;
+    }
+
+    let token = match get_session_token() {
+        Some(token) => token,
+        None => return false,
+    };
+
+    let state: ServerState = expect_context();
+    let mut sessions = state.sessions.lock().await;
+
+    sessions.is_valid(&token, user.id)
+}
+
+/// Verifies the PIN of `user`. Wrong PINs count towards locking the user
+/// out, no PIN is accepted while the user is locked out.
+#[cfg(feature = "ssr")]
+pub async fn check_pin<T>(conn: &mut T, user: &User, pin: &str) -> Result<(), ServerFnError>
+where
+    for<'a> &'a mut T: sqlx::Executor<'a, Database = crate::backend::db::DatabaseType>,
+{
+    use axum::http::StatusCode;
+    use leptos_axum::ResponseOptions;
+    use std::time::Duration;
+
+    let state: ServerState = expect_context();
+    let response_opts: ResponseOptions = expect_context();
+
+    if let Some(locked_for) = state.sessions.lock().await.pin_locked_for(user.id) {
+        response_opts.set_status(StatusCode::TOO_MANY_REQUESTS);
+        return Err(ServerFnError::new(format!(
+            "Too many wrong PINs for {}, try again in {} seconds!",
+            user.nickname,
+            locked_for.as_secs() + 1
+        )));
+    }
+
+    match user.verify_pin(&mut *conn, pin).await {
+        Ok(true) => {
+            state.sessions.lock().await.clear_pin_failures(user.id);
+            Ok(())
+        }
+        Ok(false) => {
+            debug!("Wrong PIN entered for user '{}'", user.id);
+            let pin_settings = &state.settings.pin;
+            state.sessions.lock().await.record_pin_failure(
+                user.id,
+                pin_settings.max_attempts,
+                Duration::from_secs(pin_settings.lockout),
+            );
+            response_opts.set_status(StatusCode::UNAUTHORIZED);
+            Err(ServerFnError::new(format!(
+                "Wrong PIN for {}!",
+                user.nickname
+            )))
+        }
+        Err(e) => {
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            error!("Failed to verify PIN: {}", e);
+            Err(ServerFnError::new("Failed to verify PIN!"))
+        }
+    }
+}
+
+#[server]
+pub async fn is_user_unlocked(id: UserId) -> Result<bool, ServerFnError> {
+    use axum::http::StatusCode;
+    use leptos_axum::ResponseOptions;
+
+    let response_opts: ResponseOptions = expect_context();
+
+    let user = match get_user(id).await? {
+        Some(user) => user,
+        None => {
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(ServerFnError::new("No such user exists!"));
+        }
+    };
+
+    Ok(is_unlocked(&user).await)
+}
+
+#[server]
+pub async fn unlock_user(id: UserId, pin: String) -> Result<(), ServerFnError> {
+    use axum::http::StatusCode;
+    use leptos_axum::ResponseOptions;
+    use std::time::Duration;
+
+    let state: ServerState = expect_context();
+    let response_opts: ResponseOptions = expect_context();
+
+    let user = match get_user(id).await? {
+        Some(user) => user,
+        None => {
+            warn!("No such user with id '{}' exists!", id);
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(ServerFnError::new("No such user exists!"));
+        }
+    };
+
+    let db = state.db.lock().await;
+
+    let mut conn = match db.get_conn().await {
+        Ok(value) => value,
+        Err(e) => {
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            error!("Failed to get database handle: {}", e);
+            return Err(ServerFnError::new("Failed to get a database handle!"));
+        }
+    };
+
+    check_pin(&mut *conn, &user, &pin).await?;
+
+    let timeout = state.settings.pin.session_timeout;
+
+    let mut sessions = state.sessions.lock().await;
+
+    // users unlocked before on this kiosk stay unlocked
+    let token = match get_session_token() {
+        Some(token) if sessions.unlock(&token, user.id, Duration::from_secs(timeout)) => token,
+        _ => sessions.create(user.id, Duration::from_secs(timeout)),
+    };
+    set_session_cookie(&token, timeout);
+
+    Ok(())
+}
+
+#[server]
+pub async fn lock_user(id: UserId) -> Result<(), ServerFnError> {
+    let state: ServerState = expect_context();
+
+    let session_left = match get_session_token() {
+        Some(token) => state.sessions.lock().await.lock(&token, id),
+        None => false,
+    };
+
+    if !session_left {
+        set_session_cookie("", 0);
+    }
+
+    Ok(())
+}
+
+#[component]
+pub fn PinLock(user_id: UserId) -> impl IntoView {
+    let unlock_action = ServerAction::<UnlockUser>::new();
+    let lock_action = ServerAction::<LockUser>::new();
+
+    let unlocked_resource = Resource::new(
+        move || (unlock_action.version().get(), lock_action.version().get()),
+        move |_| is_user_unlocked(user_id),
+    );
+
+    view! {
+        <Suspense>
+            {move || {
+                unlocked_resource
+                    .get()
+                    .map(|unlocked| match unlocked {
+                        Ok(true) => {
+                            view! {
+                                <ActionForm action=lock_action>
+                                    <div class="flex justify-center pt-2">
+                                        <input type="hidden" value=user_id.0 name="id" />
+                                        <input
+                                            class="text-white hover:bg-pink-700 bg-emerald-700 rounded-full p-2"
+                                            type="submit"
+                                            value="Lock"
+                                        />
+                                    </div>
+                                </ActionForm>
+                            }
+                                .into_any()
+                        }
+                        Ok(false) => {
+                            view! {
+                                <ActionForm action=unlock_action>
+                                    <div class="flex justify-center gap-2 pt-2">
+                                        <input type="hidden" value=user_id.0 name="id" />
+                                        <input
+                                            class="text-center rounded"
+                                            type="password"
+                                            inputmode="numeric"
+                                            placeholder="PIN"
+                                            name="pin"
+                                        />
+                                        <input
+                                            class="text-white hover:bg-pink-700 bg-emerald-700 rounded-full p-2"
+                                            type="submit"
+                                            value="Unlock"
+                                        />
+                                    </div>
+                                </ActionForm>
+                                {move || match unlock_action.value().get() {
+                                    Some(Err(e)) => {
+                                        let msg = match e {
+                                            ServerFnError::ServerError(msg) => msg,
+                                            _ => e.to_string(),
+                                        };
+                                        view! {
+                                            <p class="text-center text-white bg-red-400 rounded">
+                                                {msg}
+                                            </p>
+                                        }
+                                            .into_any()
+                                    }
+                                    _ => ().into_any(),
+                                }}
+                            }
+                                .into_any()
+                        }
+                        Err(e) => {
+                            view! {
+                                <p class="text-center text-white bg-red-400 rounded">
+                                    "Failed to check PIN: "{e.to_string()}
+                                </p>
+                            }
+                                .into_any()
+                        }
+                    })
+            }}
+        </Suspense>
+    }
+}
@@ -0,0 +1,217 @@
+use leptos::{prelude::*, task::spawn_local};
+use leptos_router::hooks::use_params_map;
+
+use crate::{
+    models::UserId, routes::split_cost::components::multi_user_selection::SelectMultiUser,
+};
+
+#[cfg(feature = "ssr")]
+use {
+    crate::models::{Money, MoneyRequestDB, User},
+    crate::routes::user::{get_user, is_unlocked},
+    chrono::{Duration, Utc},
+    tracing::error,
+};
+
+/// Asks every user in `from_users` to pay `amount` to the user.
+#[server]
+pub async fn request_money(
+    user_id: UserId,
+    from_users: Option<Vec<String>>,
+    amount: String,
+    description: String,
+) -> Result<(), ServerFnError> {
+    use crate::backend::ServerState;
+    let state: ServerState = expect_context();
+
+    use axum::http::StatusCode;
+    use leptos_axum::redirect;
+    use leptos_axum::ResponseOptions;
+
+    let response_opts: ResponseOptions = expect_context();
+
+    let money: Money = match amount.clone().try_into() {
+        Ok(value) => value,
+        Err(e) => {
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(ServerFnError::new(format!(
+                "Failed to convert '{amount}' to internal representation: {e}"
+            )));
+        }
+    };
+
+    if money.value <= 0 {
+        response_opts.set_status(StatusCode::BAD_REQUEST);
+        return Err(ServerFnError::new("Requested amount must be > 0!"));
+    }
+
+    let from_users = from_users.unwrap_or_default();
+
+    if from_users.is_empty() {
+        response_opts.set_status(StatusCode::BAD_REQUEST);
+        return Err(ServerFnError::new("Choose whom to request the money from!"));
+    }
+
+    let requester = match get_user(user_id).await? {
+        Some(value) => value,
+        None => {
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(ServerFnError::new(
+                "The user you are trying to request the money for does not exist!",
+            ));
+        }
+    };
+
+    if !is_unlocked(&requester).await {
+        response_opts.set_status(StatusCode::UNAUTHORIZED);
+        return Err(ServerFnError::new("Please enter your PIN first!"));
+    }
+
+    let description = match description.trim().is_empty() {
+        true => None,
+        false => Some(description),
+    };
+
+    let expires_at = Utc::now() + Duration::days(state.settings.requests.expire_after as i64);
+
+    let db = state.db.lock().await;
+
+    let mut db_trns = match db.get_conn_transaction().await {
+        Ok(value) => value,
+        Err(e) => {
+            error!("Failed to get db transaction: {}", e);
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(ServerFnError::new("Failed to acquire db transaction!"));
+        }
+    };
+
+    for nickname in from_users.iter() {
+        let debtor = match User::get_by_nick(&mut *db_trns, nickname).await {
+            Ok(Some(value)) => value,
+            Ok(None) => {
+                response_opts.set_status(StatusCode::BAD_REQUEST);
+                return Err(ServerFnError::new(format!(
+                    "User '{nickname}' was not found!"
+                )));
+            }
+            Err(e) => {
+                error!("Failed to lookup db: {e}");
+                response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+                return Err(ServerFnError::new("Failed to lookup db"));
+            }
+        };
+
+        if debtor.is_system_user || debtor.is_archived {
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(ServerFnError::new(format!(
+                "You can't request money from '{nickname}'!"
+            )));
+        }
+
+        if debtor.id == requester.id {
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(ServerFnError::new("You can't request money from yourself!"));
+        }
+
+        if let Err(e) = MoneyRequestDB::create(
+            &mut *db_trns,
+            requester.id,
+            debtor.id,
+            money,
+            description.clone(),
+            expires_at,
+        )
+        .await
+        {
+            error!("Failed to create money request: {e}");
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            return Err(ServerFnError::new("Failed to create money request!"));
+        }
+    }
+
+    if let Err(e) = db_trns.commit().await {
+        error!("Failed to commit transaction: {}", e);
+        response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+        return Err(ServerFnError::new("Failed to save the requests!"));
+    };
+
+    redirect(&format!("/user/{}", requester.id));
+
+    Ok(())
+}
+
+#[component]
+pub fn Show() -> impl IntoView {
+    let params = use_params_map();
+    let user_id_string = params.read_untracked().get("id").unwrap_or_default();
+
+    let user_id = match user_id_string.parse::<i64>() {
+        Ok(value) => UserId(value),
+        Err(_e) => {
+            return view! { <p class="text-red-500">"Failed to convert id to a number!"</p> }
+                .into_any();
+        }
+    };
+
+    let from_users = RwSignal::new(Vec::<String>::new());
+    let amount_input = RwSignal::new(String::new());
+    let description_input = RwSignal::new(String::new());
+
+    let error_result = RwSignal::new(String::new());
+
+    let on_click = move |_| {
+        spawn_local(async move {
+            if let Err(e) = request_money(
+                user_id,
+                Some(from_users.get_untracked()),
+                amount_input.get_untracked(),
+                description_input.get_untracked(),
+            )
+            .await
+            {
+                error_result.set(e.to_string());
+            }
+        })
+    };
+
+    view! {
+        <div class="flex flex-col items-center gap-3 text-[1.25em]">
+            <SelectMultiUser
+                title=String::from("Who owes you money?")
+                users_input=from_users
+                single_user_extra_class="w-full h-full".to_string()
+            />
+            <div class="bg-indigo-100 rounded p-5">
+                <label class="block mb-2 text-indigo-500">"Amount per person"</label>
+                <input
+                    bind:value=amount_input
+                    autocomplete="off"
+                    class="w-full p-2 mb-6 text-indigo-700 border-b-2 border-indigo-500 outline-none focus:bg-gray-300"
+                    type="text"
+                />
+                <label class="block mb-2 text-indigo-500">"What for?"</label>
+                <input
+                    bind:value=description_input
+                    autocomplete="off"
+                    class="w-full p-2 mb-6 text-indigo-700 border-b-2 border-indigo-500 outline-none focus:bg-gray-300"
+                    type="text"
+                />
+                <button
+                    class="w-full bg-indigo-700 hover:bg-pink-700 text-white font-bold py-2 px-4 mb-6 rounded"
+                    on:click=on_click
+                >
+                    "Request money"
+                </button>
+                {move || match error_result.get().len() {
+                    0 => ().into_any(),
+                    _ => {
+                        let msg = error_result.get();
+                        view! { <p class="text-red-900">"Failed to request money: "{msg}</p> }
+                            .into_any()
+                    }
+                }}
+            </div>
+        </div>
+    }
+    .into_any()
+}
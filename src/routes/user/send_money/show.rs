@@ -2,14 +2,15 @@ use leptos::{html, leptos_dom::logging::console_log, prelude::*, task::spawn_loc
 use leptos_router::hooks::use_params_map;
 
 use crate::{
-    models::UserId,
+    models::{new_idempotency_key, UserId},
     routes::{home::get_all_users, user::get_user},
 };
 
 #[cfg(feature = "ssr")]
 use {
+    crate::models::{BookOnceError, IdempotencyKey, Money, Transaction, User},
     crate::models::{GroupDB, GroupId},
-    crate::models::{Money, Transaction, User},
+    crate::routes::user::is_unlocked,
     tracing::error,
 };
 
@@ -18,6 +19,7 @@ pub async fn send_money(
     user_id: UserId,
     to_user: String,
     amount: String,
+    idempotency_key: Option<String>,
 ) -> Result<(), ServerFnError> {
     use crate::backend::ServerState;
     let state: ServerState = expect_context();
@@ -53,6 +55,11 @@ pub async fn send_money(
         }
     };
 
+    if !is_unlocked(&sender).await {
+        response_opts.set_status(StatusCode::UNAUTHORIZED);
+        return Err(ServerFnError::new("Please enter your PIN first!"));
+    }
+
     let db = state.db.lock().await;
 
     let mut db_trns = match db.get_conn_transaction().await {
@@ -108,16 +115,42 @@ pub async fn send_money(
         }
     };
 
-    Transaction::create(
+    if let Err(e) = IdempotencyKey::book_once(
         &mut *db_trns,
-        GroupId(sender_group),
-        GroupId(recipient_group),
-        crate::models::TransactionType::Sent(GroupId(recipient_group)),
-        None,
-        money,
-        &state.settings,
+        sender.id,
+        idempotency_key.as_deref(),
+        &(recipient.id, money.value),
+        state.settings.idempotency.window,
+        async |conn| {
+            Transaction::create(
+                conn,
+                GroupId(sender_group),
+                GroupId(recipient_group),
+                crate::models::TransactionType::Sent(GroupId(recipient_group)),
+                None,
+                money,
+                &state.settings,
+            )
+            .await
+        },
     )
-    .await?;
+    .await
+    {
+        match e {
+            BookOnceError::KeyReused => {
+                response_opts.set_status(StatusCode::CONFLICT);
+                return Err(ServerFnError::new(
+                    "The idempotency key was already used for another request!",
+                ));
+            }
+            BookOnceError::Database(e) => {
+                error!("Failed to look up or store idempotency key: {e}");
+                response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+                return Err(ServerFnError::new("Failed to check idempotency key!"));
+            }
+            BookOnceError::Booking(e) => return Err(e.into()),
+        }
+    }
 
     if let Err(e) = db_trns.commit().await {
         error!("Failed to commit transaction: {}", e);
@@ -272,6 +305,7 @@ pub fn Show() -> impl IntoView {
                                                             user_id,
                                                             receiver_input.get(),
                                                             amount_input.get(),
+                                                            Some(new_idempotency_key()),
                                                         )
                                                         .await
                                                     {
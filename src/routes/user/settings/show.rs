@@ -1,20 +1,20 @@
 use leptos::prelude::*;
 use leptos_router::hooks::use_params_map;
 
-use crate::{models::UserId, routes::user::get_user};
+use crate::{
+    models::{payment_reference, DatabaseId, UserId},
+    routes::user::{get_user, PinLock},
+};
 
 #[cfg(feature = "ssr")]
 use {
     crate::models::User,
+    crate::routes::user::is_unlocked,
     tracing::{debug, error, warn},
 };
 
 #[server]
-pub async fn update_user(
-    id: UserId,
-    nickname: String,
-    card_number: String,
-) -> Result<(), ServerFnError> {
+pub async fn update_user(id: UserId, nickname: String) -> Result<(), ServerFnError> {
     use crate::backend::ServerState;
     let state: ServerState = expect_context();
     use axum::http::StatusCode;
@@ -41,18 +41,84 @@ pub async fn update_user(
         }
     };
 
-    match User::get_by_card_number(&*state.db.lock().await, card_number.clone()).await {
-        Ok(value) => match value {
-            None => {}
-            Some(user) => {
-                if user.id != id {
-                    warn!("The card number '{}' is already used!", card_number);
-                    response_opts.set_status(StatusCode::BAD_REQUEST);
-                    return Err(ServerFnError::new("The card number is already used!"));
-                }
-            }
-        },
+    if !is_unlocked(&user).await {
+        response_opts.set_status(StatusCode::UNAUTHORIZED);
+        return Err(ServerFnError::new("Please enter your PIN first!"));
+    }
+
+    let db = &*state.db.lock().await;
+
+    let mut db_trans = match db.get_conn_transaction().await {
+        Ok(value) => value,
+        Err(e) => {
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            error!("Failed to get database handle: {}", e);
+            return Err(ServerFnError::new("Faile to get a database handle!"));
+        }
+    };
+
+    match user.set_name(&mut *db_trans, nickname).await {
+        Ok(_) => {}
+        Err(e) => {
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            error!("Failed to set a new username: {}", e);
+            return Err(ServerFnError::new("Failed to set a new username!"));
+        }
+    }
+
+    match db_trans.commit().await {
+        Ok(_) => {}
+        Err(e) => {
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            error!("Failed to commit database transaction: {}", e);
+            return Err(ServerFnError::new(
+                "Failed to commit the database transaction",
+            ));
+        }
+    }
+
+    redirect(&format!("/user/{}", id));
+
+    Ok(())
+}
+
+#[server]
+pub async fn add_card(id: UserId, card_number: String, label: String) -> Result<(), ServerFnError> {
+    use crate::backend::ServerState;
+    let state: ServerState = expect_context();
+    use axum::http::StatusCode;
+    use leptos_axum::ResponseOptions;
+
+    let response_opts: ResponseOptions = expect_context();
+
+    if card_number.is_empty() {
+        response_opts.set_status(StatusCode::BAD_REQUEST);
+        return Err(ServerFnError::new("The card number may not be empty!"));
+    }
+
+    let mut user = match get_user(id).await? {
+        Some(user) => user,
+        None => {
+            warn!("No such user with id '{}' exists!", id);
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(ServerFnError::new("No such user exists!"));
+        }
+    };
+
+    if !is_unlocked(&user).await {
+        response_opts.set_status(StatusCode::UNAUTHORIZED);
+        return Err(ServerFnError::new("Please enter your PIN first!"));
+    }
 
+    let db = state.db.lock().await;
+
+    match User::get_by_card_number(&db, card_number.clone()).await {
+        Ok(None) => {}
+        Ok(Some(_)) => {
+            warn!("The card number '{}' is already used!", card_number);
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(ServerFnError::new("The card number is already used!"));
+        }
         Err(e) => {
             error!("Failed to check for existence of the card number: {}", e);
             response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
@@ -62,54 +128,263 @@ pub async fn update_user(
         }
     }
 
-    let card_number = match card_number.len() {
+    let label = match label.len() {
         0 => None,
-        _ => Some(card_number),
+        _ => Some(label),
     };
 
-    let db = &*state.db.lock().await;
+    let mut conn = match db.get_conn().await {
+        Ok(value) => value,
+        Err(e) => {
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            error!("Failed to get database handle: {}", e);
+            return Err(ServerFnError::new("Failed to get a database handle!"));
+        }
+    };
 
-    let mut db_trans = match db.get_conn_transaction().await {
+    debug!("Adding card '{}' to user '{}'", card_number, user.id);
+
+    if let Err(e) = user.add_card(&mut *conn, card_number, label).await {
+        response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+        error!("Failed to add card: {}", e);
+        return Err(ServerFnError::new("Failed to add card!"));
+    }
+
+    Ok(())
+}
+
+#[server]
+pub async fn remove_card(id: UserId, card_id: DatabaseId) -> Result<(), ServerFnError> {
+    use crate::backend::ServerState;
+    let state: ServerState = expect_context();
+    use axum::http::StatusCode;
+    use leptos_axum::ResponseOptions;
+
+    let response_opts: ResponseOptions = expect_context();
+
+    let mut user = match get_user(id).await? {
+        Some(user) => user,
+        None => {
+            warn!("No such user with id '{}' exists!", id);
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(ServerFnError::new("No such user exists!"));
+        }
+    };
+
+    if !is_unlocked(&user).await {
+        response_opts.set_status(StatusCode::UNAUTHORIZED);
+        return Err(ServerFnError::new("Please enter your PIN first!"));
+    }
+
+    if !user.cards.iter().any(|card| card.id == card_id) {
+        response_opts.set_status(StatusCode::BAD_REQUEST);
+        return Err(ServerFnError::new("The card does not belong to this user!"));
+    }
+
+    let db = state.db.lock().await;
+
+    let mut conn = match db.get_conn().await {
         Ok(value) => value,
         Err(e) => {
             response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
             error!("Failed to get database handle: {}", e);
-            return Err(ServerFnError::new("Faile to get a database handle!"));
+            return Err(ServerFnError::new("Failed to get a database handle!"));
         }
     };
 
-    match user.set_name(&mut *db_trans, nickname).await {
-        Ok(_) => {}
+    debug!("Removing card '{}' from user '{}'", card_id, user.id);
+
+    if let Err(e) = user.remove_card(&mut *conn, card_id).await {
+        response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+        error!("Failed to remove card: {}", e);
+        return Err(ServerFnError::new("Failed to remove card!"));
+    }
+
+    Ok(())
+}
+
+#[server]
+pub async fn set_pin(id: UserId, pin: String) -> Result<(), ServerFnError> {
+    use crate::backend::ServerState;
+    let state: ServerState = expect_context();
+    use axum::http::StatusCode;
+    use leptos_axum::ResponseOptions;
+
+    let response_opts: ResponseOptions = expect_context();
+
+    let mut user = match get_user(id).await? {
+        Some(user) => user,
+        None => {
+            warn!("No such user with id '{}' exists!", id);
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(ServerFnError::new("No such user exists!"));
+        }
+    };
+
+    if !is_unlocked(&user).await {
+        response_opts.set_status(StatusCode::UNAUTHORIZED);
+        return Err(ServerFnError::new("Please enter your PIN first!"));
+    }
+
+    // an empty PIN removes it
+    let pin = match pin.len() {
+        0 => None,
+        _ => Some(pin),
+    };
+
+    if let Some(pin) = &pin {
+        if pin.len() < 4 || !pin.chars().all(|c| c.is_ascii_digit()) {
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(ServerFnError::new(
+                "The PIN has to consist of at least 4 digits!",
+            ));
+        }
+    }
+
+    let db = state.db.lock().await;
+
+    let mut conn = match db.get_conn().await {
+        Ok(value) => value,
         Err(e) => {
             response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
-            error!("Failed to set a new username: {}", e);
-            return Err(ServerFnError::new("Failed to set a new username!"));
+            error!("Failed to get database handle: {}", e);
+            return Err(ServerFnError::new("Failed to get a database handle!"));
         }
+    };
+
+    debug!("Changing PIN of user '{}'", user.id);
+
+    if let Err(e) = user.set_pin(&mut *conn, pin).await {
+        response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+        error!("Failed to set PIN: {}", e);
+        return Err(ServerFnError::new("Failed to set PIN!"));
     }
 
-    debug!(
-        "Changing card number for user '{}' to '{:?}'",
-        user.id, user.card_number
-    );
+    Ok(())
+}
 
-    match user.set_card_number(&mut *db_trans, card_number).await {
-        Ok(_) => {}
+#[server]
+pub async fn archive_user(id: UserId) -> Result<(), ServerFnError> {
+    use crate::backend::ServerState;
+    let state: ServerState = expect_context();
+    use axum::http::StatusCode;
+    use leptos_axum::redirect;
+    use leptos_axum::ResponseOptions;
+
+    let response_opts: ResponseOptions = expect_context();
+
+    let user = match get_user(id).await? {
+        Some(user) => user,
+        None => {
+            warn!("No such user with id '{}' exists!", id);
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(ServerFnError::new("No such user exists!"));
+        }
+    };
+
+    if !is_unlocked(&user).await {
+        response_opts.set_status(StatusCode::UNAUTHORIZED);
+        return Err(ServerFnError::new("Please enter your PIN first!"));
+    }
+
+    let db = state.db.lock().await;
+
+    let mut db_trans = match db.get_conn_transaction().await {
+        Ok(value) => value,
         Err(e) => {
             response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
-            error!("Failed to set a new card number: {}", e);
-            return Err(ServerFnError::new("Failed to set a new card number!"));
+            error!("Failed to get database handle: {}", e);
+            return Err(ServerFnError::new("Failed to get a database handle!"));
+        }
+    };
+
+    // reload the user inside of the transaction, so no booking can change the
+    // balance between the check and archiving
+    let mut user = match User::get(&mut *db_trans, id).await {
+        Ok(Some(user)) => user,
+        _ => {
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            error!("Failed to reload user '{}'", id);
+            return Err(ServerFnError::new("Failed to fetch user!"));
         }
+    };
+
+    if user.is_archived {
+        response_opts.set_status(StatusCode::BAD_REQUEST);
+        return Err(ServerFnError::new("The user is already archived!"));
     }
 
-    match db_trans.commit().await {
-        Ok(_) => {}
+    if user.money.value != 0 {
+        response_opts.set_status(StatusCode::BAD_REQUEST);
+        return Err(ServerFnError::new(format!(
+            "The balance of {} has to be settled before archiving!",
+            user.money.format_eur()
+        )));
+    }
+
+    if let Err(e) = user.set_archived(&mut *db_trans, true).await {
+        response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+        error!("Failed to archive user: {}", e);
+        return Err(ServerFnError::new("Failed to archive user!"));
+    }
+
+    if let Err(e) = db_trans.commit().await {
+        response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+        error!("Failed to commit database transaction: {}", e);
+        return Err(ServerFnError::new(
+            "Failed to commit the database transaction",
+        ));
+    }
+
+    redirect("/");
+
+    Ok(())
+}
+
+#[server]
+pub async fn unarchive_user(id: UserId) -> Result<(), ServerFnError> {
+    use crate::backend::ServerState;
+    let state: ServerState = expect_context();
+    use axum::http::StatusCode;
+    use leptos_axum::redirect;
+    use leptos_axum::ResponseOptions;
+
+    let response_opts: ResponseOptions = expect_context();
+
+    let mut user = match get_user(id).await? {
+        Some(user) => user,
+        None => {
+            warn!("No such user with id '{}' exists!", id);
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(ServerFnError::new("No such user exists!"));
+        }
+    };
+
+    if !is_unlocked(&user).await {
+        response_opts.set_status(StatusCode::UNAUTHORIZED);
+        return Err(ServerFnError::new("Please enter your PIN first!"));
+    }
+
+    if !user.is_archived {
+        response_opts.set_status(StatusCode::BAD_REQUEST);
+        return Err(ServerFnError::new("The user is not archived!"));
+    }
+
+    let db = state.db.lock().await;
+
+    let mut conn = match db.get_conn().await {
+        Ok(value) => value,
         Err(e) => {
             response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
-            error!("Failed to commit database transaction: {}", e);
-            return Err(ServerFnError::new(
-                "Failed to commit the database transaction",
-            ));
+            error!("Failed to get database handle: {}", e);
+            return Err(ServerFnError::new("Failed to get a database handle!"));
         }
+    };
+
+    if let Err(e) = user.set_archived(&mut *conn, false).await {
+        response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+        error!("Failed to unarchive user: {}", e);
+        return Err(ServerFnError::new("Failed to unarchive user!"));
     }
 
     redirect(&format!("/user/{}", id));
@@ -130,9 +405,23 @@ pub fn Show() -> impl IntoView {
         }
     };
 
-    let user_resource = OnceResource::new(get_user(user_id));
-
     let update_action = ServerAction::<UpdateUser>::new();
+    let archive_action = ServerAction::<ArchiveUser>::new();
+    let unarchive_action = ServerAction::<UnarchiveUser>::new();
+    let add_card_action = ServerAction::<AddCard>::new();
+    let remove_card_action = ServerAction::<RemoveCard>::new();
+    let set_pin_action = ServerAction::<SetPin>::new();
+
+    let user_resource = Resource::new(
+        move || {
+            (
+                add_card_action.version().get(),
+                remove_card_action.version().get(),
+                set_pin_action.version().get(),
+            )
+        },
+        move |_| get_user(user_id),
+    );
     view! {
         <Suspense fallback=move || {
             view! { <p class="text-white text-center pt-5">"Loading User..."</p> }
@@ -168,6 +457,7 @@ pub fn Show() -> impl IntoView {
                 };
 
                 view! {
+                    {user.has_pin.then(|| view! { <PinLock user_id=user.id /> })}
                     {move || match update_action.value().get() {
                         Some(Err(e)) => {
                             let msg = match e {
@@ -195,15 +485,6 @@ pub fn Show() -> impl IntoView {
                                     name="nickname"
                                 />
                             </div>
-                            <div class="flex flex-col items-center">
-                                <label class="text-white text-[1.25em]">"Card number"</label>
-                                <input
-                                    class="text-[1.25em]"
-                                    type="text"
-                                    value=user.card_number
-                                    name="card_number"
-                                />
-                            </div>
                             <input type="hidden" value=user.id.0 name="id" />
                             <input
                                 class="text-white hover:bg-pink-700 bg-emerald-700 rounded-full text-[1.25em] p-2"
@@ -212,6 +493,193 @@ pub fn Show() -> impl IntoView {
                             />
                         </div>
                     </ActionForm>
+                    {move || {
+                        let result = match add_card_action.value().get() {
+                            Some(Err(e)) => Some(e),
+                            _ => {
+                                match remove_card_action.value().get() {
+                                    Some(Err(e)) => Some(e),
+                                    _ => None,
+                                }
+                            }
+                        };
+                        match result {
+                            Some(e) => {
+                                let msg = match e {
+                                    ServerFnError::ServerError(msg) => msg,
+                                    _ => e.to_string(),
+                                };
+
+                                view! {
+                                    <p class="p-3 bg-red-400 text-white text-center">
+                                        "Failed to change cards: "{msg}
+                                    </p>
+                                }
+                                    .into_any()
+                            }
+                            None => ().into_any(),
+                        }
+                    }}
+                    <div class="flex flex-col items-center gap-5 pt-10">
+                        <p class="text-white text-[1.25em]">"Cards"</p>
+                        {user
+                            .cards
+                            .clone()
+                            .into_iter()
+                            .map(|card| {
+                                view! {
+                                    <ActionForm action=remove_card_action>
+                                        <div class="flex flex-row items-center gap-3">
+                                            <p class="text-white">
+                                                {card.label.unwrap_or("Card".to_string())}
+                                                ": "
+                                                {card.card_number}
+                                            </p>
+                                            <input type="hidden" value=user.id.0 name="id" />
+                                            <input type="hidden" value=card.id name="card_id" />
+                                            <input
+                                                class="text-white hover:bg-pink-700 bg-red-400 rounded-full p-2"
+                                                type="submit"
+                                                value="Remove"
+                                            />
+                                        </div>
+                                    </ActionForm>
+                                }
+                            })
+                            .collect_view()}
+                        <ActionForm action=add_card_action>
+                            <div class="flex flex-col items-center gap-3">
+                                <input
+                                    class="text-[1.25em]"
+                                    type="text"
+                                    placeholder="Card number"
+                                    name="card_number"
+                                />
+                                <input
+                                    class="text-[1.25em]"
+                                    type="text"
+                                    placeholder="Label"
+                                    name="label"
+                                />
+                                <input type="hidden" value=user.id.0 name="id" />
+                                <input
+                                    class="text-white hover:bg-pink-700 bg-emerald-700 rounded-full text-[1.25em] p-2"
+                                    type="submit"
+                                    value="Add card"
+                                />
+                            </div>
+                        </ActionForm>
+                    </div>
+                    {move || match set_pin_action.value().get() {
+                        Some(Err(e)) => {
+                            let msg = match e {
+                                ServerFnError::ServerError(msg) => msg,
+                                _ => e.to_string(),
+                            };
+
+                            view! {
+                                <p class="p-3 bg-red-400 text-white text-center">
+                                    "Failed to change PIN: "{msg}
+                                </p>
+                            }
+                                .into_any()
+                        }
+                        _ => ().into_any(),
+                    }}
+                    <div class="flex flex-col items-center gap-3 pt-10">
+                        <p class="text-white text-[1.25em]">"PIN"</p>
+                        <ActionForm action=set_pin_action>
+                            <div class="flex flex-col items-center gap-3">
+                                <input
+                                    class="text-[1.25em]"
+                                    type="password"
+                                    inputmode="numeric"
+                                    placeholder="New PIN"
+                                    name="pin"
+                                />
+                                <input type="hidden" value=user.id.0 name="id" />
+                                <input
+                                    class="text-white hover:bg-pink-700 bg-emerald-700 rounded-full text-[1.25em] p-2"
+                                    type="submit"
+                                    value="Set PIN"
+                                />
+                            </div>
+                        </ActionForm>
+                        {user
+                            .has_pin
+                            .then(|| {
+                                view! {
+                                    <ActionForm action=set_pin_action>
+                                        <input type="hidden" value="" name="pin" />
+                                        <input type="hidden" value=user.id.0 name="id" />
+                                        <input
+                                            class="text-white hover:bg-pink-700 bg-red-400 rounded-full text-[1.25em] p-2"
+                                            type="submit"
+                                            value="Remove PIN"
+                                        />
+                                    </ActionForm>
+                                }
+                            })}
+                    </div>
+                    <div class="flex flex-col items-center gap-3 pt-10">
+                        <p class="text-white text-[1.25em]">"Bank transfer"</p>
+                        <p class="text-white">
+                            "Put "<b>{payment_reference(user.id)}</b>
+                            " into the reference when topping up by bank transfer"
+                        </p>
+                    </div>
+                    {move || {
+                        let result = match user.is_archived {
+                            true => unarchive_action.value().get(),
+                            false => archive_action.value().get(),
+                        };
+                        match result {
+                            Some(Err(e)) => {
+                                let msg = match e {
+                                    ServerFnError::ServerError(msg) => msg,
+                                    _ => e.to_string(),
+                                };
+
+                                view! {
+                                    <p class="p-3 bg-red-400 text-white text-center">
+                                        "Failed to change archive state: "{msg}
+                                    </p>
+                                }
+                                    .into_any()
+                            }
+                            _ => ().into_any(),
+                        }
+                    }}
+                    <div class="flex flex-col items-center gap-5 pt-10">
+                        {match user.is_archived {
+                            true => {
+                                view! {
+                                    <ActionForm action=unarchive_action>
+                                        <input type="hidden" value=user.id.0 name="id" />
+                                        <input
+                                            class="text-white hover:bg-pink-700 bg-emerald-700 rounded-full text-[1.25em] p-2"
+                                            type="submit"
+                                            value="Unarchive user"
+                                        />
+                                    </ActionForm>
+                                }
+                                    .into_any()
+                            }
+                            false => {
+                                view! {
+                                    <ActionForm action=archive_action>
+                                        <input type="hidden" value=user.id.0 name="id" />
+                                        <input
+                                            class="text-white hover:bg-pink-700 bg-red-400 rounded-full text-[1.25em] p-2"
+                                            type="submit"
+                                            value="Archive user"
+                                        />
+                                    </ActionForm>
+                                }
+                                    .into_any()
+                            }
+                        }}
+                    </div>
                 }
                     .into_any()
             }}
@@ -4,23 +4,34 @@ use leptos::{prelude::*, server_fn::error::ServerFnErrorErr, task::spawn_local};
 use leptos_router::hooks::use_params_map;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
-use tracing::error;
 
 #[cfg(feature = "ssr")]
 use crate::backend::db::DBError;
 use crate::{
-    models::{play_sound, AudioPlayback, Money, Transaction, TransactionType, User, UserId},
-    routes::user::components::{buy_article::BuyArticle, scan_input::invisible_scan_input},
+    models::{
+        new_idempotency_key, play_sound, AudioPlayback, Money, Transaction, TransactionType, User,
+        UserId,
+    },
+    routes::user::components::{
+        basket::{Basket, ShowBasket},
+        buy_article::BuyArticle,
+        money_requests::MoneyRequests,
+        scan_input::invisible_scan_input,
+        top_up_qr::TopUpQrCode,
+    },
+    routes::user::PinLock,
 };
 
 #[cfg(feature = "ssr")]
 use {
     crate::backend::db::{DBGROUP_AUFLADUNG_ID, DBGROUP_SNACKBAR_ID},
     crate::backend::db::{DBUSER_AUFLADUNG_ID, DBUSER_SNACKBAR_ID},
-    crate::models::Group,
+    crate::models::{BookOnceError, Group, IdempotencyKey},
     crate::routes::articles::get_article,
+    crate::routes::user::is_unlocked,
     rand::seq::IndexedRandom,
     std::{path::PathBuf, str::FromStr},
+    tracing::error,
 };
 
 use super::components::transaction_view::ShowTransactions;
@@ -72,11 +83,20 @@ pub async fn get_user(id: UserId) -> Result<Option<User>, ServerFnError> {
 
 #[derive(Error, Debug, Clone, Deserialize, Serialize)]
 pub enum CreateTransactionError {
-    #[error("the following users have too little money: {}", .0.join(", "))]
-    TooLittleMoneyError(Vec<String>),
+    #[error("the following users have too little money: {}", format_limits(.0))]
+    TooLittleMoneyError(Vec<(String, Money)>),
 
-    #[error("the following users have too much money: {}", .0.join(", "))]
-    TooMuchMoneyError(Vec<String>),
+    #[error("the following users have too much money: {}", format_limits(.0))]
+    TooMuchMoneyError(Vec<(String, Money)>),
+
+    #[error("the following users are archived: {}", .0.join(", "))]
+    ArchivedUserError(Vec<String>),
+
+    #[error("the PIN of the user has to be entered first")]
+    PinRequiredError,
+
+    #[error("only {1} of {0} left in stock")]
+    OutOfStockError(String, i64),
 
     #[error("Failed to create transaction: {0}")]
     StringMessage(String),
@@ -85,6 +105,14 @@ pub enum CreateTransactionError {
     ServerFn(ServerFnErrorErr),
 }
 
+fn format_limits(users: &[(String, Money)]) -> String {
+    users
+        .iter()
+        .map(|(nickname, limit)| format!("{} (limit: {})", nickname, limit.format_eur()))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 impl CreateTransactionError {
     pub fn new(value: &str) -> Self {
         Self::StringMessage(value.to_string())
@@ -116,6 +144,7 @@ pub async fn create_transaction(
     user_id: UserId,
     money: Money,
     transaction_type: TransactionType,
+    idempotency_key: Option<String>,
 ) -> Result<Transaction, CreateTransactionError> {
     type Error = CreateTransactionError;
     use crate::backend::ServerState;
@@ -131,6 +160,19 @@ pub async fn create_transaction(
         return Err(Error::new("Money may not be negative"));
     }
 
+    let user = match get_user(user_id).await? {
+        Some(user) => user,
+        None => {
+            response_opts.set_status(StatusCode::BAD_REQUEST);
+            return Err(Error::new("No such user exists!"));
+        }
+    };
+
+    if !is_unlocked(&user).await {
+        response_opts.set_status(StatusCode::UNAUTHORIZED);
+        return Err(Error::PinRequiredError);
+    }
+
     let db = state.db.lock().await;
     let mut db_trans = match db.get_conn_transaction().await {
         Ok(value) => value,
@@ -158,16 +200,41 @@ pub async fn create_transaction(
         _ => return Err(Error::new("Invalid state")),
     };
 
-    let transaction_id = Transaction::create(
+    let transaction_id = match IdempotencyKey::book_once(
         &mut *db_trans,
-        sender_group_id,
-        receiver_group_id,
-        transaction_type,
-        None,
-        money,
-        &state.settings,
+        user_id,
+        idempotency_key.as_deref(),
+        &(transaction_type, money.value),
+        state.settings.idempotency.window,
+        async |conn| {
+            Transaction::create(
+                conn,
+                sender_group_id,
+                receiver_group_id,
+                transaction_type,
+                None,
+                money,
+                &state.settings,
+            )
+            .await
+        },
     )
-    .await?;
+    .await
+    {
+        Ok(value) => value,
+        Err(BookOnceError::KeyReused) => {
+            response_opts.set_status(StatusCode::CONFLICT);
+            return Err(Error::new(
+                "The idempotency key was already used for another request!",
+            ));
+        }
+        Err(BookOnceError::Database(e)) => {
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            error!("Failed to look up or store idempotency key: {}", e);
+            return Err(Error::new("Failed to check idempotency key!"));
+        }
+        Err(BookOnceError::Booking(e)) => return Err(e),
+    };
 
     let transaction = match Transaction::get(&mut *db_trans, transaction_id, user_id).await {
         Ok(val) => val,
@@ -187,6 +254,24 @@ pub async fn create_transaction(
         }
     };
 
+    let mut transaction = transaction;
+
+    match Transaction::set_undo_deadlines(
+        &mut *db_trans,
+        user_id,
+        &state.settings.undo,
+        std::slice::from_mut(&mut transaction),
+    )
+    .await
+    {
+        Ok(_) => {}
+        Err(e) => {
+            response_opts.set_status(StatusCode::INTERNAL_SERVER_ERROR);
+            error!("Failed to check undo policy: {}", e);
+            return Err(Error::new("Failed to check undo policy!"));
+        }
+    }
+
     match db_trans.commit().await {
         Ok(_) => {}
         Err(e) => {
@@ -217,24 +302,33 @@ pub async fn get_item_sound_url(audio: AudioPlayback) -> Result<Vec<u8>, ServerF
     let sounds = &state.settings.sounds;
 
     let file = match audio {
-        AudioPlayback::Failed => choose_random_item(&sounds.failed),
-        AudioPlayback::Undo => choose_random_item(&sounds.generic),
-        AudioPlayback::Deposit(_) => choose_random_item(&sounds.generic),
-        AudioPlayback::Sent(_) => choose_random_item(&sounds.generic),
-        AudioPlayback::Withdraw(_) => choose_random_item(&sounds.generic),
+        AudioPlayback::Failed => choose_random_item(&sounds.failed).cloned(),
+        AudioPlayback::Undo => choose_random_item(&sounds.generic).cloned(),
+        AudioPlayback::Deposit(_) => choose_random_item(&sounds.generic).cloned(),
+        AudioPlayback::Sent(_) => choose_random_item(&sounds.generic).cloned(),
+        AudioPlayback::Withdraw(_) => choose_random_item(&sounds.generic).cloned(),
         AudioPlayback::Bought(article_id) => {
             let article = get_article(article_id).await?;
 
-            let sounds = match sounds.articles.get(&article.name) {
-                Some(sounds) => sounds,
-                None => &sounds.generic,
-            };
-
-            choose_random_item(sounds)
+            // sounds assigned on the edit page win over the ones configured
+            // by article name
+            let uploaded = article
+                .sounds
+                .into_iter()
+                .map(|sound| sound.path)
+                .collect::<Vec<_>>();
+
+            match uploaded.is_empty() {
+                false => choose_random_item(&uploaded).cloned(),
+                true => match sounds.articles.get(&article.name) {
+                    Some(sounds) => choose_random_item(sounds).cloned(),
+                    None => choose_random_item(&sounds.generic).cloned(),
+                },
+            }
         }
     };
 
-    let path = PathBuf::from_str(match file {
+    let path = PathBuf::from_str(match &file {
         Some(val) => val,
         None => {
             error!("Failed to choose a random sound file");
@@ -363,12 +457,14 @@ pub fn ShowUser() -> impl IntoView {
                                 let args = Rc::new(m_args);
                                 let custom_money_change = RwSignal::new(String::new());
                                 let custom_money_is_focused = RwSignal::new(false);
+                                let basket = Basket::new();
 
                                 view! {
                                     {invisible_scan_input(
                                         custom_money_is_focused,
                                         error_signal,
                                         args.clone(),
+                                        basket,
                                     )}
                                     <div class="grid grid-cols-2">
                                         <div class="pt-5">
@@ -378,6 +474,18 @@ pub fn ShowUser() -> impl IntoView {
                                                     <p class="text-center text-white text-[2em]">
                                                         {user.nickname.clone()}
                                                     </p>
+                                                    {user
+                                                        .has_pin
+                                                        .then(|| view! { <PinLock user_id /> })}
+                                                    {user
+                                                        .is_archived
+                                                        .then(|| {
+                                                            view! {
+                                                                <p class="text-center text-white bg-red-400 rounded">
+                                                                    "This account is archived"
+                                                                </p>
+                                                            }
+                                                        })}
                                                     <p
                                                         class="text-center text-[2em]"
                                                         class=(
@@ -430,10 +538,31 @@ pub fn ShowUser() -> impl IntoView {
                                                             </svg>
                                                             <p class="text-center">"Send money"</p>
                                                         </a>
+                                                        <a
+                                                            href=format!("/user/{}/request_money", user_id)
+                                                            class="text-white w-[3rem] flex flex-col items-center"
+                                                        >
+                                                            <svg
+                                                                viewBox="0 0 24 24"
+                                                                fill="none"
+                                                                xmlns="http://www.w3.org/2000/svg"
+                                                            >
+                                                                <path
+                                                                    d="M12 3V15M12 15L8 11M12 15L16 11M4 17V19C4 20.1046 4.89543 21 6 21H18C19.1046 21 20 20.1046 20 19V17"
+                                                                    stroke="#ffffff"
+                                                                    stroke-width="2"
+                                                                    stroke-linecap="round"
+                                                                    stroke-linejoin="round"
+                                                                ></path>
+                                                            </svg>
+                                                            <p class="text-center">"Request money"</p>
+                                                        </a>
                                                     </div>
                                                 </div>
                                             </div>
-                                            <BuyArticle args=args.clone() />
+                                            <BuyArticle args=args.clone() basket />
+                                            <ShowBasket args=args.clone() basket />
+                                            <MoneyRequests args=args.clone() />
                                         </div>
                                         <div>
                                             // right side (put in money)
@@ -491,6 +620,7 @@ pub fn ShowUser() -> impl IntoView {
 
                                                 </div>
                                             </div>
+                                            <TopUpQrCode user_id=args.user_id />
                                         </div>
                                     </div>
                                     <div class="pt-5">
@@ -571,7 +701,14 @@ fn change_money(money: Money, args: Rc<MoneyArgs>) {
             TransactionType::Withdraw
         };
 
-        match create_transaction(args.user_id, fixed_money, t_type).await {
+        match create_transaction(
+            args.user_id,
+            fixed_money,
+            t_type,
+            Some(new_idempotency_key()),
+        )
+        .await
+        {
             Ok(transaction) => {
                 args.money
                     .update(|money_struct| money_struct.value += money.value);
@@ -585,17 +722,39 @@ fn change_money(money: Money, args: Rc<MoneyArgs>) {
                         TransactionType::Withdraw => AudioPlayback::Withdraw(transaction.money),
                         TransactionType::Received(_) => return,
                         TransactionType::SentAndReceived(_) => return,
+                        TransactionType::Correction(_) => return,
                         TransactionType::Sent(_) => AudioPlayback::Sent(transaction.money),
                     },
                 );
             }
             Err(e) => {
                 let msg = match e {
-                    CreateTransactionError::TooLittleMoneyError(_) => {
-                        "You have too little money!".to_string()
+                    CreateTransactionError::TooLittleMoneyError(users) => match users.first() {
+                        Some((_, limit)) => {
+                            format!(
+                                "You have too little money! Your limit is {}.",
+                                limit.format_eur(),
+                            )
+                        }
+                        None => "You have too little money!".to_string(),
+                    },
+                    CreateTransactionError::TooMuchMoneyError(users) => match users.first() {
+                        Some((_, limit)) => {
+                            format!(
+                                "You have too much money! Your limit is {}.",
+                                limit.format_eur(),
+                            )
+                        }
+                        None => "You have too much money!".to_string(),
+                    },
+                    CreateTransactionError::ArchivedUserError(_) => {
+                        "This account is archived!".to_string()
+                    }
+                    CreateTransactionError::PinRequiredError => {
+                        "Please enter your PIN first!".to_string()
                     }
-                    CreateTransactionError::TooMuchMoneyError(_) => {
-                        "You have too much money!".to_string()
+                    CreateTransactionError::OutOfStockError(article, stock) => {
+                        format!("Only {} of {} left in stock!", stock, article)
                     }
                     CreateTransactionError::StringMessage(msg) => msg,
                     CreateTransactionError::ServerFn(server_fn) => server_fn.to_string(),